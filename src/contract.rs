@@ -0,0 +1,882 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/contract.rs — XLS-101 smart contract entry points
+// ═══════════════════════════════════════════════════════════════════════
+//
+// The escrow build has one host-driven entry point, finish(). As an
+// XLS-101 smart contract the same rules are exposed as named functions
+// called with ContractCall, each taking typed parameters:
+//
+//   approve(proposal: u32) — record the caller's approval; a non-zero
+//                            proposal id votes on a governance proposal
+//   revoke()               — withdraw the caller's approval
+//   release(amount: u64)   — pay the configured `amount` drops to
+//                            `destination` once the release conditions
+//                            hold; the parameter must match it
+//   freeze(on: u8)         — 1 freezes, 0 unfreezes (roles in `freeze_by`)
+//   status()               — (state << 8) | approval_count, never negative
+//   propose_upgrade(code_hash: Hash256), vote_upgrade(code_hash: Hash256),
+//   veto_upgrade(), apply_upgrade(code_hash: Hash256)
+//                          — code upgrade governance (see src/upgrade.rs);
+//                            apply_upgrade records an authorized upgrade
+//                            as the active code
+//
+// Build with `--features smart-contract`; the escrow exports are then
+// left out. The handlers below are written against ContractHost so they
+// run natively in tests; only the wasm glue at the bottom talks to the
+// host.
+//
+// Extra keys for the contract build:
+//   destination — 40-hex AccountID paid by release() (a contract has no
+//                 Destination field)
+//   amount      — drops paid by release() (nor an Amount field)
+//   freeze_by   — roles that may freeze/unfreeze: owner, destination,
+//                 notary, any (default: owner)
+//
+// The binding keys work as in the escrow build: release() checks `owner`
+// against the contract's Owner and `max_amount` against the amount it
+// pays, so a notary_exclude that names the owner can't be sidestepped
+// with a wrong `owner` key. A contract payment carries no tags, so
+// destination_tag and source_tag are rejected.
+//
+// Conditions that read other ledger objects or the transaction's memos
+// (oracle_*, credential_*, hashlock, depends_on, notaries_from, policy),
+// amount tiers, party consent, destination acks and commit-reveal approvals
+// (commit_deadline) are not available here; configuring them is rejected
+// with ERR_BAD_CONFIG rather than silently ignored.
+//
+// Deputies (deputy_N) are honoured by approve() and revoke(), but there
+// is no set_deputy here: they come from the initial data.
+// ═══════════════════════════════════════════════════════════════════════
+
+use crate::upgrade::{
+    check_upgrade_authorized, check_veto_submitter, record_upgrade_applied, record_upgrade_proposal,
+    record_upgrade_veto, record_upgrade_vote, CODE_HASH_SIZE,
+};
+use crate::{
+    apply_due_proposal, apply_inactivity_switch, check_caller_is_notary, check_challenge_period, check_config_binding,
+    check_finish_submitter, check_listed_notary, check_tiered_threshold, decode_hex, find_value, parse_finish_by,
+    parse_u64, parse_u8_digit, record_approval, record_audit, record_proposal_vote, record_revocation, record_transition,
+    recorded_state, required_threshold, EscrowFields, EscrowState, LifecycleEvent, Tally, ACCOUNT_ID_SIZE,
+    ERR_BAD_CONFIG, ERR_WRONG_ACCOUNT, FINISH_BY_ANY, FINISH_BY_DESTINATION, FINISH_BY_NOTARY,
+    FINISH_BY_OWNER, SUCCESS,
+};
+
+/// Keys whose conditions need host reads the contract build doesn't do.
+const UNSUPPORTED_KEYS: [&[u8]; 12] = [
+    b"oracle_owner",
+    b"credential_issuer",
+    b"hashlock",
+    b"depends_on",
+    b"tier_0",
+    b"notaries_from",
+    b"policy",
+    b"require_consent",
+    b"require_destination_ack",
+    b"commit_deadline",
+    b"destination_tag",
+    b"source_tag",
+];
+
+/// What the handlers need from the XLS-101 host.
+pub(crate) trait ContractHost {
+    /// Account that submitted the ContractCall.
+    fn caller(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32>;
+    /// Owner of the contract.
+    fn owner(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32>;
+    /// Sequence number of the ledger currently being built.
+    fn ledger_sequence(&self) -> Result<u32, i32>;
+    /// Copy the contract's data into `out`, returning its length.
+    fn read_data(&self, out: &mut [u8]) -> Result<usize, i32>;
+    /// Replace the contract's data.
+    fn write_data(&mut self, data: &[u8]) -> i32;
+    /// Pay `amount_drops` of XRP from the contract to `destination`.
+    fn pay(&mut self, destination: &[u8; ACCOUNT_ID_SIZE], amount_drops: u64) -> i32;
+}
+
+/// Contract data with any template expanded, the dead-man switch and any
+/// due governance change applied — the same preamble every escrow entry point runs.
+struct Loaded {
+    data: [u8; 4096],
+    len: usize,
+    caller: [u8; ACCOUNT_ID_SIZE],
+    current_ledger: u32,
+}
+
+impl Loaded {
+    fn data(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+fn load(host: &impl ContractHost) -> Result<Loaded, i32> {
+    let caller = host.caller()?;
+    let current_ledger = host.ledger_sequence()?;
+
+    let mut data = [0u8; 4096];
+    let mut len = host.read_data(&mut data)?.min(data.len());
+
+    if let Some((d, l)) = crate::template::expand_template(&data[..len], len)? {
+        data = d;
+        len = l;
+    }
+    if let Some((d, l)) = apply_inactivity_switch(&data[..len], len, current_ledger)? {
+        data = d;
+        len = l;
+    }
+    if let Some((d, l)) = apply_due_proposal(&data[..len], len, None, current_ledger)? {
+        data = d;
+        len = l;
+    }
+    Ok(Loaded { data, len, caller, current_ledger })
+}
+
+/// The configured payee of release().
+fn destination(data: &[u8]) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+    let hex = find_value(data, b"destination").ok_or(ERR_BAD_CONFIG)?;
+    let mut account = [0u8; ACCOUNT_ID_SIZE];
+    if hex.len() != ACCOUNT_ID_SIZE * 2 || decode_hex(hex, &mut account).is_none() {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(account)
+}
+
+/// The configured payment of release().
+fn amount(data: &[u8]) -> Result<u64, i32> {
+    find_value(data, b"amount").and_then(parse_u64).ok_or(ERR_BAD_CONFIG)
+}
+
+/// Reject configuration this build can't enforce.
+fn check_supported(data: &[u8]) -> Result<(), i32> {
+    if UNSUPPORTED_KEYS.iter().any(|k| find_value(data, k).is_some()) {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(())
+}
+
+/// approve(proposal: u32)
+pub(crate) fn approve(host: &mut impl ContractHost, proposal: u32) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    if let Err(code) = check_supported(data) {
+        return code;
+    }
+    let notary_index = match check_caller_is_notary(data, &loaded.caller, loaded.current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+
+    if proposal != 0 {
+        let (voted, voted_len) = match record_proposal_vote(data, loaded.len, proposal, notary_index, loaded.current_ledger) {
+            Ok(r) => r,
+            Err(code) => return code,
+        };
+        return match apply_due_proposal(&voted[..voted_len], voted_len, None, loaded.current_ledger) {
+            Ok(Some((applied, applied_len))) => host.write_data(&applied[..applied_len]),
+            Ok(None) => host.write_data(&voted[..voted_len]),
+            Err(code) => code,
+        };
+    }
+
+    let threshold = match required_threshold(data, None, loaded.current_ledger) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+    // ContractCall has no Sequence-bearing EscrowFinish — the ledger stands in
+    let (approved, approved_len) = match record_approval(
+        data, loaded.len, notary_index, &loaded.caller, loaded.current_ledger, loaded.current_ledger, threshold,
+    ) {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let tally = Tally::of(&approved[..approved_len], threshold);
+    match record_transition(&approved[..approved_len], approved_len, LifecycleEvent::Approve(tally)) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// revoke()
+pub(crate) fn revoke(host: &mut impl ContractHost) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let notary_index = match check_caller_is_notary(data, &loaded.caller, loaded.current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+    let threshold = match required_threshold(data, None, loaded.current_ledger) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+
+    let (revoked, revoked_len) = match record_revocation(data, loaded.len, notary_index, loaded.current_ledger, threshold) {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let tally = Tally::of(&revoked[..revoked_len], threshold);
+    match record_transition(&revoked[..revoked_len], revoked_len, LifecycleEvent::Revoke(tally)) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// release(amount: u64)
+///
+/// Pays the configured `amount`; a caller-chosen `amount_drops` that
+/// differs from it is refused with ERR_BAD_CONFIG.
+///
+/// Same order as finish(): lifecycle, config binding, submitter, threshold,
+/// challenge period. Denials are written to the audit fields like finish()
+/// does.
+pub(crate) fn release(host: &mut impl ContractHost, amount_drops: u64) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let current_ledger = loaded.current_ledger;
+
+    if let Err(code) = check_supported(data) {
+        return code;
+    }
+    let (released, released_len) = match record_transition(data, loaded.len, LifecycleEvent::Release) {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+    let (owner, destination) = match host.owner().and_then(|o| destination(data).map(|d| (o, d))) {
+        Ok(parties) => parties,
+        Err(code) => return code,
+    };
+    match amount(data) {
+        Ok(configured) if configured == amount_drops => {}
+        Ok(_) => return ERR_BAD_CONFIG,
+        Err(code) => return code,
+    }
+    let bound = EscrowFields {
+        owner,
+        destination,
+        destination_tag: None,
+        source_tag: None,
+        amount_drops: Some(amount_drops),
+    };
+    let binding = check_config_binding(data, &bound);
+    if binding != SUCCESS {
+        return binding;
+    }
+    if let Err(code) = check_finish_submitter(data, &loaded.caller, &owner, &destination) {
+        return code;
+    }
+
+    let mut result = check_tiered_threshold(data, None, current_ledger);
+    if result == SUCCESS {
+        result = check_challenge_period(data, current_ledger);
+    }
+    if result != SUCCESS {
+        let (audit, audit_len) = record_audit(data, loaded.len, result, current_ledger);
+        let _ = host.write_data(&audit[..audit_len]);
+        return result;
+    }
+
+    let paid = host.pay(&destination, amount_drops);
+    if paid != SUCCESS {
+        return paid;
+    }
+    let (audit, audit_len) = record_audit(&released[..released_len], released_len, SUCCESS, current_ledger);
+    let written = host.write_data(&audit[..audit_len]);
+    if written != SUCCESS {
+        return written;
+    }
+    SUCCESS
+}
+
+/// Verify that the caller may freeze or unfreeze (default: owner only).
+fn check_freeze_submitter(
+    data: &[u8],
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    let roles = match find_value(data, b"freeze_by") {
+        Some(value) => parse_finish_by(value).ok_or(ERR_BAD_CONFIG)?,
+        None => FINISH_BY_OWNER,
+    };
+
+    if roles & FINISH_BY_ANY != 0 || (roles & FINISH_BY_OWNER != 0 && caller == owner) {
+        return Ok(());
+    }
+    if roles & FINISH_BY_DESTINATION != 0 && destination(data)? == *caller {
+        return Ok(());
+    }
+    if roles & FINISH_BY_NOTARY != 0 {
+        return check_listed_notary(data, caller).map(|_| ());
+    }
+    Err(ERR_WRONG_ACCOUNT)
+}
+
+/// freeze(on: u8)
+pub(crate) fn freeze(host: &mut impl ContractHost, on: u8) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let owner = match host.owner() {
+        Ok(o) => o,
+        Err(code) => return code,
+    };
+    if let Err(code) = check_freeze_submitter(data, &loaded.caller, &owner) {
+        return code;
+    }
+
+    let event = match on {
+        1 => LifecycleEvent::Freeze,
+        0 => match required_threshold(data, None, loaded.current_ledger) {
+            Ok(threshold) => LifecycleEvent::Unfreeze(Tally::of(data, threshold)),
+            Err(code) => return code,
+        },
+        _ => return ERR_BAD_CONFIG,
+    };
+    match record_transition(data, loaded.len, event) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// propose_upgrade(code_hash: Hash256)
+pub(crate) fn propose_upgrade(host: &mut impl ContractHost, code_hash: &[u8; CODE_HASH_SIZE]) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let notary_index = match check_caller_is_notary(data, &loaded.caller, loaded.current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+    match record_upgrade_proposal(data, loaded.len, code_hash, notary_index, loaded.current_ledger) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// vote_upgrade(code_hash: Hash256)
+pub(crate) fn vote_upgrade(host: &mut impl ContractHost, code_hash: &[u8; CODE_HASH_SIZE]) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let notary_index = match check_caller_is_notary(data, &loaded.caller, loaded.current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+    match record_upgrade_vote(data, loaded.len, code_hash, notary_index, loaded.current_ledger) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// veto_upgrade()
+pub(crate) fn veto_upgrade(host: &mut impl ContractHost) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let (owner, destination) = match host.owner().and_then(|o| destination(data).map(|d| (o, d))) {
+        Ok(parties) => parties,
+        Err(code) => return code,
+    };
+    if let Err(code) = check_veto_submitter(data, &loaded.caller, &owner, &destination) {
+        return code;
+    }
+    match record_upgrade_veto(data, loaded.len, loaded.current_ledger) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// apply_upgrade(code_hash: Hash256)
+///
+/// Anyone may record the pending upgrade as applied once it is authorized;
+/// naming the hash keeps a stale call from applying a different one.
+pub(crate) fn apply_upgrade(host: &mut impl ContractHost, code_hash: &[u8; CODE_HASH_SIZE]) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let authorized = check_upgrade_authorized(data, code_hash, loaded.current_ledger);
+    if authorized != SUCCESS {
+        return authorized;
+    }
+    match record_upgrade_applied(data, loaded.len, loaded.current_ledger) {
+        Ok((d, l)) => host.write_data(&d[..l]),
+        Err(code) => code,
+    }
+}
+
+/// status() — read-only: (state << 8) | approval_count.
+pub(crate) fn status(host: &impl ContractHost) -> i32 {
+    let loaded = match load(host) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = loaded.data();
+    let state = match recorded_state(data) {
+        Ok(s) => s,
+        Err(code) => return code,
+    };
+    let code = match state {
+        EscrowState::Configured => 0,
+        EscrowState::Collecting => 1,
+        EscrowState::Quorum => 2,
+        EscrowState::Released => 3,
+        EscrowState::Frozen => 4,
+        EscrowState::Aborted => 5,
+    };
+    let approvals = find_value(data, b"approval_count")
+        .and_then(parse_u8_digit)
+        .unwrap_or(0);
+    (code << 8) | approvals as i32
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// WASM GLUE — XLS-101 exports (feature = "smart-contract")
+//
+// xrpl-wasm-stdlib 0.7 wraps the XLS-100 host functions only. Reading
+// ContractCall parameters and paying from the contract account use the
+// XLS-101 draft host functions, declared here until the stdlib wraps them.
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(all(target_arch = "wasm32", feature = "smart-contract"))]
+mod wasm {
+    use super::ContractHost;
+    use crate::upgrade::CODE_HASH_SIZE;
+    use crate::{ACCOUNT_ID_SIZE, ERR_DATA_READ, ERR_HOST_CALL, SUCCESS};
+    use xrpl_wasm_stdlib::host;
+    use xrpl_wasm_stdlib::sfield;
+
+    /// STI type codes of the parameters we read
+    const STI_UINT8: i32 = 16;
+    const STI_UINT32: i32 = 2;
+    const STI_UINT64: i32 = 3;
+    const STI_UINT256: i32 = 5;
+
+    #[link(wasm_import_module = "host_lib")]
+    extern "C" {
+        /// Copy ContractCall parameter `index`, which must be of `sti_type`, into `out`.
+        fn function_param(index: i32, sti_type: i32, out: *mut u8, out_len: usize) -> i32;
+        /// Emit an XRP Payment of `drops` from the contract's account.
+        fn emit_xrp_payment(destination: *const u8, destination_len: usize, drops: u64) -> i32;
+    }
+
+    struct XrplContractHost;
+
+    fn read_account(read: impl FnOnce(*mut u8, usize) -> i32) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+        let mut account = [0u8; ACCOUNT_ID_SIZE];
+        if read(account.as_mut_ptr(), account.len()) != ACCOUNT_ID_SIZE as i32 {
+            return Err(ERR_HOST_CALL);
+        }
+        Ok(account)
+    }
+
+    impl ContractHost for XrplContractHost {
+        fn caller(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+            read_account(|ptr, len| unsafe { host::get_tx_field(sfield::Account, ptr, len) })
+        }
+
+        fn owner(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+            read_account(|ptr, len| unsafe { host::get_current_ledger_obj_field(sfield::Owner, ptr, len) })
+        }
+
+        fn ledger_sequence(&self) -> Result<u32, i32> {
+            crate::current_ledger_sequence()
+        }
+
+        fn read_data(&self, out: &mut [u8]) -> Result<usize, i32> {
+            let len = unsafe { host::get_current_ledger_obj_field(sfield::Data, out.as_mut_ptr(), out.len()) };
+            if len < 0 {
+                return Err(ERR_DATA_READ);
+            }
+            Ok(len as usize)
+        }
+
+        fn write_data(&mut self, data: &[u8]) -> i32 {
+            if unsafe { host::update_data(data.as_ptr(), data.len()) } < 0 {
+                return ERR_HOST_CALL;
+            }
+            SUCCESS
+        }
+
+        fn pay(&mut self, destination: &[u8; ACCOUNT_ID_SIZE], amount_drops: u64) -> i32 {
+            if unsafe { emit_xrp_payment(destination.as_ptr(), destination.len(), amount_drops) } < 0 {
+                return ERR_HOST_CALL;
+            }
+            SUCCESS
+        }
+    }
+
+    fn param<const N: usize>(index: i32, sti_type: i32) -> Result<[u8; N], i32> {
+        let mut buf = [0u8; N];
+        if unsafe { function_param(index, sti_type, buf.as_mut_ptr(), N) } != N as i32 {
+            return Err(ERR_HOST_CALL);
+        }
+        Ok(buf)
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn approve() -> i32 {
+        match param::<4>(0, STI_UINT32) {
+            Ok(p) => super::approve(&mut XrplContractHost, u32::from_le_bytes(p)),
+            Err(code) => code,
+        }
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn revoke() -> i32 {
+        super::revoke(&mut XrplContractHost)
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn release() -> i32 {
+        match param::<8>(0, STI_UINT64) {
+            Ok(p) => super::release(&mut XrplContractHost, u64::from_le_bytes(p)),
+            Err(code) => code,
+        }
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn freeze() -> i32 {
+        match param::<1>(0, STI_UINT8) {
+            Ok([on]) => super::freeze(&mut XrplContractHost, on),
+            Err(code) => code,
+        }
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn status() -> i32 {
+        super::status(&XrplContractHost)
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn propose_upgrade() -> i32 {
+        match param::<CODE_HASH_SIZE>(0, STI_UINT256) {
+            Ok(hash) => super::propose_upgrade(&mut XrplContractHost, &hash),
+            Err(code) => code,
+        }
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn vote_upgrade() -> i32 {
+        match param::<CODE_HASH_SIZE>(0, STI_UINT256) {
+            Ok(hash) => super::vote_upgrade(&mut XrplContractHost, &hash),
+            Err(code) => code,
+        }
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn veto_upgrade() -> i32 {
+        super::veto_upgrade(&mut XrplContractHost)
+    }
+
+    #[unsafe(no_mangle)]
+    pub extern "C" fn apply_upgrade() -> i32 {
+        match param::<CODE_HASH_SIZE>(0, STI_UINT256) {
+            Ok(hash) => super::apply_upgrade(&mut XrplContractHost, &hash),
+            Err(code) => code,
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — handlers against an in-memory host
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_hex, ERR_ILLEGAL_TRANSITION, ERR_NOT_APPROVED, ERR_TOO_EARLY};
+
+    const OWNER: [u8; ACCOUNT_ID_SIZE] = [0x10; ACCOUNT_ID_SIZE];
+    const DESTINATION: [u8; ACCOUNT_ID_SIZE] = [0x20; ACCOUNT_ID_SIZE];
+    const NOTARIES: [[u8; ACCOUNT_ID_SIZE]; 3] = [[0x01; ACCOUNT_ID_SIZE], [0x02; ACCOUNT_ID_SIZE], [0x03; ACCOUNT_ID_SIZE]];
+
+    struct MockHost {
+        caller: [u8; ACCOUNT_ID_SIZE],
+        ledger: u32,
+        data: Vec<u8>,
+        payments: Vec<([u8; ACCOUNT_ID_SIZE], u64)>,
+    }
+
+    impl ContractHost for MockHost {
+        fn caller(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+            Ok(self.caller)
+        }
+        fn owner(&self) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+            Ok(OWNER)
+        }
+        fn ledger_sequence(&self) -> Result<u32, i32> {
+            Ok(self.ledger)
+        }
+        fn read_data(&self, out: &mut [u8]) -> Result<usize, i32> {
+            out[..self.data.len()].copy_from_slice(&self.data);
+            Ok(self.data.len())
+        }
+        fn write_data(&mut self, data: &[u8]) -> i32 {
+            self.data = data.to_vec();
+            SUCCESS
+        }
+        fn pay(&mut self, destination: &[u8; ACCOUNT_ID_SIZE], amount_drops: u64) -> i32 {
+            self.payments.push((*destination, amount_drops));
+            SUCCESS
+        }
+    }
+
+    fn hex(account: &[u8; ACCOUNT_ID_SIZE]) -> [u8; ACCOUNT_ID_SIZE * 2] {
+        let mut out = [0u8; ACCOUNT_ID_SIZE * 2];
+        encode_hex(account, &mut out);
+        out
+    }
+
+    const AMOUNT: u64 = 5_000_000;
+
+    /// 2-of-3 contract paying AMOUNT drops to DESTINATION.
+    fn host(extra: &[u8]) -> MockHost {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"notary_count=3;threshold=2");
+        for (i, notary) in NOTARIES.iter().enumerate() {
+            data.extend_from_slice(b";notary_");
+            data.push(b'0' + i as u8);
+            data.push(b'=');
+            data.extend_from_slice(&hex(notary));
+        }
+        data.extend_from_slice(b";destination=");
+        data.extend_from_slice(&hex(&DESTINATION));
+        data.extend_from_slice(b";amount=5000000");
+        data.extend_from_slice(extra);
+        MockHost { caller: NOTARIES[0], ledger: 100, data, payments: Vec::new() }
+    }
+
+    fn value<'a>(host: &'a MockHost, key: &[u8]) -> Option<&'a [u8]> {
+        find_value(&host.data, key)
+    }
+
+    fn approve_as(host: &mut MockHost, notary: usize) -> i32 {
+        host.caller = NOTARIES[notary];
+        approve(host, 0)
+    }
+
+    #[test]
+    fn approve_release_flow() {
+        let mut h = host(b"");
+        assert_eq!(approve_as(&mut h, 0), SUCCESS);
+        assert_eq!(value(&h, b"state"), Some(b"collecting" as &[u8]));
+        assert_eq!(status(&h), (1 << 8) | 1);
+
+        h.caller = NOTARIES[1];
+        assert_eq!(release(&mut h, AMOUNT), ERR_NOT_APPROVED);
+        assert_eq!(value(&h, b"last_result"), Some(b"not_approved" as &[u8]));
+        assert!(h.payments.is_empty());
+
+        assert_eq!(approve_as(&mut h, 1), SUCCESS);
+        assert_eq!(status(&h), (2 << 8) | 2);
+        assert_eq!(release(&mut h, AMOUNT), SUCCESS);
+        assert_eq!(h.payments, vec![(DESTINATION, AMOUNT)]);
+        assert_eq!(value(&h, b"state"), Some(b"released" as &[u8]));
+        assert_eq!(value(&h, b"last_result"), Some(b"approved" as &[u8]));
+        assert_eq!(status(&h) >> 8, 3);
+
+        // Released is terminal
+        assert_eq!(release(&mut h, AMOUNT), ERR_ILLEGAL_TRANSITION);
+        assert_eq!(h.payments.len(), 1);
+    }
+
+    #[test]
+    fn revoke_moves_back_to_collecting() {
+        let mut h = host(b"");
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 2);
+        h.caller = NOTARIES[2];
+        assert_eq!(revoke(&mut h), SUCCESS);
+        assert_eq!(value(&h, b"state"), Some(b"collecting" as &[u8]));
+        assert_eq!(value(&h, b"approval_count"), Some(b"1" as &[u8]));
+    }
+
+    #[test]
+    fn only_notaries_approve() {
+        let mut h = host(b"");
+        h.caller = OWNER;
+        assert_eq!(approve(&mut h, 0), ERR_WRONG_ACCOUNT);
+        assert_eq!(revoke(&mut h), ERR_WRONG_ACCOUNT);
+    }
+
+    #[test]
+    fn release_honours_finish_by_and_challenge_period() {
+        let mut h = host(b";finish_by=destination;challenge_ledgers=10");
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+
+        h.caller = NOTARIES[0];
+        assert_eq!(release(&mut h, AMOUNT), ERR_WRONG_ACCOUNT);
+
+        h.caller = DESTINATION;
+        assert_eq!(release(&mut h, AMOUNT), ERR_TOO_EARLY);
+        h.ledger = 110;
+        assert_eq!(release(&mut h, AMOUNT), SUCCESS);
+    }
+
+    #[test]
+    fn freeze_blocks_everything_until_unfrozen() {
+        let mut h = host(b"");
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+
+        h.caller = NOTARIES[0];
+        assert_eq!(freeze(&mut h, 1), ERR_WRONG_ACCOUNT);
+        h.caller = OWNER;
+        assert_eq!(freeze(&mut h, 1), SUCCESS);
+        assert_eq!(status(&h) >> 8, 4);
+
+        assert_eq!(approve_as(&mut h, 2), ERR_ILLEGAL_TRANSITION);
+        h.caller = NOTARIES[0];
+        assert_eq!(release(&mut h, AMOUNT), ERR_ILLEGAL_TRANSITION);
+        assert!(h.payments.is_empty());
+
+        // Unfreezing resumes where the tally stands
+        h.caller = OWNER;
+        assert_eq!(freeze(&mut h, 0), SUCCESS);
+        assert_eq!(value(&h, b"state"), Some(b"quorum" as &[u8]));
+        assert_eq!(freeze(&mut h, 0), ERR_ILLEGAL_TRANSITION);
+        assert_eq!(freeze(&mut h, 2), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn freeze_by_notary() {
+        let mut h = host(b";freeze_by=notary");
+        h.caller = OWNER;
+        assert_eq!(freeze(&mut h, 1), ERR_WRONG_ACCOUNT);
+        h.caller = NOTARIES[2];
+        assert_eq!(freeze(&mut h, 1), SUCCESS);
+    }
+
+    #[test]
+    fn approve_with_proposal_votes() {
+        let mut h = host(
+            b";gov_threshold=2;gov_delay_ledgers=0;prop_id=4;prop_key=threshold;prop_value=3;prop_vote_0=1;prop_votes=1",
+        );
+        h.caller = NOTARIES[1];
+        assert_eq!(approve(&mut h, 5), crate::ERR_PROPOSAL);
+        assert_eq!(approve(&mut h, 4), SUCCESS);
+        // No delay: applied by the deciding vote, no release approval recorded
+        assert_eq!(value(&h, b"threshold"), Some(b"3" as &[u8]));
+        assert_eq!(value(&h, b"approval_count"), None);
+    }
+
+    #[test]
+    fn host_dependent_conditions_are_rejected() {
+        for extra in [
+            b";hashlock=00" as &[u8],
+            b";depends_on=00:1",
+            b";oracle_owner=00",
+            b";credential_issuer=00",
+            b";tier_0=0:1",
+            b";destination_tag=7",
+            b";source_tag=7",
+        ] {
+            let mut h = host(extra);
+            assert_eq!(approve(&mut h, 0), ERR_BAD_CONFIG);
+            assert_eq!(release(&mut h, AMOUNT), ERR_BAD_CONFIG);
+        }
+    }
+
+    #[test]
+    fn release_checks_owner_and_max_amount() {
+        let mut h = host(b";max_amount=4999999;owner=");
+        h.data.extend_from_slice(&hex(&OWNER));
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT), ERR_BAD_CONFIG);
+
+        let mut h = host(b";max_amount=5000000;owner=");
+        h.data.extend_from_slice(&hex(&OWNER));
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT), SUCCESS);
+        assert_eq!(h.payments, vec![(DESTINATION, AMOUNT)]);
+
+        let mut h = host(b";owner=");
+        h.data.extend_from_slice(&hex(&NOTARIES[2]));
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT), ERR_BAD_CONFIG);
+        assert!(h.payments.is_empty());
+    }
+
+    #[test]
+    fn release_pays_the_configured_amount() {
+        let mut h = host(b"");
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT + 1), ERR_BAD_CONFIG);
+        assert_eq!(release(&mut h, AMOUNT - 1), ERR_BAD_CONFIG);
+        assert!(h.payments.is_empty());
+        assert_eq!(release(&mut h, AMOUNT), SUCCESS);
+        assert_eq!(h.payments, vec![(DESTINATION, AMOUNT)]);
+
+        let mut h = host(b"");
+        let start = h.data.windows(8).position(|w| w == b";amount=").unwrap();
+        h.data.truncate(start);
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT), ERR_BAD_CONFIG);
+    }
+
+    const CODE_HASH: [u8; CODE_HASH_SIZE] = [0xAA; CODE_HASH_SIZE];
+
+    #[test]
+    fn upgrade_flow() {
+        let mut h = host(b";upgrade_threshold=2;upgrade_delay_ledgers=50");
+        h.caller = OWNER;
+        assert_eq!(propose_upgrade(&mut h, &CODE_HASH), ERR_WRONG_ACCOUNT);
+
+        h.caller = NOTARIES[0];
+        assert_eq!(propose_upgrade(&mut h, &CODE_HASH), SUCCESS);
+        assert_eq!(apply_upgrade(&mut h, &CODE_HASH), ERR_NOT_APPROVED);
+        h.caller = NOTARIES[1];
+        assert_eq!(vote_upgrade(&mut h, &[0xBB; CODE_HASH_SIZE]), crate::ERR_UPGRADE);
+        assert_eq!(vote_upgrade(&mut h, &CODE_HASH), SUCCESS);
+        assert_eq!(apply_upgrade(&mut h, &CODE_HASH), ERR_TOO_EARLY);
+
+        h.ledger = 150;
+        h.caller = DESTINATION;
+        assert_eq!(apply_upgrade(&mut h, &CODE_HASH), SUCCESS);
+        assert_eq!(value(&h, b"active_code_hash"), Some(&[b'a'; 64][..]));
+        assert_eq!(value(&h, b"pending_code_hash"), None);
+    }
+
+    #[test]
+    fn upgrade_veto_by_owner() {
+        let mut h = host(b";upgrade_threshold=2");
+        h.caller = NOTARIES[0];
+        assert_eq!(veto_upgrade(&mut h), ERR_WRONG_ACCOUNT);
+        assert_eq!(propose_upgrade(&mut h, &CODE_HASH), SUCCESS);
+        assert_eq!(veto_upgrade(&mut h), ERR_WRONG_ACCOUNT);
+        h.caller = OWNER;
+        assert_eq!(veto_upgrade(&mut h), SUCCESS);
+        assert_eq!(value(&h, b"pending_code_hash"), None);
+        assert_eq!(value(&h, b"upgrade_last"), Some(b"vetoed" as &[u8]));
+    }
+
+    #[test]
+    fn release_needs_destination() {
+        let mut h = host(b"");
+        let start = h.data.windows(13).position(|w| w == b";destination=").unwrap();
+        h.data.truncate(start);
+        approve_as(&mut h, 0);
+        approve_as(&mut h, 1);
+        assert_eq!(release(&mut h, AMOUNT), ERR_BAD_CONFIG);
+    }
+}
@@ -0,0 +1,474 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/expr.rs — Release policy expressions
+// ═══════════════════════════════════════════════════════════════════════
+//
+// With a `policy` key, finish() evaluates that expression instead of the
+// fixed threshold → hash-lock → credential → dependency → oracle sequence.
+// Who may submit (finish_by), FinishAfter and the lifecycle still apply.
+//
+//   policy=AND(threshold, ledger>=9000000, OR(preimage, guardian))
+//
+// Grammar (whitespace is ignored):
+//   expr    := AND(expr, ...) | OR(expr, ...) | NOT(expr) | compare | check
+//   compare := term op number        op: >= <= > < == !=
+//   term    := approvals | ledger | time
+//   check   := threshold | challenge | preimage | guardian
+//            | credential | oracle | dependency
+//
+// `approvals` counts notaries that approved (stored plus multisign
+// Signers; SignerList weight with notaries_from), `ledger` is the current
+// ledger sequence and `time` the parent close time in Unix seconds. Each
+// check runs the matching check_* primitive against its usual config keys
+// (hashlock, guardian, credential_*, depends_on, oracle_*).
+//
+// The parsed form is a fixed array of nodes in prefix order; every node
+// records where its subtree ends, so AND/OR short-circuit and skip host
+// reads for branches that can't change the outcome. Size, node count and
+// nesting are bounded so a hostile policy can't exhaust the instance.
+// ═══════════════════════════════════════════════════════════════════════
+
+use crate::{ERR_BAD_CONFIG, ERR_DATA_READ, ERR_HOST_CALL, SUCCESS};
+
+/// Maximum length of a policy expression in bytes
+pub(crate) const MAX_POLICY_SIZE: usize = 256;
+
+/// Maximum number of nodes (operators and leaves) in a policy
+const MAX_NODES: usize = 32;
+
+/// Maximum nesting of AND/OR/NOT
+const MAX_DEPTH: usize = 8;
+
+/// A numeric input compared against a constant.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Term {
+    Approvals,
+    Ledger,
+    Time,
+}
+
+/// A condition decided by one of the contract's check_* primitives.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Check {
+    /// The configured approval threshold (tiers, decay, SignerList quorum)
+    Threshold,
+    /// The challenge period after quorum has elapsed
+    Challenge,
+    /// The memo carries the `hashlock` preimage
+    Preimage,
+    /// The `guardian` account submitted or multisigned the finish
+    Guardian,
+    Credential,
+    Oracle,
+    Dependency,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Op {
+    Ge,
+    Le,
+    Gt,
+    Lt,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn apply(self, a: u64, b: u64) -> bool {
+        match self {
+            Op::Ge => a >= b,
+            Op::Le => a <= b,
+            Op::Gt => a > b,
+            Op::Lt => a < b,
+            Op::Eq => a == b,
+            Op::Ne => a != b,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum NodeKind {
+    And,
+    Or,
+    Not,
+    Compare(Term, Op, u64),
+    Check(Check),
+}
+
+/// One node; its subtree occupies indexes `[self, end)`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Node {
+    kind: NodeKind,
+    end: u8,
+}
+
+/// A parsed policy expression.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Policy {
+    nodes: [Node; MAX_NODES],
+    len: usize,
+}
+
+/// The host-backed facts a policy is evaluated against. finish()
+/// implements this over the current escrow and transaction.
+pub(crate) trait Inputs {
+    /// Current value of a numeric term, or an error code.
+    fn value(&mut self, term: Term) -> Result<u64, i32>;
+    /// Result code of the check_* primitive behind `check`.
+    fn check(&mut self, check: Check) -> i32;
+}
+
+/// Parse a policy expression, or ERR_BAD_CONFIG.
+pub(crate) fn parse_policy(source: &[u8]) -> Result<Policy, i32> {
+    if source.is_empty() || source.len() > MAX_POLICY_SIZE {
+        return Err(ERR_BAD_CONFIG);
+    }
+    let mut parser = Parser {
+        src: source,
+        pos: 0,
+        policy: Policy {
+            nodes: [Node { kind: NodeKind::And, end: 0 }; MAX_NODES],
+            len: 0,
+        },
+    };
+    parser.expr(0)?;
+    parser.skip_space();
+    if parser.pos != source.len() {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(parser.policy)
+}
+
+/// Evaluate a policy. Ok(false) means the policy denies release; Err is a
+/// configuration or host failure that must not be read as a plain "no".
+pub(crate) fn evaluate(policy: &Policy, inputs: &mut impl Inputs) -> Result<bool, i32> {
+    eval_node(policy, 0, inputs)
+}
+
+fn eval_node(policy: &Policy, index: usize, inputs: &mut impl Inputs) -> Result<bool, i32> {
+    let node = policy.nodes[index];
+    match node.kind {
+        NodeKind::And | NodeKind::Or => {
+            let want = node.kind == NodeKind::Or;
+            let mut child = index + 1;
+            while child < node.end as usize {
+                if eval_node(policy, child, inputs)? == want {
+                    return Ok(want);
+                }
+                child = policy.nodes[child].end as usize;
+            }
+            Ok(!want)
+        }
+        NodeKind::Not => Ok(!eval_node(policy, index + 1, inputs)?),
+        NodeKind::Compare(term, op, constant) => Ok(op.apply(inputs.value(term)?, constant)),
+        NodeKind::Check(check) => match inputs.check(check) {
+            SUCCESS => Ok(true),
+            code @ (ERR_BAD_CONFIG | ERR_HOST_CALL | ERR_DATA_READ) => Err(code),
+            _ => Ok(false),
+        },
+    }
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    policy: Policy,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos] == b' ' {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_space();
+        self.src.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), i32> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(ERR_BAD_CONFIG)
+        }
+    }
+
+    fn word(&mut self) -> &[u8] {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.src.len() && (self.src[self.pos].is_ascii_alphabetic() || self.src[self.pos] == b'_') {
+            self.pos += 1;
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn push(&mut self, kind: NodeKind) -> Result<usize, i32> {
+        let index = self.policy.len;
+        if index == MAX_NODES {
+            return Err(ERR_BAD_CONFIG);
+        }
+        self.policy.nodes[index] = Node { kind, end: index as u8 + 1 };
+        self.policy.len += 1;
+        Ok(index)
+    }
+
+    fn expr(&mut self, depth: usize) -> Result<(), i32> {
+        let word = self.word();
+        let kind = match word {
+            b"AND" => NodeKind::And,
+            b"OR" => NodeKind::Or,
+            b"NOT" => NodeKind::Not,
+            b"approvals" | b"ledger" | b"time" => {
+                let term = match word {
+                    b"approvals" => Term::Approvals,
+                    b"ledger" => Term::Ledger,
+                    _ => Term::Time,
+                };
+                let op = self.op()?;
+                let constant = self.number()?;
+                return self.push(NodeKind::Compare(term, op, constant)).map(|_| ());
+            }
+            _ => {
+                let check = match word {
+                    b"threshold" => Check::Threshold,
+                    b"challenge" => Check::Challenge,
+                    b"preimage" => Check::Preimage,
+                    b"guardian" => Check::Guardian,
+                    b"credential" => Check::Credential,
+                    b"oracle" => Check::Oracle,
+                    b"dependency" => Check::Dependency,
+                    _ => return Err(ERR_BAD_CONFIG),
+                };
+                return self.push(NodeKind::Check(check)).map(|_| ());
+            }
+        };
+
+        if depth == MAX_DEPTH {
+            return Err(ERR_BAD_CONFIG);
+        }
+        let index = self.push(kind)?;
+        self.expect(b'(')?;
+        let mut children = 0;
+        loop {
+            self.expr(depth + 1)?;
+            children += 1;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b')') => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => return Err(ERR_BAD_CONFIG),
+            }
+        }
+        if kind == NodeKind::Not && children != 1 {
+            return Err(ERR_BAD_CONFIG);
+        }
+        self.policy.nodes[index].end = self.policy.len as u8;
+        Ok(())
+    }
+
+    fn op(&mut self) -> Result<Op, i32> {
+        self.skip_space();
+        let rest = &self.src[self.pos..];
+        let (op, len) = match rest {
+            [b'>', b'=', ..] => (Op::Ge, 2),
+            [b'<', b'=', ..] => (Op::Le, 2),
+            [b'=', b'=', ..] => (Op::Eq, 2),
+            [b'!', b'=', ..] => (Op::Ne, 2),
+            [b'>', ..] => (Op::Gt, 1),
+            [b'<', ..] => (Op::Lt, 1),
+            _ => return Err(ERR_BAD_CONFIG),
+        };
+        self.pos += len;
+        Ok(op)
+    }
+
+    fn number(&mut self) -> Result<u64, i32> {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        crate::parse_u64(&self.src[start..self.pos]).ok_or(ERR_BAD_CONFIG)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ERR_BAD_PREIMAGE, ERR_NOT_APPROVED, ERR_WRONG_ACCOUNT};
+
+    /// Fixed inputs that record which checks were consulted.
+    struct MockInputs {
+        approvals: u64,
+        ledger: u64,
+        time: u64,
+        results: [(Check, i32); 7],
+        consulted: Vec<Check>,
+    }
+
+    impl MockInputs {
+        fn new() -> Self {
+            MockInputs {
+                approvals: 0,
+                ledger: 0,
+                time: 0,
+                results: [
+                    (Check::Threshold, ERR_NOT_APPROVED),
+                    (Check::Challenge, SUCCESS),
+                    (Check::Preimage, ERR_BAD_PREIMAGE),
+                    (Check::Guardian, ERR_WRONG_ACCOUNT),
+                    (Check::Credential, SUCCESS),
+                    (Check::Oracle, SUCCESS),
+                    (Check::Dependency, SUCCESS),
+                ],
+                consulted: Vec::new(),
+            }
+        }
+
+        fn with(mut self, check: Check, result: i32) -> Self {
+            for entry in self.results.iter_mut() {
+                if entry.0 == check {
+                    entry.1 = result;
+                }
+            }
+            self
+        }
+    }
+
+    impl Inputs for MockInputs {
+        fn value(&mut self, term: Term) -> Result<u64, i32> {
+            Ok(match term {
+                Term::Approvals => self.approvals,
+                Term::Ledger => self.ledger,
+                Term::Time => self.time,
+            })
+        }
+
+        fn check(&mut self, check: Check) -> i32 {
+            self.consulted.push(check);
+            self.results.iter().find(|(c, _)| *c == check).map(|(_, r)| *r).unwrap()
+        }
+    }
+
+    fn eval(source: &[u8], inputs: &mut MockInputs) -> Result<bool, i32> {
+        evaluate(&parse_policy(source)?, inputs)
+    }
+
+    #[test]
+    fn example_policy_from_the_request() {
+        let policy = b"AND(approvals>=2, ledger>=9000000, OR(preimage, guardian))";
+        let mut inputs = MockInputs::new();
+        inputs.approvals = 2;
+        inputs.ledger = 9_000_000;
+        assert_eq!(eval(policy, &mut inputs), Ok(false));
+
+        let mut inputs = MockInputs::new().with(Check::Guardian, SUCCESS);
+        inputs.approvals = 2;
+        inputs.ledger = 9_000_000;
+        assert_eq!(eval(policy, &mut inputs), Ok(true));
+
+        inputs.ledger = 8_999_999;
+        assert_eq!(eval(policy, &mut inputs), Ok(false));
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let mut inputs = MockInputs::new();
+        inputs.ledger = 10;
+        for (source, expected) in [
+            (&b"ledger>=10"[..], true),
+            (b"ledger>10", false),
+            (b"ledger<=10", true),
+            (b"ledger<11", true),
+            (b"ledger==10", true),
+            (b"ledger!=10", false),
+            (b"time>0", false),
+        ] {
+            assert_eq!(eval(source, &mut inputs), Ok(expected), "{:?}", core::str::from_utf8(source));
+        }
+    }
+
+    #[test]
+    fn not_and_nesting() {
+        let mut inputs = MockInputs::new();
+        assert_eq!(eval(b"NOT(threshold)", &mut inputs), Ok(true));
+        assert_eq!(eval(b"OR(AND(challenge, NOT(oracle)), dependency)", &mut inputs), Ok(true));
+        assert_eq!(eval(b"AND(challenge, OR(threshold, preimage))", &mut inputs), Ok(false));
+    }
+
+    #[test]
+    fn and_or_short_circuit() {
+        let mut inputs = MockInputs::new();
+        assert_eq!(eval(b"AND(threshold, oracle, credential)", &mut inputs), Ok(false));
+        assert_eq!(inputs.consulted, vec![Check::Threshold]);
+
+        let mut inputs = MockInputs::new();
+        assert_eq!(eval(b"OR(challenge, oracle)", &mut inputs), Ok(true));
+        assert_eq!(inputs.consulted, vec![Check::Challenge]);
+    }
+
+    #[test]
+    fn config_and_host_errors_propagate() {
+        let mut inputs = MockInputs::new().with(Check::Oracle, ERR_BAD_CONFIG);
+        assert_eq!(eval(b"OR(oracle, challenge)", &mut inputs), Err(ERR_BAD_CONFIG));
+        // NOT doesn't turn a failure into a pass
+        assert_eq!(eval(b"NOT(oracle)", &mut inputs), Err(ERR_BAD_CONFIG));
+
+        let mut inputs = MockInputs::new().with(Check::Credential, ERR_HOST_CALL);
+        assert_eq!(eval(b"credential", &mut inputs), Err(ERR_HOST_CALL));
+    }
+
+    #[test]
+    fn rejects_malformed_policies() {
+        for source in [
+            &b""[..],
+            b"AND()",
+            b"AND(threshold",
+            b"AND(threshold,)",
+            b"NOT(threshold, oracle)",
+            b"approvals",
+            b"approvals>=",
+            b"approvals=>2",
+            b"ledger>=-1",
+            b"and(threshold)",
+            b"unknown",
+            b"threshold oracle",
+            b"threshold)",
+        ] {
+            assert_eq!(parse_policy(source).map(|_| ()), Err(ERR_BAD_CONFIG), "{:?}", core::str::from_utf8(source));
+        }
+    }
+
+    #[test]
+    fn enforces_size_node_and_depth_limits() {
+        let too_long = [b' '; MAX_POLICY_SIZE + 1];
+        assert_eq!(parse_policy(&too_long).map(|_| ()), Err(ERR_BAD_CONFIG));
+
+        // MAX_NODES leaves under one AND is one node too many
+        let mut wide = b"AND(".to_vec();
+        for i in 0..MAX_NODES {
+            if i > 0 {
+                wide.push(b',');
+            }
+            wide.extend_from_slice(b"oracle");
+        }
+        wide.push(b')');
+        assert_eq!(parse_policy(&wide).map(|_| ()), Err(ERR_BAD_CONFIG));
+
+        let nested = |levels: usize| {
+            let mut s = b"NOT(".repeat(levels);
+            s.extend_from_slice(b"oracle");
+            s.extend_from_slice(&b")".repeat(levels));
+            s
+        };
+        assert!(parse_policy(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(parse_policy(&nested(MAX_DEPTH + 1)).map(|_| ()), Err(ERR_BAD_CONFIG));
+    }
+}
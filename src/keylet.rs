@@ -0,0 +1,127 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/keylet.rs — Ledger object index (keylet) computation
+// ═══════════════════════════════════════════════════════════════════════
+//
+// Every ledger object lives at SHA-512Half(space || fields...), where the
+// space is a 2-byte big-endian namespace tag (rippled's LedgerNameSpace)
+// and the fields are the object's identifying values in binary form.
+// ═══════════════════════════════════════════════════════════════════════
+
+use crate::sha512::{Sha512, HALF_DIGEST_SIZE};
+use crate::ACCOUNT_ID_SIZE;
+
+/// Size of a ledger object index in bytes
+pub(crate) const KEYLET_SIZE: usize = HALF_DIGEST_SIZE;
+
+/// LedgerNameSpace::ACCOUNT ('a')
+const SPACE_ACCOUNT: u16 = 0x0061;
+
+/// LedgerNameSpace::ESCROW ('u')
+const SPACE_ESCROW: u16 = 0x0075;
+
+/// LedgerNameSpace::SIGNER_LIST ('S')
+const SPACE_SIGNER_LIST: u16 = 0x0053;
+
+/// SignerListID of an account's (only) SignerList
+const DEFAULT_SIGNER_LIST_ID: u32 = 0;
+
+/// Index of an AccountRoot: SHA-512Half('a' || AccountID).
+pub(crate) fn account_keylet(account: &[u8; ACCOUNT_ID_SIZE]) -> [u8; KEYLET_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(&SPACE_ACCOUNT.to_be_bytes());
+    hasher.update(account);
+    hasher.finalize_half()
+}
+
+/// Index of an Escrow: SHA-512Half('u' || owner AccountID || sequence).
+/// `sequence` is the Sequence (or TicketSequence) of the EscrowCreate.
+pub(crate) fn escrow_keylet(owner: &[u8; ACCOUNT_ID_SIZE], sequence: u32) -> [u8; KEYLET_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(&SPACE_ESCROW.to_be_bytes());
+    hasher.update(owner);
+    hasher.update(&sequence.to_be_bytes());
+    hasher.finalize_half()
+}
+
+/// Index of an account's SignerList: SHA-512Half('S' || AccountID || SignerListID).
+pub(crate) fn signer_list_keylet(account: &[u8; ACCOUNT_ID_SIZE]) -> [u8; KEYLET_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(&SPACE_SIGNER_LIST.to_be_bytes());
+    hasher.update(account);
+    hasher.update(&DEFAULT_SIGNER_LIST_ID.to_be_bytes());
+    hasher.finalize_half()
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — known ledger indexes
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+
+    /// Genesis account rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh
+    const GENESIS: [u8; ACCOUNT_ID_SIZE] = [
+        0xB5, 0xF7, 0x62, 0x79, 0x8A, 0x53, 0xD5, 0x43, 0xA0, 0x14,
+        0xCA, 0xF8, 0xB2, 0x97, 0xCF, 0xF8, 0xF2, 0xF9, 0x37, 0xE8,
+    ];
+
+    fn index_from_hex(hex: &[u8]) -> [u8; KEYLET_SIZE] {
+        let mut out = [0u8; KEYLET_SIZE];
+        assert_eq!(decode_hex(hex, &mut out), Some(KEYLET_SIZE));
+        out
+    }
+
+    #[test]
+    fn genesis_account_root_index() {
+        // The well-known index of the genesis AccountRoot on mainnet
+        assert_eq!(
+            account_keylet(&GENESIS),
+            index_from_hex(b"2B6AC232AA4C4BE41BF49D2459FA4A0347E1B543A4C92FCEE0821C0201E2E9A8")
+        );
+    }
+
+    #[test]
+    fn known_escrow_indexes() {
+        // rDx69ebzbowuqztksVDmZXjizTd12BVr4x, sequence 84 — xrpl.js's hashEscrow test
+        let owner = [
+            0x8E, 0x29, 0xE6, 0xEF, 0x85, 0x6E, 0x31, 0x7E, 0x9A, 0xD1,
+            0xF7, 0xA7, 0xB1, 0xD8, 0xB4, 0x4B, 0xD0, 0x88, 0x4C, 0x9A,
+        ];
+        assert_eq!(
+            escrow_keylet(&owner, 84),
+            index_from_hex(b"61E8E8ED53FA2CEBE192B23897071E9A75217BF5A410E9CB5B45AAB7AECA567A")
+        );
+
+        // rf1BiGeXwwQoi8Z2ueFYTEXSwuJYfV2Jpn, sequence 366 — the example
+        // Escrow ledger entry in the XRPL reference docs
+        let owner = [
+            0x4B, 0x4E, 0x9C, 0x06, 0xF2, 0x42, 0x96, 0x07, 0x4F, 0x7B,
+            0xC4, 0x8F, 0x92, 0xA9, 0x79, 0x16, 0xC6, 0xDC, 0x5E, 0xA9,
+        ];
+        assert_eq!(
+            escrow_keylet(&owner, 366),
+            index_from_hex(b"DC5F3851D8A1AB622F957761E5963BC5BD439D5C24AC6AD7AC4523F0640244AC")
+        );
+    }
+
+    #[test]
+    fn escrow_index_depends_on_sequence_and_space() {
+        // Sequence is encoded big-endian: 1 and 0x01000000 must differ
+        assert_ne!(escrow_keylet(&GENESIS, 1), escrow_keylet(&GENESIS, 0x0100_0000));
+        // Same account, different namespace
+        assert_ne!(escrow_keylet(&GENESIS, 0)[..], account_keylet(&GENESIS)[..]);
+    }
+
+    #[test]
+    fn signer_list_index_layout() {
+        // 'S' || AccountID || SignerListID 0, hashed like every other keylet
+        let mut preimage = [0u8; 2 + ACCOUNT_ID_SIZE + 4];
+        preimage[..2].copy_from_slice(b"\x00S");
+        preimage[2..2 + ACCOUNT_ID_SIZE].copy_from_slice(&GENESIS);
+        let digest = crate::sha512::sha512(&preimage);
+        assert_eq!(signer_list_keylet(&GENESIS)[..], digest[..KEYLET_SIZE]);
+        // Same account and trailing u32 as escrow sequence 0 — only the space differs
+        assert_ne!(signer_list_keylet(&GENESIS), escrow_keylet(&GENESIS, 0));
+    }
+}
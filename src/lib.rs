@@ -23,6 +23,72 @@
 //     approval_count   — current number of approvals (ASCII digit)
 //     last_attempt_seq — tx sequence of last finish attempt (for rate limiting)
 //     last_result      — result code of last finish attempt
+//     tier_0..N        — amount-tiered thresholds as "<min_drops>:<threshold>";
+//                        tier_0 must start at 0 and tiers must be monotonic
+//     decay_0..N       — time-decaying thresholds as "<from_ledger>:<threshold>";
+//                        decay_0 must start at 0, later steps may only relax
+//     finish_by        — who may submit the releasing EscrowFinish: comma-separated
+//                        roles from notary, destination, owner, any (default: notary)
+//     oracle_*         — optional XLS-47 price condition (see PRICE ORACLE CONDITION)
+//     credential_*     — optional XLS-70 credential requirement (see CREDENTIAL CONDITION)
+//     hashlock         — optional SHA-256 digest (64 hex) unlocked by a preimage
+//     hashlock_mode    — "and" (preimage AND threshold, default) or "or"
+//     depends_on       — "<owner hex>:<sequence>" of an escrow that must be finished first
+//     depends_on_missing — "deny": a missing depends_on escrow doesn't count as finished
+//     challenge_ledgers — ledgers that must pass after quorum before finish() releases
+//     quorum_ledger    — ledger at which quorum was first reached (cleared if lost)
+//     inactivity_ledgers — dead-man switch: ledgers without approval activity after
+//                        which the backup notary set takes over
+//     created_ledger   — activity baseline until the first approval/revocation
+//     backup_notary_count, backup_notary_0..N, backup_threshold — the backup set
+//     last_activity_ledger — ledger of the last approval or revocation
+//     notary_set       — "backup" once the dead-man switch has fired (absent: primary)
+//     notary_set_ledger — ledger at which the backup set became authoritative
+//     state            — lifecycle state: configured, collecting, quorum, released,
+//                        frozen or aborted (absent: configured)
+//     gov_threshold, gov_delay_ledgers, gov_expiry_ledgers, immutable, prop_*,
+//                        gov_last* — config change proposals (see GOVERNANCE PROPOSALS)
+//     upgrade_*, pending_code_hash, active_code_hash — XLS-101 code upgrades
+//                        (see src/upgrade.rs)
+//     notaries_from    — "signerlist:<account hex>": notaries, weights and quorum
+//                        come from that account's SignerList (see SIGNERLIST NOTARIES)
+//     signer_approval_<account hex> — "1" once that SignerList member approved
+//     policy           — release policy expression evaluated by finish() in place of
+//                        the fixed condition sequence (see src/expr.rs)
+//     guardian         — 40-hex AccountID for the policy's `guardian` check
+//     owner, destination — optional 40-hex AccountIDs the escrow's Account and
+//                        Destination must match (see CONFIG BINDING)
+//     destination_tag, source_tag — optional tags the escrow must carry
+//     max_amount       — optional cap on the escrow Amount in drops
+//     notary_exclude   — owner and/or destination: parties that may not be notaries
+//                        (needs the matching binding key; see CONFLICT OF INTEREST)
+//     require_consent  — owner and/or destination: parties whose own, non-counting
+//                        approval finish() also requires
+//     consent_owner, consent_destination — ledger at which that consent was given
+//     require_destination_ack — "1": the Destination must call acknowledge() before
+//                        finish() releases (see DESTINATION ACKNOWLEDGEMENT)
+//     dest_ack, dest_ack_seq, dest_ack_ledger — who acknowledged, tx sequence, ledger
+//     abort_threshold  — notary votes that abort the escrow for good (see ABORT VOTE)
+//     abort_vote_0..N, abort_votes, abort_ledger — abort votes and when it passed
+//     revocation_policy — always (default), before_quorum, never or
+//                        within_n_ledgers_of_approval (see REVOCATION POLICY)
+//     revocation_window_ledgers — the N for within_n_ledgers_of_approval
+//     approve_ledger_0..N — ledger of each notary's current approval
+//     last_revocation_policy, last_revocation_ledger — audit of the last revocation
+//     deputy_0..N, deputy_until_0..N — a notary's deputy and the last ledger
+//                  it may act (see DEPUTY DELEGATION)
+//     approve_deputy_0..N — deputy that gave the latest approval, for notary N
+//     approval_round   — current approval round (absent = 0); see APPROVAL ROUNDS
+//     approve_round_0..N — round each notary's approval was given in
+//     round_vote_0..N, round_ledger — votes for the next round, and when it began
+//     commit_deadline, reveal_deadline — ledgers ending the commit and reveal
+//                  phases of commit-reveal approvals (see COMMIT-REVEAL)
+//     vote_commit_0..N, vote_reveal_0..N — each notary's sealed vote and its reveal
+//     template         — named policy expanded into the keys above on first use;
+//                        recorded as template_used (see src/template.rs)
+//
+//   A multisigned EscrowFinish also counts each notary in its Signers array
+//   as an approval for that invocation (see MULTISIGNED FINISH).
 //
 // ARCHITECTURE:
 //   - Compiles to WASM, embedded in an EscrowCreate transaction
@@ -71,8 +137,23 @@ use xrpl_wasm_stdlib::core::ledger_objects::current_escrow::get_current_escrow;
 #[cfg(target_arch = "wasm32")]
 use xrpl_wasm_stdlib::core::ledger_objects::traits::CurrentEscrowFields;
 #[cfg(target_arch = "wasm32")]
+use xrpl_wasm_stdlib::core::types::amount::token_amount::TokenAmount;
+#[cfg(target_arch = "wasm32")]
 use xrpl_wasm_stdlib::core::types::contract_data::{ContractData, XRPL_CONTRACT_DATA_SIZE};
 
+// -----------------------------------------------------------------------
+// Self-contained primitives (no host dependency, tested natively)
+// -----------------------------------------------------------------------
+mod expr;
+mod keylet;
+mod sha256;
+mod sha512;
+mod template;
+mod upgrade;
+
+// XLS-101 smart contract handlers; exported with the smart-contract feature
+mod contract;
+
 // ═══════════════════════════════════════════════════════════════════════
 // CONSTANTS
 // ═══════════════════════════════════════════════════════════════════════
@@ -83,10 +164,61 @@ const ACCOUNT_ID_SIZE: usize = 20;
 /// Maximum number of notaries supported
 const MAX_NOTARIES: usize = 5;
 
+/// Maximum number of amount tiers (`tier_0..tier_N`)
+const MAX_TIERS: usize = 5;
+
+/// Maximum number of threshold decay steps (`decay_0..decay_N`)
+const MAX_DECAY_STEPS: usize = 5;
+
+/// Size of an XRPL currency code in bytes
+const CURRENCY_SIZE: usize = 20;
+
+/// Maximum decimal places accepted for oracle prices (keeps comparisons in u128)
+const MAX_PRICE_SCALE: u8 = 18;
+
+/// Maximum length of an XLS-70 CredentialType in bytes
+const MAX_CREDENTIAL_TYPE_SIZE: usize = 64;
+
+/// Credential ledger object flag: the subject has accepted the credential
+const LSF_ACCEPTED: u32 = 0x0001_0000;
+
+/// Maximum entries in a transaction's Signers array (XRPL SignerList limit)
+const MAX_SIGNERS: usize = 32;
+
+/// Maximum hash-lock preimage size in bytes (read into a fixed buffer)
+const MAX_PREIMAGE_SIZE: usize = 64;
+
+/// Longest config key a governance proposal may change
+const MAX_PROPOSAL_KEY_SIZE: usize = 32;
+
+/// Longest value a governance proposal may set (a notary AccountID is 40 hex)
+const MAX_PROPOSAL_VALUE_SIZE: usize = 64;
+
+/// Buffer size for governance memos ("id=..;key=..;value=.." or "proposal=..")
+const MAX_GOVERNANCE_MEMO_SIZE: usize = 128;
+
+/// Seconds between the Unix epoch and the Ripple epoch (2000-01-01T00:00:00Z).
+/// Ledger close times use the Ripple epoch; Oracle LastUpdateTime uses Unix time.
+const RIPPLE_EPOCH_OFFSET: u32 = 946_684_800;
+
 /// Minimum ledgers between finish attempts (rate limiting)
 /// ~30-50 seconds at 3-5 sec/ledger
 const COOLDOWN_LEDGERS: u32 = 10;
 
+/// Ledgers between a proposal passing and applying when
+/// `gov_delay_ledgers` is not set (~12 hours)
+const DEFAULT_GOV_DELAY_LEDGERS: u32 = 8640;
+
+/// Ledgers a proposal may gather votes when `gov_expiry_ledgers` is not
+/// set (~24 hours)
+const DEFAULT_GOV_EXPIRY_LEDGERS: u32 = 17_280;
+
+/// Roles that may submit the releasing EscrowFinish (`finish_by` key bitmask)
+const FINISH_BY_NOTARY: u8 = 0b0001;
+const FINISH_BY_DESTINATION: u8 = 0b0010;
+const FINISH_BY_OWNER: u8 = 0b0100;
+const FINISH_BY_ANY: u8 = 0b1000;
+
 // ═══════════════════════════════════════════════════════════════════════
 // RETURN CODES
 //   > 0  →  Escrow CAN be finished (funds released)
@@ -101,6 +233,21 @@ const ERR_HOST_CALL: i32 = -5;
 const ERR_BAD_CONFIG: i32 = -6;
 const ERR_ALREADY_APPROVED: i32 = -7;
 const ERR_COOLDOWN: i32 = -8;
+const ERR_PRICE_CONDITION: i32 = -9;
+const ERR_ORACLE_STALE: i32 = -10;
+const ERR_NO_CREDENTIAL: i32 = -11;
+const ERR_BAD_PREIMAGE: i32 = -12;
+const ERR_DEPENDENCY: i32 = -13;
+const ERR_ILLEGAL_TRANSITION: i32 = -14;
+const ERR_IMMUTABLE_KEY: i32 = -15;
+const ERR_PROPOSAL: i32 = -16;
+const ERR_UPGRADE: i32 = -17;
+const ERR_POLICY: i32 = -18;
+const ERR_DESTINATION_ACK: i32 = -19;
+const ERR_ABORTED: i32 = -20;
+const ERR_REVOCATION_LOCKED: i32 = -21;
+const ERR_REVEAL: i32 = -22;
+const ERR_PHASE_CLOSED: i32 = -23;
 
 // ═══════════════════════════════════════════════════════════════════════
 // DATA PARSING UTILITIES
@@ -160,7 +307,7 @@ fn parse_u32(data: &[u8]) -> Option<u32> {
     }
     let mut result: u32 = 0;
     for &b in data {
-        if b < b'0' || b > b'9' {
+        if !b.is_ascii_digit() {
             return None;
         }
         result = result.checked_mul(10)?.checked_add((b - b'0') as u32)?;
@@ -168,11 +315,27 @@ fn parse_u32(data: &[u8]) -> Option<u32> {
     Some(result)
 }
 
+/// Parse a u64 from ASCII decimal bytes (e.g. an XRP amount in drops).
+/// Returns None if the slice is empty or contains non-digit characters.
+fn parse_u64(data: &[u8]) -> Option<u64> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut result: u64 = 0;
+    for &b in data {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        result = result.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+    }
+    Some(result)
+}
+
 /// Decode a hex string (ASCII) into raw bytes.
 /// Writes into `out` and returns the number of bytes written.
 /// Returns None if the hex string is invalid or `out` is too small.
 fn decode_hex(hex: &[u8], out: &mut [u8]) -> Option<usize> {
-    if hex.len() % 2 != 0 {
+    if !hex.len().is_multiple_of(2) {
         return None;
     }
     let byte_len = hex.len() / 2;
@@ -249,30 +412,118 @@ fn write_separator(data: &mut [u8], pos: usize) -> usize {
     }
 }
 
+/// Copy every non-empty entry of `existing_data[..existing_len]` into `out`,
+/// except those whose key matches `skip`. Returns the number of bytes written.
+fn copy_entries_except(
+    existing_data: &[u8],
+    existing_len: usize,
+    out: &mut [u8],
+    skip: impl Fn(&[u8]) -> bool,
+) -> usize {
+    let mut pos = 0;
+    let mut scan = 0;
+    while scan < existing_len {
+        let entry_end = existing_data[scan..existing_len].iter()
+            .position(|&b| b == b';')
+            .map(|p| scan + p)
+            .unwrap_or(existing_len);
+
+        let entry = &existing_data[scan..entry_end];
+
+        let skipped = match entry.iter().position(|&b| b == b'=') {
+            Some(eq) => skip(&entry[..eq]),
+            None => false,
+        };
+
+        if !skipped && !entry.is_empty() {
+            if pos > 0 {
+                pos = write_separator(out, pos);
+            }
+            let elen = entry.len();
+            if pos + elen <= out.len() {
+                out[pos..pos + elen].copy_from_slice(entry);
+                pos += elen;
+            }
+        }
+
+        scan = entry_end + 1;
+    }
+    pos
+}
+
+/// Build a copy of the data with `key` set to `value`, replacing any
+/// existing entry for `key`.
+fn replace_entry(
+    existing_data: &[u8],
+    existing_len: usize,
+    key: &[u8],
+    value: &[u8],
+) -> ([u8; 4096], usize) {
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| k == key);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, key, value);
+    (new_data, pos)
+}
+
 // ═══════════════════════════════════════════════════════════════════════
 // CONTRACT LOGIC — Pure functions testable without WASM host
 // ═══════════════════════════════════════════════════════════════════════
 
-/// Verify that the caller is one of the registered notaries.
+/// Verify that the caller is one of the registered notaries, or a deputy
+/// acting for one at `current_ledger` (see DEPUTY DELEGATION).
 /// Returns the notary index (0..N) if authorized, or ERR_WRONG_ACCOUNT.
-fn check_caller_is_notary(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE]) -> Result<u8, i32> {
+///
+/// Checks whichever set is authoritative: the primary `notary_N` list, or
+/// the `backup_notary_N` list once the dead-man switch has been recorded.
+fn check_caller_is_notary(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE], current_ledger: u32) -> Result<u8, i32> {
+    match check_listed_notary(data, caller) {
+        Err(ERR_WRONG_ACCOUNT) => find_deputy(data, caller, current_ledger).ok_or(ERR_WRONG_ACCOUNT),
+        result => result,
+    }
+}
+
+/// check_caller_is_notary without deputies: the caller itself is listed.
+fn check_listed_notary(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE]) -> Result<u8, i32> {
+    let (count_key, prefix) = notary_set_keys(recorded_notary_set(data)?);
+    find_notary_index(data, count_key, prefix, caller)
+}
+
+/// Count key and entry prefix of a notary set.
+fn notary_set_keys(set: NotarySet) -> (&'static [u8], &'static [u8]) {
+    match set {
+        NotarySet::Primary => (b"notary_count", b"notary_"),
+        NotarySet::Backup => (b"backup_notary_count", b"backup_notary_"),
+    }
+}
+
+/// Look the caller up in one notary list (`<prefix>0..<prefix>N`).
+fn find_notary_index(
+    data: &[u8],
+    count_key: &[u8],
+    prefix: &[u8],
+    caller: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<u8, i32> {
     // Read notary count from config
-    let count = find_value(data, b"notary_count")
+    let count = find_value(data, count_key)
         .and_then(parse_u8_digit)
         .ok_or(ERR_BAD_CONFIG)?;
 
     if count == 0 || count as usize > MAX_NOTARIES {
         return Err(ERR_BAD_CONFIG);
     }
+    check_notary_conflicts(data, count, prefix)?;
 
     // Encode the caller's AccountID as hex for comparison
     let mut caller_hex = [0u8; ACCOUNT_ID_SIZE * 2];
     encode_hex(caller, &mut caller_hex);
 
     // Check each registered notary
-    let mut key_buf = [0u8; 16]; // "notary_X"
+    let mut key_buf = [0u8; 24]; // "notary_X" / "backup_notary_X"
     for i in 0..count {
-        let key_len = build_indexed_key(b"notary_", i, &mut key_buf);
+        let key_len = build_indexed_key(prefix, i, &mut key_buf);
         let key = &key_buf[..key_len];
 
         if let Some(stored_hex) = find_value(data, key) {
@@ -287,8 +538,78 @@ fn check_caller_is_notary(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE]) -> Result
     Err(ERR_WRONG_ACCOUNT)
 }
 
+/// Parse a comma-separated `finish_by` policy into a role bitmask.
+/// Returns None if the list is empty or names an unknown role.
+///
+/// Example: parse_finish_by(b"notary,destination") returns
+///          Some(FINISH_BY_NOTARY | FINISH_BY_DESTINATION)
+fn parse_finish_by(value: &[u8]) -> Option<u8> {
+    if value.is_empty() {
+        return None;
+    }
+    let mut roles = 0;
+    for role in value.split(|&b| b == b',') {
+        roles |= match role {
+            b"notary" => FINISH_BY_NOTARY,
+            b"destination" => FINISH_BY_DESTINATION,
+            b"owner" => FINISH_BY_OWNER,
+            b"any" => FINISH_BY_ANY,
+            _ => return None,
+        };
+    }
+    Some(roles)
+}
+
+/// Verify that the caller may submit the releasing EscrowFinish.
+/// `owner` and `destination` are the escrow's Account and Destination fields.
+/// Without a `finish_by` key only registered notaries may finish.
+///
+/// This only decides WHO may submit — the approval threshold is still
+/// checked separately, so `any` means "anyone, once quorum is reached".
+fn check_finish_submitter(
+    data: &[u8],
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    check_submitter_roles(data, None, caller, owner, destination)
+}
+
+/// check_finish_submitter against either notary source: the data's own
+/// notary list, or `signer_list` when the escrow uses `notaries_from`.
+fn check_submitter_roles(
+    data: &[u8],
+    signer_list: Option<&SignerList>,
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    let roles = match find_value(data, b"finish_by") {
+        Some(value) => parse_finish_by(value).ok_or(ERR_BAD_CONFIG)?,
+        None => FINISH_BY_NOTARY,
+    };
+
+    if roles & FINISH_BY_ANY != 0 {
+        return Ok(());
+    }
+    if roles & FINISH_BY_DESTINATION != 0 && caller == destination {
+        return Ok(());
+    }
+    if roles & FINISH_BY_OWNER != 0 && caller == owner {
+        return Ok(());
+    }
+    if roles & FINISH_BY_NOTARY != 0 {
+        return check_notary_membership(data, signer_list, caller);
+    }
+
+    Err(ERR_WRONG_ACCOUNT)
+}
+
 /// Check if the approval threshold has been met.
 /// Returns SUCCESS if enough notaries have approved.
+///
+/// `approval_count` only covers the current `approval_round`: starting a
+/// round resets it, and approvals from earlier rounds never add to it.
 fn check_approval_threshold(data: &[u8]) -> i32 {
     let threshold = match find_value(data, b"threshold")
         .and_then(parse_u8_digit) {
@@ -296,6 +617,11 @@ fn check_approval_threshold(data: &[u8]) -> i32 {
         None => return ERR_BAD_CONFIG,
     };
 
+    check_approvals_meet(data, threshold)
+}
+
+/// Compare the recorded approval count against a required threshold.
+fn check_approvals_meet(data: &[u8], threshold: u8) -> i32 {
     let approval_count = find_value(data, b"approval_count")
         .and_then(parse_u8_digit)
         .unwrap_or(0);
@@ -307,6 +633,245 @@ fn check_approval_threshold(data: &[u8]) -> i32 {
     }
 }
 
+/// Parse a tier entry of the form "<min_drops>:<threshold>".
+///
+/// Example: parse_tier(b"10000000000:2") returns Some((10000000000, 2))
+fn parse_tier(value: &[u8]) -> Option<(u64, u8)> {
+    let colon = value.iter().position(|&b| b == b':')?;
+    let min_drops = parse_u64(&value[..colon])?;
+    let threshold = parse_u8_digit(&value[colon + 1..])?;
+    Some((min_drops, threshold))
+}
+
+/// Validate the amount tier table and return the number of tiers (0 if none).
+///
+/// Tiers are read from tier_0 upwards until the first missing index. They
+/// must start at 0 drops, have strictly increasing amounts, non-decreasing
+/// thresholds of at least 1, and the highest threshold must be satisfiable
+/// by `notary_count`.
+fn validate_tiers(data: &[u8]) -> Result<usize, i32> {
+    let mut key_buf = [0u8; 16];
+    let mut tiers = 0;
+    let mut prev: Option<(u64, u8)> = None;
+
+    for i in 0..MAX_TIERS as u8 {
+        let key_len = build_indexed_key(b"tier_", i, &mut key_buf);
+        let value = match find_value(data, &key_buf[..key_len]) {
+            Some(v) => v,
+            None => break,
+        };
+        let (min_drops, threshold) = parse_tier(value).ok_or(ERR_BAD_CONFIG)?;
+
+        if threshold == 0 {
+            return Err(ERR_BAD_CONFIG);
+        }
+        match prev {
+            None if min_drops != 0 => return Err(ERR_BAD_CONFIG),
+            Some((p_drops, p_threshold)) if min_drops <= p_drops || threshold < p_threshold => {
+                return Err(ERR_BAD_CONFIG);
+            }
+            _ => {}
+        }
+
+        prev = Some((min_drops, threshold));
+        tiers += 1;
+    }
+
+    // A tier after a gap in the numbering, or beyond MAX_TIERS, would be
+    // silently ignored — reject it instead
+    for i in tiers as u8..=MAX_TIERS as u8 {
+        let key_len = build_indexed_key(b"tier_", i, &mut key_buf);
+        if find_value(data, &key_buf[..key_len]).is_some() {
+            return Err(ERR_BAD_CONFIG);
+        }
+    }
+
+    if let Some((_, highest)) = prev {
+        let count = find_value(data, b"notary_count")
+            .and_then(parse_u8_digit)
+            .ok_or(ERR_BAD_CONFIG)?;
+        if highest > count {
+            return Err(ERR_BAD_CONFIG);
+        }
+    }
+
+    Ok(tiers)
+}
+
+/// Parse a decay step of the form "<from_ledger>:<threshold>".
+///
+/// Example: parse_decay_step(b"80000000:2") returns Some((80000000, 2))
+fn parse_decay_step(value: &[u8]) -> Option<(u32, u8)> {
+    let colon = value.iter().position(|&b| b == b':')?;
+    let from_ledger = parse_u32(&value[..colon])?;
+    let threshold = parse_u8_digit(&value[colon + 1..])?;
+    Some((from_ledger, threshold))
+}
+
+/// Validate the threshold decay schedule and return the number of steps
+/// (0 if none).
+///
+/// Steps are read from decay_0 upwards until the first missing index. They
+/// must start at ledger 0, have strictly increasing ledgers, and thresholds
+/// of at least 1 that never increase — a schedule may only relax. The first
+/// threshold must be satisfiable by `notary_count`. A schedule replaces the
+/// amount tier table, so configuring both is rejected.
+fn validate_decay_schedule(data: &[u8]) -> Result<usize, i32> {
+    let mut key_buf = [0u8; 16];
+    let mut steps = 0;
+    let mut prev: Option<(u32, u8)> = None;
+
+    for i in 0..MAX_DECAY_STEPS as u8 {
+        let key_len = build_indexed_key(b"decay_", i, &mut key_buf);
+        let value = match find_value(data, &key_buf[..key_len]) {
+            Some(v) => v,
+            None => break,
+        };
+        let (from_ledger, threshold) = parse_decay_step(value).ok_or(ERR_BAD_CONFIG)?;
+
+        if threshold == 0 {
+            return Err(ERR_BAD_CONFIG);
+        }
+        match prev {
+            None if from_ledger != 0 => return Err(ERR_BAD_CONFIG),
+            Some((p_ledger, p_threshold)) if from_ledger <= p_ledger || threshold > p_threshold => {
+                return Err(ERR_BAD_CONFIG);
+            }
+            _ => {}
+        }
+
+        if prev.is_none() {
+            let count = find_value(data, b"notary_count")
+                .and_then(parse_u8_digit)
+                .ok_or(ERR_BAD_CONFIG)?;
+            if threshold > count {
+                return Err(ERR_BAD_CONFIG);
+            }
+        }
+
+        prev = Some((from_ledger, threshold));
+        steps += 1;
+    }
+
+    // A step after a gap in the numbering, or beyond MAX_DECAY_STEPS, would
+    // be silently ignored — reject it instead
+    for i in steps as u8..=MAX_DECAY_STEPS as u8 {
+        let key_len = build_indexed_key(b"decay_", i, &mut key_buf);
+        if find_value(data, &key_buf[..key_len]).is_some() {
+            return Err(ERR_BAD_CONFIG);
+        }
+    }
+
+    if steps > 0 && find_value(data, b"tier_0").is_some() {
+        return Err(ERR_BAD_CONFIG);
+    }
+
+    Ok(steps)
+}
+
+/// Find the decay step in force at `current_ledger`.
+/// Returns (step index, ledger the step started at, threshold), or None if
+/// no schedule is configured.
+fn current_decay_step(data: &[u8], current_ledger: u32) -> Result<Option<(u8, u32, u8)>, i32> {
+    let steps = validate_decay_schedule(data)?;
+
+    let mut key_buf = [0u8; 16];
+    let mut current = None;
+    for i in 0..steps as u8 {
+        let key_len = build_indexed_key(b"decay_", i, &mut key_buf);
+        let (from_ledger, threshold) = find_value(data, &key_buf[..key_len])
+            .and_then(parse_decay_step)
+            .ok_or(ERR_BAD_CONFIG)?;
+        if current_ledger >= from_ledger {
+            current = Some((i, from_ledger, threshold));
+        }
+    }
+    Ok(current)
+}
+
+/// Determine how many approvals this escrow needs at `current_ledger`.
+/// `amount_drops` is the escrow Amount, or None if it is not an XRP amount.
+///
+/// Without a tier table or decay schedule this is the plain `threshold`
+/// key. With tiers, the highest tier whose minimum is <= the amount applies;
+/// with a decay schedule, the latest step that has started applies.
+fn required_threshold(data: &[u8], amount_drops: Option<u64>, current_ledger: u32) -> Result<u8, i32> {
+    // Once the backup set is authoritative its own threshold applies
+    if recorded_notary_set(data)? == NotarySet::Backup {
+        return find_value(data, b"backup_threshold")
+            .and_then(parse_u8_digit)
+            .ok_or(ERR_BAD_CONFIG);
+    }
+
+    if let Some((_, _, threshold)) = current_decay_step(data, current_ledger)? {
+        return Ok(threshold);
+    }
+
+    let tiers = validate_tiers(data)?;
+    if tiers == 0 {
+        return find_value(data, b"threshold")
+            .and_then(parse_u8_digit)
+            .ok_or(ERR_BAD_CONFIG);
+    }
+
+    // Tiers are denominated in drops — token escrows can't use them
+    let amount = amount_drops.ok_or(ERR_BAD_CONFIG)?;
+
+    let mut key_buf = [0u8; 16];
+    let mut required = 0;
+    for i in 0..tiers as u8 {
+        let key_len = build_indexed_key(b"tier_", i, &mut key_buf);
+        let (min_drops, threshold) = find_value(data, &key_buf[..key_len])
+            .and_then(parse_tier)
+            .ok_or(ERR_BAD_CONFIG)?;
+        if amount >= min_drops {
+            required = threshold;
+        }
+    }
+    Ok(required)
+}
+
+/// Check the approval threshold for an escrow holding `amount_drops` at
+/// `current_ledger`, honouring the amount tiers or decay schedule if configured.
+fn check_tiered_threshold(data: &[u8], amount_drops: Option<u64>, current_ledger: u32) -> i32 {
+    match required_threshold(data, amount_drops, current_ledger) {
+        Ok(threshold) => check_approvals_meet(data, threshold),
+        Err(code) => code,
+    }
+}
+
+/// Check the mandatory challenge period after quorum.
+/// With `challenge_ledgers=N`, finish() must wait until N ledgers have passed
+/// since `quorum_ledger`, giving stakeholders time to revoke or freeze.
+/// Without the key there is no challenge period.
+fn check_challenge_period(data: &[u8], current_ledger: u32) -> i32 {
+    let window = match find_value(data, b"challenge_ledgers") {
+        Some(v) => match parse_u32(v) {
+            Some(w) => w,
+            None => return ERR_BAD_CONFIG,
+        },
+        None => return SUCCESS,
+    };
+
+    // Quorum without a recorded start (e.g. approvals preset at EscrowCreate)
+    // can't prove the window has elapsed — unless the threshold decayed onto
+    // the existing approvals, in which case quorum began with that step
+    let quorum_ledger = match find_value(data, b"quorum_ledger").and_then(parse_u32) {
+        Some(l) => l,
+        None => match current_decay_step(data, current_ledger) {
+            Ok(Some((step, from_ledger, _))) if step > 0 => from_ledger,
+            Ok(_) => return ERR_TOO_EARLY,
+            Err(code) => return code,
+        },
+    };
+
+    if current_ledger >= quorum_ledger.saturating_add(window) {
+        SUCCESS
+    } else {
+        ERR_TOO_EARLY
+    }
+}
+
 /// Check if the escrow's FinishAfter time constraint is satisfied.
 /// `finish_after` is the value from the escrow object (Option<u32>).
 /// The XRPL protocol enforces FinishAfter before the WASM runs,
@@ -327,12 +892,19 @@ fn check_time_lock(finish_after: Option<u32>) -> i32 {
 
 /// Build updated contract data with a new approval recorded.
 /// Returns the new data as bytes and length, or an error.
+///
+/// `threshold` is the approvals this escrow requires (see required_threshold).
+/// If this approval reaches it for the first time, `current_ledger` is
+/// recorded as `quorum_ledger` to start the challenge period.
+/// `caller` may be a deputy of the notary at `notary_index`.
 fn record_approval(
     existing_data: &[u8],
     existing_len: usize,
     notary_index: u8,
     caller: &[u8; ACCOUNT_ID_SIZE],
     tx_sequence: u32,
+    current_ledger: u32,
+    threshold: u8,
 ) -> Result<([u8; 4096], usize), i32> {
     let mut new_data = [0u8; 4096];
 
@@ -341,11 +913,36 @@ fn record_approval(
     let approval_key_len = build_indexed_key(b"approval_", notary_index, &mut approval_key_buf);
     let approval_key = &approval_key_buf[..approval_key_len];
 
-    if let Some(val) = find_value(existing_data, approval_key) {
-        if val == b"1" {
-            return Err(ERR_ALREADY_APPROVED);
-        }
+    // An approval from an earlier round no longer counts and is replaced
+    let round = approval_round(existing_data)?;
+    if has_current_approval(existing_data, notary_index, round) {
+        return Err(ERR_ALREADY_APPROVED);
     }
+    let mut round_key_buf = [0u8; 16];
+    let round_key_len = build_indexed_key(b"approve_round_", notary_index, &mut round_key_buf);
+    let round_key = &round_key_buf[..round_key_len];
+
+    let mut approver_key_buf = [0u8; 16];
+    let approver_key_len = build_indexed_key(b"approver_", notary_index, &mut approver_key_buf);
+    let approver_key = &approver_key_buf[..approver_key_len];
+    let mut seq_key_buf = [0u8; 16];
+    let seq_key_len = build_indexed_key(b"approve_seq_", notary_index, &mut seq_key_buf);
+    let seq_key = &seq_key_buf[..seq_key_len];
+    let mut approve_ledger_key_buf = [0u8; 16];
+    let approve_ledger_key_len = build_indexed_key(b"approve_ledger_", notary_index, &mut approve_ledger_key_buf);
+    let approve_ledger_key = &approve_ledger_key_buf[..approve_ledger_key_len];
+    let mut deputy_key_buf = [0u8; 16];
+    let deputy_key_len = build_indexed_key(b"approve_deputy_", notary_index, &mut deputy_key_buf);
+    let deputy_key = &deputy_key_buf[..deputy_key_len];
+
+    // A deputy approves in its principal's name; both go in the audit trail
+    let mut caller_hex = [0u8; ACCOUNT_ID_SIZE * 2];
+    encode_hex(caller, &mut caller_hex);
+    let principal_hex = match listed_notary_hex(existing_data, notary_index)? {
+        Some(hex) if hex.len() == caller_hex.len() => hex,
+        _ => &caller_hex[..],
+    };
+    let by_deputy = principal_hex != &caller_hex[..];
 
     // Get current approval count and increment
     let current_count = find_value(existing_data, b"approval_count")
@@ -353,44 +950,13 @@ fn record_approval(
         .unwrap_or(0);
     let new_count = current_count + 1;
 
-    // Copy existing data then append/update our fields
-    // Strategy: copy all existing entries, then overwrite approval_X and approval_count
-    let mut pos = 0;
-
-    // Copy existing entries, skipping the ones we'll update
-    let mut scan = 0;
-    while scan < existing_len {
-        let entry_end = existing_data[scan..existing_len].iter()
-            .position(|&b| b == b';')
-            .map(|p| scan + p)
-            .unwrap_or(existing_len);
-
-        let entry = &existing_data[scan..entry_end];
-
-        // Skip entries we're going to rewrite
-        let skip = if let Some(eq) = entry.iter().position(|&b| b == b'=') {
-            let k = &entry[..eq];
-            k == approval_key || k == b"approval_count"
-                || (k.len() > 9 && &k[..9] == b"approver_")
-                || (k.len() > 12 && &k[..12] == b"approve_seq_")
-        } else {
-            false
-        };
-
-        if !skip && !entry.is_empty() {
-            if pos > 0 {
-                pos = write_separator(&mut new_data, pos);
-            }
-            // Copy the entry as-is
-            let elen = entry.len();
-            if pos + elen <= new_data.len() {
-                new_data[pos..pos + elen].copy_from_slice(entry);
-                pos += elen;
-            }
-        }
-
-        scan = entry_end + 1;
-    }
+    // Copy existing entries, skipping the ones we're going to rewrite.
+    // The audit trail only names the latest approver.
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == approval_key || k == b"approval_count" || k == b"last_activity_ledger"
+            || k.starts_with(b"approver_") || k.starts_with(b"approve_seq_") || k.starts_with(b"approve_deputy_")
+            || k == approve_ledger_key || k == round_key
+    });
 
     // Add approval_X=1
     if pos > 0 {
@@ -404,27 +970,54 @@ fn record_approval(
     pos = write_entry(&mut new_data, pos, b"approval_count", &count_digit);
     // Record who approved and when (audit trail)
     pos = write_separator(&mut new_data, pos);
-    let mut caller_hex = [0u8; ACCOUNT_ID_SIZE * 2];
-    encode_hex(caller, &mut caller_hex);
-    let mut approver_key_buf = [0u8; 16];
-    let approver_key_len = build_indexed_key(b"approver_", notary_index, &mut approver_key_buf);
-    pos = write_entry(&mut new_data, pos, &approver_key_buf[..approver_key_len], &caller_hex);
+    pos = write_entry(&mut new_data, pos, approver_key, principal_hex);
+    if by_deputy {
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, deputy_key, &caller_hex);
+    }
 
     pos = write_separator(&mut new_data, pos);
     let mut seq_buf = [0u8; 10];
     let seq_len = format_u32(tx_sequence, &mut seq_buf);
-    let mut seq_key_buf = [0u8; 16];
-    let seq_key_len = build_indexed_key(b"approve_seq_", notary_index, &mut seq_key_buf);
-    pos = write_entry(&mut new_data, pos, &seq_key_buf[..seq_key_len], &seq_buf[..seq_len]);
+    pos = write_entry(&mut new_data, pos, seq_key, &seq_buf[..seq_len]);
+
+    // Approval activity resets the dead-man switch
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+    // ...and dates the approval for revocation_policy's window
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, approve_ledger_key, &ledger_buf[..ledger_len]);
+    let mut round_buf = [0u8; 10];
+    let round_len = format_u32(round, &mut round_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, round_key, &round_buf[..round_len]);
+
+    // Start the challenge period the first time quorum is reached.
+    // An existing quorum_ledger was copied above and is left untouched.
+    if new_count >= threshold && find_value(existing_data, b"quorum_ledger").is_none() {
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, b"quorum_ledger", &ledger_buf[..ledger_len]);
+    }
 
     Ok((new_data, pos))
 }
 
 /// Build updated contract data with an approval revoked.
+/// If the revocation drops the count below `threshold`, the recorded
+/// `quorum_ledger` is cleared so a later quorum restarts the challenge period.
+/// `current_ledger` is recorded as approval activity for the dead-man switch.
+///
+/// Withdrawing an approval must be allowed by `revocation_policy`
+/// (ERR_REVOCATION_LOCKED otherwise), and the policy it was withdrawn under
+/// is recorded as `last_revocation_policy` / `last_revocation_ledger`.
 fn record_revocation(
     existing_data: &[u8],
     existing_len: usize,
     notary_index: u8,
+    current_ledger: u32,
+    threshold: u8,
 ) -> Result<([u8; 4096], usize), i32> {
     let mut new_data = [0u8; 4096];
 
@@ -432,10 +1025,18 @@ fn record_revocation(
     let approval_key_len = build_indexed_key(b"approval_", notary_index, &mut approval_key_buf);
     let approval_key = &approval_key_buf[..approval_key_len];
 
-    // Check if this notary even has an approval to revoke
-    let was_approved = find_value(existing_data, approval_key)
-        .map(|v| v == b"1")
-        .unwrap_or(false);
+    // Check if this notary even has an approval to revoke; one from an
+    // earlier round was already taken out of approval_count
+    let was_approved = has_current_approval(existing_data, notary_index, approval_round(existing_data)?);
+
+    let mut approve_ledger_key_buf = [0u8; 16];
+    let approve_ledger_key_len = build_indexed_key(b"approve_ledger_", notary_index, &mut approve_ledger_key_buf);
+    let approve_ledger_key = &approve_ledger_key_buf[..approve_ledger_key_len];
+    let policy = parse_revocation_policy(existing_data)?;
+    if was_approved {
+        let approved_at = find_value(existing_data, approve_ledger_key).and_then(parse_u32);
+        check_revocation_allowed(existing_data, policy, approved_at, current_ledger)?;
+    }
 
     // Get current count and decrement if was approved
     let current_count = find_value(existing_data, b"approval_count")
@@ -446,40 +1047,16 @@ fn record_revocation(
     } else {
         current_count
     };
+    let quorum_lost = new_count < threshold;
 
     // Rebuild data
-    let mut pos = 0;
-    let mut scan = 0;
-    while scan < existing_len {
-        let entry_end = existing_data[scan..existing_len].iter()
-            .position(|&b| b == b';')
-            .map(|p| scan + p)
-            .unwrap_or(existing_len);
-
-        let entry = &existing_data[scan..entry_end];
-
-        let skip = if let Some(eq) = entry.iter().position(|&b| b == b'=') {
-            let k = &entry[..eq];
-            k == approval_key || k == b"approval_count"
-                || (k.len() > 9 && &k[..9] == b"approver_")
-                || (k.len() > 12 && &k[..12] == b"approve_seq_")
-        } else {
-            false
-        };
-
-        if !skip && !entry.is_empty() {
-            if pos > 0 {
-                pos = write_separator(&mut new_data, pos);
-            }
-            let elen = entry.len();
-            if pos + elen <= new_data.len() {
-                new_data[pos..pos + elen].copy_from_slice(entry);
-                pos += elen;
-            }
-        }
-
-        scan = entry_end + 1;
-    }
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == approval_key || k == b"approval_count" || k == b"last_activity_ledger"
+            || k.starts_with(b"approver_") || k.starts_with(b"approve_seq_") || k.starts_with(b"approve_deputy_")
+            || k == approve_ledger_key
+            || (was_approved && (k == b"last_revocation_policy" || k == b"last_revocation_ledger"))
+            || (quorum_lost && k == b"quorum_ledger")
+    });
 
     // Write approval_X=0
     if pos > 0 {
@@ -492,6 +1069,16 @@ fn record_revocation(
     let count_digit = [b'0' + new_count];
     pos = write_entry(&mut new_data, pos, b"approval_count", &count_digit);
 
+    // Revocation is activity too — it shows the notaries are still around
+    pos = write_separator(&mut new_data, pos);
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+
+    if was_approved {
+        pos = write_revocation_audit(&mut new_data, pos, policy, current_ledger);
+    }
+
     Ok((new_data, pos))
 }
 
@@ -505,36 +1092,9 @@ fn record_audit(
     let mut new_data = [0u8; 4096];
 
     // Rebuild data, skipping old audit fields
-    let mut pos = 0;
-    let mut scan = 0;
-    while scan < existing_len {
-        let entry_end = existing_data[scan..existing_len].iter()
-            .position(|&b| b == b';')
-            .map(|p| scan + p)
-            .unwrap_or(existing_len);
-
-        let entry = &existing_data[scan..entry_end];
-
-        let skip = if let Some(eq) = entry.iter().position(|&b| b == b'=') {
-            let k = &entry[..eq];
-            k == b"last_result" || k == b"last_attempt_seq"
-        } else {
-            false
-        };
-
-        if !skip && !entry.is_empty() {
-            if pos > 0 {
-                pos = write_separator(&mut new_data, pos);
-            }
-            let elen = entry.len();
-            if pos + elen <= new_data.len() {
-                new_data[pos..pos + elen].copy_from_slice(entry);
-                pos += elen;
-            }
-        }
-
-        scan = entry_end + 1;
-    }
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == b"last_result" || k == b"last_attempt_seq"
+    });
 
     // Append last_result
     if pos > 0 {
@@ -549,6 +1109,21 @@ fn record_audit(
         -5 => b"host_call_err",
         -6 => b"bad_config",
         -8 => b"cooldown",
+        -9 => b"price_condition",
+        -10 => b"oracle_stale",
+        -11 => b"no_credential",
+        -12 => b"bad_preimage",
+        -13 => b"dependency_pending",
+        -14 => b"illegal_transition",
+        -15 => b"immutable_key",
+        -16 => b"proposal_invalid",
+        -17 => b"upgrade_invalid",
+        -18 => b"policy_unmet",
+        -19 => b"destination_unacked",
+        -20 => b"aborted",
+        -21 => b"revocation_locked",
+        -22 => b"reveal_invalid",
+        -23 => b"phase_closed",
         _ => b"unknown",
     };
     pos = write_entry(&mut new_data, pos, b"last_result", result_str);
@@ -584,740 +1159,6887 @@ fn format_u32(mut value: u32, out: &mut [u8]) -> usize {
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-// WASM ENTRY POINTS
+// REVOCATION POLICY — when a notary may take an approval back
+//
+//   revocation_policy=always                        (default) any time
+//   revocation_policy=before_quorum                 until quorum is reached
+//   revocation_policy=never                         approvals are final
+//   revocation_policy=within_n_ledgers_of_approval  for
+//       `revocation_window_ledgers` ledgers after the notary approved
+//
+// before_quorum locks every approval once `quorum_ledger` is recorded,
+// so nobody can pull out after the others relied on quorum. A refused
+// revocation fails with ERR_REVOCATION_LOCKED.
 // ═══════════════════════════════════════════════════════════════════════
 
-/// Main entry point — called by rippled when someone submits EscrowFinish.
-/// Checks all conditions and returns positive to release funds.
-#[cfg(target_arch = "wasm32")]
-#[unsafe(no_mangle)]
-pub extern "C" fn finish() -> i32 {
-    let tx = get_current_escrow_finish();
-    let escrow = get_current_escrow();
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum RevocationPolicy {
+    Always,
+    BeforeQuorum,
+    Never,
+    /// Ledgers after approval during which it can be revoked
+    WithinLedgers(u32),
+}
 
-    // ─── Read transaction account (who is calling finish?) ───
-    let _ = trace(">>> Condition 1: caller authorization");
-    let caller = match tx.get_account() {
-        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
-        xrpl_wasm_stdlib::host::Result::Err(_) => {
-            let _ = trace("!!! Failed to read tx account");
-            return ERR_HOST_CALL;
+impl RevocationPolicy {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            RevocationPolicy::Always => b"always",
+            RevocationPolicy::BeforeQuorum => b"before_quorum",
+            RevocationPolicy::Never => b"never",
+            RevocationPolicy::WithinLedgers(_) => b"within_n_ledgers_of_approval",
         }
-    };
+    }
+}
 
-    // ─── Read contract data from escrow ───
-    let contract_data = match escrow.get_data() {
-        xrpl_wasm_stdlib::host::Result::Ok(data) => data,
-        xrpl_wasm_stdlib::host::Result::Err(_) => {
-            let _ = trace("!!! Failed to read contract data");
-            return ERR_DATA_READ;
+/// Read `revocation_policy` (and its window). Absent means always.
+fn parse_revocation_policy(data: &[u8]) -> Result<RevocationPolicy, i32> {
+    match find_value(data, b"revocation_policy") {
+        None | Some(b"always") => Ok(RevocationPolicy::Always),
+        Some(b"before_quorum") => Ok(RevocationPolicy::BeforeQuorum),
+        Some(b"never") => Ok(RevocationPolicy::Never),
+        Some(b"within_n_ledgers_of_approval") => find_value(data, b"revocation_window_ledgers")
+            .and_then(parse_u32)
+            .map(RevocationPolicy::WithinLedgers)
+            .ok_or(ERR_BAD_CONFIG),
+        Some(_) => Err(ERR_BAD_CONFIG),
+    }
+}
+
+/// Whether an approval given at `approved_at` may be withdrawn now.
+/// An approval with no recorded ledger can't be placed in a window.
+fn check_revocation_allowed(
+    data: &[u8],
+    policy: RevocationPolicy,
+    approved_at: Option<u32>,
+    current_ledger: u32,
+) -> Result<(), i32> {
+    let allowed = match policy {
+        RevocationPolicy::Always => true,
+        RevocationPolicy::BeforeQuorum => find_value(data, b"quorum_ledger").is_none(),
+        RevocationPolicy::Never => false,
+        RevocationPolicy::WithinLedgers(window) => {
+            let approved_at = approved_at.ok_or(ERR_BAD_CONFIG)?;
+            current_ledger <= approved_at.saturating_add(window)
         }
     };
-    let data = &contract_data.data[..contract_data.len];
+    if allowed {
+        Ok(())
+    } else {
+        Err(ERR_REVOCATION_LOCKED)
+    }
+}
 
-    // ─── Check caller is a registered notary ───
-    match check_caller_is_notary(data, &caller.0) {
-        Ok(_) => { let _ = trace("    OK caller is authorized notary"); }
-        Err(code) => {
-            let _ = trace("!!! Caller is not an authorized notary");
-            return code;
+/// Append last_revocation_policy=<policy>;last_revocation_ledger=<ledger>.
+fn write_revocation_audit(data: &mut [u8], mut pos: usize, policy: RevocationPolicy, current_ledger: u32) -> usize {
+    pos = write_separator(data, pos);
+    pos = write_entry(data, pos, b"last_revocation_policy", policy.as_bytes());
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_separator(data, pos);
+    write_entry(data, pos, b"last_revocation_ledger", &ledger_buf[..ledger_len])
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// ESCROW LIFECYCLE STATE MACHINE
+//
+//   Configured ──approve──▶ Collecting ──approve──▶ Quorum ──release──▶ Released
+//        ▲                      │  ▲                  │
+//        └──────revoke──────────┘  └─────revoke───────┘
+//
+//   Configured / Collecting / Quorum ──freeze──▶ Frozen ──unfreeze──▶ (by tally)
+//   any state but Released ──abort──▶ Aborted
+//
+// The state is persisted under `state`. Every entry point computes its
+// event and goes through transition(); anything not in the table is
+// rejected with ERR_ILLEGAL_TRANSITION — or ERR_ABORTED once the notaries
+// have voted the deal off. Released and Aborted are terminal.
+// Release is legal from any open state — finish() decides whether the
+// release conditions hold (hash-lock OR mode or a decayed threshold can
+// release without a recorded Quorum), the state machine decides whether
+// the escrow is still open at all. Records that don't move the tally
+// (deputies, consent, acknowledgements, sealed votes, proposals) go
+// through the Annotate event, so they stop once the escrow is settled.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Persisted lifecycle state of the escrow.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum EscrowState {
+    Configured,
+    Collecting,
+    Quorum,
+    Released,
+    Frozen,
+    Aborted,
+}
+
+impl EscrowState {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            EscrowState::Configured => b"configured",
+            EscrowState::Collecting => b"collecting",
+            EscrowState::Quorum => b"quorum",
+            EscrowState::Released => b"released",
+            EscrowState::Frozen => b"frozen",
+            EscrowState::Aborted => b"aborted",
         }
     }
 
-    // ─── Condition 2: time-lock via FinishAfter ───
-    let _ = trace(">>> Condition 2: time-lock");
-    let finish_after = match escrow.get_finish_after() {
-        xrpl_wasm_stdlib::host::Result::Ok(val) => val,
-        xrpl_wasm_stdlib::host::Result::Err(_) => {
-            let _ = trace("!!! Failed to read finish_after");
-            return ERR_HOST_CALL;
+    fn parse(value: &[u8]) -> Option<EscrowState> {
+        match value {
+            b"configured" => Some(EscrowState::Configured),
+            b"collecting" => Some(EscrowState::Collecting),
+            b"quorum" => Some(EscrowState::Quorum),
+            b"released" => Some(EscrowState::Released),
+            b"frozen" => Some(EscrowState::Frozen),
+            b"aborted" => Some(EscrowState::Aborted),
+            _ => None,
         }
-    };
-    let time_result = check_time_lock(finish_after);
-    if time_result != SUCCESS {
-        let _ = trace("!!! Time-lock not satisfied");
-        return time_result;
     }
-    let _ = trace("    OK time-lock passed");
-
-    // ─── Condition 3: approval threshold ───
-    let _ = trace(">>> Condition 3: approval threshold");
-    let approval_result = check_approval_threshold(data);
-    if approval_result != SUCCESS {
-        let _ = trace("!!! Approval threshold not met");
-
-        // Write audit trail for the denial
-        let tx_seq = tx.get_sequence()
-            .unwrap_or(0);
-        let (audit_data, audit_len) = record_audit(data, contract_data.len, approval_result, tx_seq);
-        let mut update = ContractData {
-            data: [0u8; XRPL_CONTRACT_DATA_SIZE],
-            len: audit_len,
-        };
-        update.data[..audit_len].copy_from_slice(&audit_data[..audit_len]);
-        let _ = <xrpl_wasm_stdlib::core::ledger_objects::current_escrow::CurrentEscrow as CurrentEscrowFields>::update_current_escrow_data(update);
 
-        return approval_result;
+    /// Open states still accept approvals, revocations and release.
+    fn is_open(self) -> bool {
+        matches!(self, EscrowState::Configured | EscrowState::Collecting | EscrowState::Quorum)
     }
-    let _ = trace("    OK approval threshold met");
+}
 
-    // ─── All conditions passed ───
-    let _ = trace("=== ALL CONDITIONS MET — releasing funds ===");
+/// Approval tally after an event, used to pick the resulting open state.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Tally {
+    approvals: u8,
+    quorum: bool,
+}
 
-    // Record successful release in audit trail
-    let tx_seq = tx.get_sequence().unwrap_or(0);
-    let (audit_data, audit_len) = record_audit(data, contract_data.len, SUCCESS, tx_seq);
-    let mut update = ContractData {
-        data: [0u8; XRPL_CONTRACT_DATA_SIZE],
-        len: audit_len,
-    };
-    update.data[..audit_len].copy_from_slice(&audit_data[..audit_len]);
-    let _ = <xrpl_wasm_stdlib::core::ledger_objects::current_escrow::CurrentEscrow as CurrentEscrowFields>::update_current_escrow_data(update);
+impl Tally {
+    /// Read the tally from contract data against a required threshold.
+    fn of(data: &[u8], threshold: u8) -> Tally {
+        let approvals = find_value(data, b"approval_count")
+            .and_then(parse_u8_digit)
+            .unwrap_or(0);
+        Tally { approvals, quorum: approvals >= threshold }
+    }
 
-    SUCCESS
+    fn state(self) -> EscrowState {
+        if self.quorum {
+            EscrowState::Quorum
+        } else if self.approvals > 0 {
+            EscrowState::Collecting
+        } else {
+            EscrowState::Configured
+        }
+    }
 }
 
-/// Called by a notary to record their approval.
-/// Each notary can only approve once. Requires M-of-N threshold.
-#[cfg(target_arch = "wasm32")]
-#[unsafe(no_mangle)]
-pub extern "C" fn set_approval() -> i32 {
-    let _ = trace(">>> set_approval called");
-    let tx = get_current_escrow_finish();
-    let escrow = get_current_escrow();
+/// Something an entry point wants to do to the escrow.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LifecycleEvent {
+    /// A notary approved; carries the tally afterwards
+    Approve(Tally),
+    /// A notary revoked; carries the tally afterwards
+    Revoke(Tally),
+    /// The dead-man switch handed control to the backup set
+    NotarySetSwitched,
+    /// A new approval round voided the outstanding approvals
+    RoundReset,
+    /// finish() is releasing the funds
+    Release,
+    Freeze,
+    /// Carries the tally to resume at
+    Unfreeze(Tally),
+    Abort,
+    /// A governance change was applied; carries the tally against the
+    /// threshold it leaves
+    Reconfigured(Tally),
+    /// Something that leaves the tally alone was recorded: a deputy,
+    /// consent, destination acknowledgement, sealed vote or proposal step
+    Annotate,
+}
 
-    let caller = match tx.get_account() {
-        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
-        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
-    };
+/// Read the persisted state. Absent means a freshly created escrow.
+fn recorded_state(data: &[u8]) -> Result<EscrowState, i32> {
+    match find_value(data, b"state") {
+        None => Ok(EscrowState::Configured),
+        Some(v) => EscrowState::parse(v).ok_or(ERR_BAD_CONFIG),
+    }
+}
 
-    let contract_data = match escrow.get_data() {
-        xrpl_wasm_stdlib::host::Result::Ok(data) => data,
-        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_DATA_READ,
-    };
-    let data = &contract_data.data[..contract_data.len];
+/// The transition table. Returns the next state, or ERR_ILLEGAL_TRANSITION
+/// (ERR_ABORTED for anything attempted on an aborted escrow).
+fn transition(state: EscrowState, event: LifecycleEvent) -> Result<EscrowState, i32> {
+    use EscrowState::*;
+    use LifecycleEvent::*;
+
+    match (state, event) {
+        (Aborted, _) => Err(ERR_ABORTED),
+        (s, Approve(tally)) | (s, Revoke(tally)) if s.is_open() => Ok(tally.state()),
+        // The backup set or a new round starts from zero approvals; a
+        // freeze outlives either
+        (s, NotarySetSwitched) | (s, RoundReset) if s.is_open() => Ok(Configured),
+        (Frozen, NotarySetSwitched) | (Frozen, RoundReset) => Ok(Frozen),
+        (s, Release) if s.is_open() => Ok(Released),
+        (s, Freeze) if s.is_open() => Ok(Frozen),
+        (Frozen, Unfreeze(tally)) => Ok(tally.state()),
+        (s, Reconfigured(tally)) if s.is_open() => Ok(tally.state()),
+        (Frozen, Reconfigured(_)) => Ok(Frozen),
+        (s, Annotate) if s.is_open() || s == Frozen => Ok(s),
+        (s, Abort) if s.is_open() || s == Frozen => Ok(Aborted),
+        _ => Err(ERR_ILLEGAL_TRANSITION),
+    }
+}
 
-    // Verify caller is a registered notary and get their index
-    let notary_index = match check_caller_is_notary(data, &caller.0) {
-        Ok(idx) => idx,
-        Err(code) => {
-            let _ = trace("!!! Caller not authorized to approve");
-            return code;
-        }
-    };
+/// Apply `event` to the recorded state and return the data with the new
+/// `state` persisted.
+fn record_transition(
+    existing_data: &[u8],
+    existing_len: usize,
+    event: LifecycleEvent,
+) -> Result<([u8; 4096], usize), i32> {
+    let next = transition(recorded_state(existing_data)?, event)?;
 
-    let tx_seq = tx.get_sequence().unwrap_or(0);
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| k == b"state");
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"state", next.as_bytes());
 
-    // Record the approval
-    let (new_data, new_len) = match record_approval(
-        data, contract_data.len, notary_index, &caller.0, tx_seq
-    ) {
-        Ok((d, l)) => (d, l),
-        Err(code) => {
-            let _ = trace("!!! Failed to record approval");
-            return code;
-        }
-    };
+    Ok((new_data, pos))
+}
 
-    // Write updated data back to the escrow
-    let mut update = ContractData {
-        data: [0u8; XRPL_CONTRACT_DATA_SIZE],
-        len: new_len,
-    };
-    update.data[..new_len].copy_from_slice(&new_data[..new_len]);
+// ═══════════════════════════════════════════════════════════════════════
+// APPROVAL ROUNDS — voiding every outstanding approval at once
+//
+// Each approval records the round it was given in (`approve_round_N`).
+// new_round() moves `approval_round` on by one: the Owner can do it
+// alone, or the notaries vote for it (`round_vote_N`) until as many
+// agree as releasing needs. Approvals from earlier rounds stay in the
+// data as a record but no longer count: approval_count and quorum_ledger
+// restart, and the notaries approve again. Party consent (`consent_*`)
+// was given in the old round and is cleared too; a destination
+// acknowledgement (`dest_ack*`) only proves control of the account, so it
+// survives. A new round withdraws every current approval, so it is
+// refused with ERR_REVOCATION_LOCKED where `revocation_policy` would not
+// let one of them be revoked. Rounds need the data notary list; a
+// notaries_from escrow has no round, nor does a commit-reveal one.
+// ═══════════════════════════════════════════════════════════════════════
 
-    match <xrpl_wasm_stdlib::core::ledger_objects::current_escrow::CurrentEscrow as CurrentEscrowFields>::update_current_escrow_data(update) {
-        xrpl_wasm_stdlib::host::Result::Ok(_) => {
-            let _ = trace("    OK approval recorded");
-            SUCCESS
-        }
-        xrpl_wasm_stdlib::host::Result::Err(_) => ERR_DATA_READ,
+/// The current `approval_round`. Absent means round 0.
+fn approval_round(data: &[u8]) -> Result<u32, i32> {
+    match find_value(data, b"approval_round") {
+        None => Ok(0),
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG),
     }
 }
 
-/// Called by a notary to revoke their own approval.
-/// Only the notary who approved can revoke their own approval.
-#[cfg(target_arch = "wasm32")]
-#[unsafe(no_mangle)]
-pub extern "C" fn revoke_approval() -> i32 {
-    let _ = trace(">>> revoke_approval called");
-    let tx = get_current_escrow_finish();
-    let escrow = get_current_escrow();
+/// Whether notary `index` has approved in `round`. Approvals recorded
+/// without a round predate rounds and belong to round 0.
+fn has_current_approval(data: &[u8], index: u8, round: u32) -> bool {
+    let mut key_buf = [0u8; 16];
+    let key_len = build_indexed_key(b"approval_", index, &mut key_buf);
+    if find_value(data, &key_buf[..key_len]) != Some(b"1") {
+        return false;
+    }
+    let key_len = build_indexed_key(b"approve_round_", index, &mut key_buf);
+    match find_value(data, &key_buf[..key_len]) {
+        None => round == 0,
+        Some(v) => parse_u32(v) == Some(round),
+    }
+}
 
-    let caller = match tx.get_account() {
-        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
-        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
-    };
+/// Start the next approval round.
+fn start_round(
+    existing_data: &[u8],
+    existing_len: usize,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    // Sealed votes belong to the one commit phase there is
+    if parse_notaries_from(existing_data)?.is_some() || parse_commit_reveal(existing_data)?.is_some() {
+        return Err(ERR_BAD_CONFIG);
+    }
+    let round = approval_round(existing_data)?;
+    let next_round = round.checked_add(1).ok_or(ERR_BAD_CONFIG)?;
+    let next = transition(recorded_state(existing_data)?, LifecycleEvent::RoundReset)?;
+
+    // A reset withdraws every current approval, so each must be revocable
+    let policy = parse_revocation_policy(existing_data)?;
+    let mut key_buf = [0u8; 16];
+    for i in 0..MAX_NOTARIES as u8 {
+        if has_current_approval(existing_data, i, round) {
+            let key_len = build_indexed_key(b"approve_ledger_", i, &mut key_buf);
+            let approved_at = find_value(existing_data, &key_buf[..key_len]).and_then(parse_u32);
+            check_revocation_allowed(existing_data, policy, approved_at, current_ledger)?;
+        }
+    }
 
-    let contract_data = match escrow.get_data() {
-        xrpl_wasm_stdlib::host::Result::Ok(data) => data,
-        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_DATA_READ,
-    };
-    let data = &contract_data.data[..contract_data.len];
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == b"approval_round"
+            || k == b"round_ledger"
+            || k == b"approval_count"
+            || k == b"quorum_ledger"
+            || k == b"state"
+            || k.starts_with(b"round_vote_")
+            || CONSENT_KEYS.iter().any(|&(_, key)| k == key)
+    });
+
+    let mut num_buf = [0u8; 10];
+    let num_len = format_u32(next_round, &mut num_buf);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"approval_round", &num_buf[..num_len]);
+    let num_len = format_u32(current_ledger, &mut num_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"round_ledger", &num_buf[..num_len]);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"approval_count", b"0");
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"state", next.as_bytes());
 
-    // Verify caller is a registered notary
-    let notary_index = match check_caller_is_notary(data, &caller.0) {
-        Ok(idx) => idx,
-        Err(code) => return code,
-    };
+    Ok((new_data, pos))
+}
 
-    // Record the revocation
-    let (new_data, new_len) = match record_revocation(data, contract_data.len, notary_index) {
-        Ok((d, l)) => (d, l),
-        Err(code) => return code,
-    };
+/// Record notary `notary_index`'s vote for the next round, starting it
+/// once `threshold` notaries agree.
+fn record_round_vote(
+    existing_data: &[u8],
+    existing_len: usize,
+    notary_index: u8,
+    threshold: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::RoundReset)?;
+    let next_round = approval_round(existing_data)?.checked_add(1).ok_or(ERR_BAD_CONFIG)?;
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"round_vote_", notary_index, &mut vote_key_buf);
+    let vote_key = &vote_key_buf[..vote_key_len];
+
+    let mut votes: u8 = 0;
+    let mut key_buf = [0u8; 16];
+    for i in 0..MAX_NOTARIES as u8 {
+        let key_len = build_indexed_key(b"round_vote_", i, &mut key_buf);
+        if find_value(existing_data, &key_buf[..key_len]).and_then(parse_u32) == Some(next_round) {
+            if i == notary_index {
+                return Err(ERR_ALREADY_APPROVED);
+            }
+            votes += 1;
+        }
+    }
 
-    let mut update = ContractData {
-        data: [0u8; XRPL_CONTRACT_DATA_SIZE],
-        len: new_len,
-    };
-    update.data[..new_len].copy_from_slice(&new_data[..new_len]);
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == vote_key || k == b"last_activity_ledger"
+    });
+    let mut num_buf = [0u8; 10];
+    let num_len = format_u32(next_round, &mut num_buf);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, vote_key, &num_buf[..num_len]);
+    // Voting is activity for the dead-man switch
+    let num_len = format_u32(current_ledger, &mut num_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &num_buf[..num_len]);
 
-    match <xrpl_wasm_stdlib::core::ledger_objects::current_escrow::CurrentEscrow as CurrentEscrowFields>::update_current_escrow_data(update) {
-        xrpl_wasm_stdlib::host::Result::Ok(_) => {
-            let _ = trace("    OK approval revoked");
-            SUCCESS
-        }
-        xrpl_wasm_stdlib::host::Result::Err(_) => ERR_DATA_READ,
+    if votes + 1 >= threshold {
+        return start_round(&new_data[..pos], pos, current_ledger);
     }
+    Ok((new_data, pos))
 }
 
 // ═══════════════════════════════════════════════════════════════════════
-// TESTS — Run with: cargo test -- --nocapture
+// COMMIT-REVEAL — sealed approvals, opened after a deadline
 //
-// All contract logic is tested via pure functions that don't require
-// the WASM host. This tests the decision logic exhaustively.
+//   commit_deadline=<ledger>   last ledger a vote can be committed
+//   reveal_deadline=<ledger>   last ledger a vote can be revealed
+//
+// Until commit_deadline each notary commits SHA-256(vote || salt), with
+// vote one byte (1 approves, 0 declines) and salt 16-32 random bytes,
+// through set_approval with a "commit=<64 hex>" memo; it may re-commit
+// while the phase is open. After commit_deadline and up to reveal_deadline
+// it reveals with "vote=<0|1>;salt=<hex>". A reveal matching the commitment
+// is recorded, and a revealed 1 is an ordinary approval. Nothing else
+// approves: a plain set_approval and EscrowFinish Signers don't count.
+//
+// The commit phase happens once, so this needs the primary notary list
+// for the whole escrow: no notaries_from, backup set or approval rounds.
 // ═══════════════════════════════════════════════════════════════════════
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    // ─────────────────────────────────────────────────────────────
-    // TEST HELPERS — Build realistic contract data for testing
-    // ─────────────────────────────────────────────────────────────
+/// Smallest salt accepted; a vote is one bit, so the salt is the secret.
+const MIN_SALT_SIZE: usize = 16;
+const MAX_SALT_SIZE: usize = 32;
 
-    /// Create a mock 20-byte AccountID from a simple seed value.
-    /// Each seed produces a unique, deterministic AccountID.
-    fn mock_account(seed: u8) -> [u8; ACCOUNT_ID_SIZE] {
-        let mut id = [0u8; ACCOUNT_ID_SIZE];
-        id[0] = seed;
-        id[19] = seed; // put seed at both ends for distinctness
-        id
-    }
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct CommitReveal {
+    commit_deadline: u32,
+    reveal_deadline: u32,
+}
 
-    /// Encode a mock account as hex string bytes.
-    fn mock_account_hex(seed: u8) -> [u8; 40] {
-        let account = mock_account(seed);
-        let mut hex = [0u8; 40];
-        encode_hex(&account, &mut hex).unwrap();
-        hex
+/// Read the phase deadlines. Without `commit_deadline` approvals are open.
+fn parse_commit_reveal(data: &[u8]) -> Result<Option<CommitReveal>, i32> {
+    let commit_deadline = match find_value(data, b"commit_deadline") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None if find_value(data, b"reveal_deadline").is_some() => return Err(ERR_BAD_CONFIG),
+        None => return Ok(None),
+    };
+    let reveal_deadline = find_value(data, b"reveal_deadline")
+        .and_then(parse_u32)
+        .ok_or(ERR_BAD_CONFIG)?;
+    if reveal_deadline <= commit_deadline
+        || find_value(data, b"notaries_from").is_some()
+        || find_value(data, b"backup_notary_count").is_some()
+    {
+        return Err(ERR_BAD_CONFIG);
     }
+    Ok(Some(CommitReveal { commit_deadline, reveal_deadline }))
+}
 
-    /// Build contract data for a single-notary escrow (threshold=1).
-    fn single_notary_data(notary_seed: u8) -> (Vec<u8>, [u8; ACCOUNT_ID_SIZE]) {
-        let account = mock_account(notary_seed);
-        let hex = mock_account_hex(notary_seed);
-        let mut data = Vec::new();
-        data.extend_from_slice(b"notary_count=1;threshold=1;notary_0=");
-        data.extend_from_slice(&hex);
-        (data, account)
+/// Record notary `notary_index`'s commitment (64 hex of the digest).
+fn record_commit(
+    existing_data: &[u8],
+    existing_len: usize,
+    phases: &CommitReveal,
+    notary_index: u8,
+    digest_hex: &[u8],
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    if current_ledger > phases.commit_deadline {
+        return Err(ERR_PHASE_CLOSED);
     }
+    let mut digest = [0u8; sha256::DIGEST_SIZE];
+    if digest_hex.len() != sha256::DIGEST_SIZE * 2 || decode_hex(digest_hex, &mut digest).is_none() {
+        return Err(ERR_REVEAL);
+    }
+    let mut hex = [0u8; sha256::DIGEST_SIZE * 2];
+    encode_hex(&digest, &mut hex);
 
-    /// Build contract data for a 2-of-3 multi-notary escrow.
+    let mut key_buf = [0u8; 16];
+    let key_len = build_indexed_key(b"vote_commit_", notary_index, &mut key_buf);
+    let key = &key_buf[..key_len];
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == key || k == b"last_activity_ledger"
+    });
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, key, &hex);
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+    Ok((new_data, pos))
+}
+
+/// Parse a reveal memo: "vote=<0|1>;salt=<hex>" into (vote, salt bytes, salt length).
+fn parse_reveal_memo(memo: &[u8]) -> Result<(u8, [u8; MAX_SALT_SIZE], usize), i32> {
+    let vote = match find_value(memo, b"vote") {
+        Some(b"1") => 1,
+        Some(b"0") => 0,
+        _ => return Err(ERR_REVEAL),
+    };
+    let hex = find_value(memo, b"salt").ok_or(ERR_REVEAL)?;
+    let mut salt = [0u8; MAX_SALT_SIZE];
+    if hex.len() > MAX_SALT_SIZE * 2 {
+        return Err(ERR_REVEAL);
+    }
+    match decode_hex(hex, &mut salt) {
+        Some(len) if len >= MIN_SALT_SIZE => Ok((vote, salt, len)),
+        _ => Err(ERR_REVEAL),
+    }
+}
+
+/// Check a reveal against notary `notary_index`'s commitment.
+fn check_reveal(
+    data: &[u8],
+    phases: &CommitReveal,
+    notary_index: u8,
+    vote: u8,
+    salt: &[u8],
+    current_ledger: u32,
+) -> Result<(), i32> {
+    if current_ledger <= phases.commit_deadline {
+        return Err(ERR_TOO_EARLY);
+    }
+    if current_ledger > phases.reveal_deadline {
+        return Err(ERR_PHASE_CLOSED);
+    }
+    let mut key_buf = [0u8; 16];
+    let key_len = build_indexed_key(b"vote_reveal_", notary_index, &mut key_buf);
+    if find_value(data, &key_buf[..key_len]).is_some() {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+
+    let key_len = build_indexed_key(b"vote_commit_", notary_index, &mut key_buf);
+    let mut committed = [0u8; sha256::DIGEST_SIZE];
+    match find_value(data, &key_buf[..key_len]) {
+        Some(hex) if decode_hex(hex, &mut committed) == Some(sha256::DIGEST_SIZE) => {}
+        _ => return Err(ERR_REVEAL),
+    }
+    let mut hasher = sha256::Sha256::new();
+    hasher.update(&[vote]);
+    hasher.update(salt);
+    if hasher.finalize() == committed {
+        Ok(())
+    } else {
+        Err(ERR_REVEAL)
+    }
+}
+
+/// Record a checked reveal as `vote_reveal_N`. A revealed 1 must then be
+/// recorded as the notary's approval with record_approval.
+fn record_reveal(
+    existing_data: &[u8],
+    existing_len: usize,
+    phases: &CommitReveal,
+    notary_index: u8,
+    reveal: (u8, &[u8]),
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    let (vote, salt) = reveal;
+    check_reveal(existing_data, phases, notary_index, vote, salt, current_ledger)?;
+
+    let mut key_buf = [0u8; 16];
+    let key_len = build_indexed_key(b"vote_reveal_", notary_index, &mut key_buf);
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| k == b"last_activity_ledger");
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, &key_buf[..key_len], &[b'0' + vote]);
+
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+    Ok((new_data, pos))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// DEPUTY DELEGATION — a notary's stand-in until a given ledger
+//
+//   deputy_N=<40 hex>        account acting for notary N
+//   deputy_until_N=<ledger>  last ledger on which it may act
+//
+// A listed notary registers or replaces its deputy with set_deputy(),
+// the first Memo carrying "deputy=<40 hex>;until=<ledger>" ("deputy=none"
+// withdraws it). check_caller_is_notary resolves a live deputy to its
+// principal's index, so the two share one approval; record_approval keeps
+// the principal in `approver_N` and the deputy in `approve_deputy_N`.
+//
+// Deputies act through transactions they submit. Signers on an
+// EscrowFinish and the notary role of finish_by/freeze_by still mean
+// listed notaries. Deputies belong to an index, so the dead-man switch
+// drops them along with the primary set.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// The `notary_N` (or `backup_notary_N`) entry of the recorded set.
+fn listed_notary_hex(data: &[u8], index: u8) -> Result<Option<&[u8]>, i32> {
+    let (_, prefix) = notary_set_keys(recorded_notary_set(data)?);
+    let mut key_buf = [0u8; 24];
+    let key_len = build_indexed_key(prefix, index, &mut key_buf);
+    Ok(find_value(data, &key_buf[..key_len]))
+}
+
+/// Index of the notary `caller` is a live deputy for, if any.
+fn find_deputy(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE], current_ledger: u32) -> Option<u8> {
+    let (count_key, _) = notary_set_keys(recorded_notary_set(data).ok()?);
+    let count = find_value(data, count_key).and_then(parse_u8_digit)?;
+    let mut caller_hex = [0u8; ACCOUNT_ID_SIZE * 2];
+    encode_hex(caller, &mut caller_hex);
+
+    let mut key_buf = [0u8; 16];
+    (0..count.min(MAX_NOTARIES as u8)).find(|&i| {
+        let key_len = build_indexed_key(b"deputy_", i, &mut key_buf);
+        if find_value(data, &key_buf[..key_len]) != Some(&caller_hex[..]) {
+            return false;
+        }
+        let key_len = build_indexed_key(b"deputy_until_", i, &mut key_buf);
+        matches!(find_value(data, &key_buf[..key_len]).and_then(parse_u32), Some(until) if current_ledger <= until)
+    })
+}
+
+/// Parse a set_deputy memo: Some((deputy, until)), or None to withdraw.
+fn parse_deputy_memo(memo: &[u8]) -> Result<Option<([u8; ACCOUNT_ID_SIZE], u32)>, i32> {
+    let hex = find_value(memo, b"deputy").ok_or(ERR_BAD_CONFIG)?;
+    if hex == b"none" {
+        return Ok(None);
+    }
+    let mut deputy = [0u8; ACCOUNT_ID_SIZE];
+    if hex.len() != ACCOUNT_ID_SIZE * 2 || decode_hex(hex, &mut deputy) != Some(ACCOUNT_ID_SIZE) {
+        return Err(ERR_BAD_CONFIG);
+    }
+    let until = find_value(memo, b"until").and_then(parse_u32).ok_or(ERR_BAD_CONFIG)?;
+    Ok(Some((deputy, until)))
+}
+
+/// Register (`Some`) or withdraw (`None`) notary `notary_index`'s deputy.
+///
+/// The deputy can't be a listed notary, an excluded party, or a live
+/// deputy of another notary, and its term must not already be over.
+fn record_deputy(
+    existing_data: &[u8],
+    existing_len: usize,
+    notary_index: u8,
+    deputy: Option<([u8; ACCOUNT_ID_SIZE], u32)>,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    let mut deputy_key_buf = [0u8; 16];
+    let deputy_key_len = build_indexed_key(b"deputy_", notary_index, &mut deputy_key_buf);
+    let deputy_key = &deputy_key_buf[..deputy_key_len];
+    let mut until_key_buf = [0u8; 16];
+    let until_key_len = build_indexed_key(b"deputy_until_", notary_index, &mut until_key_buf);
+    let until_key = &until_key_buf[..until_key_len];
+
+    if let Some((account, until)) = &deputy {
+        let (parties, party_count) = excluded_parties(existing_data)?;
+        let other_deputy = find_deputy(existing_data, account, current_ledger).is_some_and(|i| i != notary_index);
+        if *until < current_ledger
+            || check_listed_notary(existing_data, account) != Err(ERR_WRONG_ACCOUNT)
+            || parties[..party_count].contains(account)
+            || other_deputy
+        {
+            return Err(ERR_BAD_CONFIG);
+        }
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == deputy_key || k == until_key
+    });
+    if let Some((account, until)) = deputy {
+        let mut hex = [0u8; ACCOUNT_ID_SIZE * 2];
+        encode_hex(&account, &mut hex);
+        if pos > 0 {
+            pos = write_separator(&mut new_data, pos);
+        }
+        pos = write_entry(&mut new_data, pos, deputy_key, &hex);
+        let mut until_buf = [0u8; 10];
+        let until_len = format_u32(until, &mut until_buf);
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, until_key, &until_buf[..until_len]);
+    }
+    Ok((new_data, pos))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// ABORT VOTE — notaries collectively calling the deal off
+//
+// Revocation only withdraws one notary's yes. With `abort_threshold=M`,
+// notaries can vote_abort(); the M-th vote moves the escrow to Aborted.
+// From then on transition() answers every event with ERR_ABORTED: finish()
+// never releases and approvals are refused, so the funds can only go back
+// through EscrowCancel once CancelAfter has passed. Abort votes are not
+// revocable, and like approvals they are dropped by the dead-man switch.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Votes required to abort. Aborting is off without `abort_threshold`.
+fn abort_threshold(data: &[u8]) -> Result<u8, i32> {
+    match find_value(data, b"abort_threshold").and_then(parse_u8_digit) {
+        Some(t) if t >= 1 => Ok(t),
+        _ => Err(ERR_BAD_CONFIG),
+    }
+}
+
+/// Record `notary_index`'s abort vote; the deciding vote also records the
+/// Abort transition and `abort_ledger`.
+fn record_abort_vote(
+    existing_data: &[u8],
+    existing_len: usize,
+    notary_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    let threshold = abort_threshold(existing_data)?;
+    // Refuses at once on a released or already aborted escrow
+    transition(recorded_state(existing_data)?, LifecycleEvent::Abort)?;
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"abort_vote_", notary_index, &mut vote_key_buf);
+    let vote_key = &vote_key_buf[..vote_key_len];
+    if find_value(existing_data, vote_key).is_some() {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+    let votes = find_value(existing_data, b"abort_votes")
+        .and_then(parse_u8_digit)
+        .unwrap_or(0)
+        + 1;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == b"abort_votes" || k == b"last_activity_ledger"
+    });
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, vote_key, b"1");
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"abort_votes", &[b'0' + votes]);
+
+    // Voting is notary activity for the dead-man switch
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+
+    if votes < threshold {
+        return Ok((new_data, pos));
+    }
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"abort_ledger", &ledger_buf[..ledger_len]);
+    record_transition(&new_data[..pos], pos, LifecycleEvent::Abort)
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// DEAD-MAN SWITCH — backup notary set after inactivity
+//
+// With `inactivity_ledgers=N`, if no approval or revocation has been
+// recorded for N ledgers (counted from `last_activity_ledger`, or from
+// `created_ledger` before the first one), the backup set
+// (`backup_notary_count`, `backup_notary_N`, `backup_threshold`) becomes
+// authoritative. The switch is one-way and is persisted as
+// `notary_set=backup;notary_set_ledger=<ledger>` by the next entry point
+// that runs. Primary approvals stop counting at the switch.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Which notary list is authoritative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NotarySet {
+    Primary,
+    Backup,
+}
+
+/// The notary set recorded in the data (ignores pending inactivity).
+fn recorded_notary_set(data: &[u8]) -> Result<NotarySet, i32> {
+    match find_value(data, b"notary_set") {
+        None | Some(b"primary") => Ok(NotarySet::Primary),
+        Some(b"backup") => Ok(NotarySet::Backup),
+        Some(_) => Err(ERR_BAD_CONFIG),
+    }
+}
+
+/// Validate the backup notary set configuration.
+fn validate_backup_set(data: &[u8]) -> Result<(), i32> {
+    let count = find_value(data, b"backup_notary_count")
+        .and_then(parse_u8_digit)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let threshold = find_value(data, b"backup_threshold")
+        .and_then(parse_u8_digit)
+        .ok_or(ERR_BAD_CONFIG)?;
+
+    if count == 0 || count as usize > MAX_NOTARIES || threshold == 0 || threshold > count {
+        return Err(ERR_BAD_CONFIG);
+    }
+    check_notary_conflicts(data, count, b"backup_notary_")
+}
+
+/// Ledger at which the dead-man switch fires, or None if it is not configured.
+fn inactivity_deadline(data: &[u8]) -> Result<Option<u32>, i32> {
+    let window = match find_value(data, b"inactivity_ledgers") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => return Ok(None),
+    };
+    let baseline = find_value(data, b"last_activity_ledger")
+        .or_else(|| find_value(data, b"created_ledger"))
+        .and_then(parse_u32)
+        .ok_or(ERR_BAD_CONFIG)?;
+    Ok(Some(baseline.saturating_add(window)))
+}
+
+/// Determine which notary set is authoritative at `current_ledger`.
+fn active_notary_set(data: &[u8], current_ledger: u32) -> Result<NotarySet, i32> {
+    if recorded_notary_set(data)? == NotarySet::Backup {
+        return Ok(NotarySet::Backup);
+    }
+    match inactivity_deadline(data)? {
+        Some(deadline) if current_ledger >= deadline => Ok(NotarySet::Backup),
+        _ => Ok(NotarySet::Primary),
+    }
+}
+
+/// Persist the dead-man switch if it has fired but is not yet recorded.
+/// Returns Ok(None) when nothing changes.
+///
+/// The rebuilt data drops primary approvals (`approval_N`, `approval_count`,
+/// `quorum_ledger`) and records `notary_set=backup`, `notary_set_ledger`
+/// and a fresh `approval_count=0` for the backup set to build on. Votes on
+/// an open governance proposal or pending upgrade are dropped as well —
+/// they were cast by index into the primary list. The lifecycle state goes
+/// through the NotarySetSwitched transition.
+fn apply_inactivity_switch(
+    existing_data: &[u8],
+    existing_len: usize,
+    current_ledger: u32,
+) -> Result<Option<([u8; 4096], usize)>, i32> {
+    if recorded_notary_set(existing_data)? == NotarySet::Backup {
+        return Ok(None);
+    }
+    let deadline = match inactivity_deadline(existing_data)? {
+        Some(d) if current_ledger >= d => d,
+        _ => return Ok(None),
+    };
+    validate_backup_set(existing_data)?;
+    let next = transition(recorded_state(existing_data)?, LifecycleEvent::NotarySetSwitched)?;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        // approval_N and approval_count, but not approval_round
+        k.strip_prefix(b"approval_")
+            .is_some_and(|n| !n.is_empty() && n.iter().all(u8::is_ascii_digit))
+            || k == b"approval_count"
+            || k == b"quorum_ledger"
+            || k == b"notary_set"
+            || k == b"state"
+            || k.starts_with(b"prop_vote")
+            || k == b"prop_quorum_ledger"
+            || k.starts_with(b"upgrade_vote")
+            || k.starts_with(b"abort_vote")
+            || k.starts_with(b"deputy_")
+            || k.starts_with(b"round_vote_")
+    });
+
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"notary_set", b"backup");
+    pos = write_separator(&mut new_data, pos);
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(deadline, &mut ledger_buf);
+    pos = write_entry(&mut new_data, pos, b"notary_set_ledger", &ledger_buf[..ledger_len]);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"approval_count", b"0");
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"state", next.as_bytes());
+
+    Ok(Some((new_data, pos)))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// PRICE ORACLE CONDITION (XLS-47)
+//
+// Optional condition that only releases when an asset price published in
+// an on-ledger Oracle object crosses a level. Configured with:
+//   oracle_owner     — 40-char hex AccountID that owns the Oracle object
+//   oracle_document  — OracleDocumentID (u32)
+//   oracle_base      — base asset: 3-char code ("XRP", "USD") or 40-char hex
+//   oracle_quote     — quote asset, same format as oracle_base
+//   oracle_cmp       — gt, ge, lt or le (price <cmp> oracle_price)
+//   oracle_price     — decimal level, e.g. "0.6125"
+//   oracle_max_age   — maximum age of the Oracle's LastUpdateTime in seconds
+//
+// The block is enabled by the presence of `oracle_owner`; all other keys
+// are then required.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Comparison applied as `oracle price <cmp> configured price`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PriceComparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// Parsed oracle condition block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OracleCondition {
+    owner: [u8; ACCOUNT_ID_SIZE],
+    document_id: u32,
+    base: [u8; CURRENCY_SIZE],
+    quote: [u8; CURRENCY_SIZE],
+    comparison: PriceComparison,
+    price_mantissa: u64,
+    price_scale: u8,
+    max_age: u32,
+}
+
+/// One PriceData entry read from an Oracle ledger object, along with the
+/// object's LastUpdateTime (Unix seconds).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OraclePrice {
+    asset_price: u64,
+    scale: u8,
+    last_update_time: u32,
+}
+
+/// Parse a currency code: a 3-char ISO-style code or 40-char hex.
+/// "XRP" maps to the all-zero currency, as in the ledger's binary format.
+fn parse_currency(value: &[u8]) -> Option<[u8; CURRENCY_SIZE]> {
+    let mut currency = [0u8; CURRENCY_SIZE];
+    match value.len() {
+        3 if value == b"XRP" => Some(currency),
+        3 => {
+            // Standard currency code: 12 zero bytes, 3 ASCII bytes, 5 zero bytes
+            currency[12..15].copy_from_slice(value);
+            Some(currency)
+        }
+        40 => {
+            decode_hex(value, &mut currency)?;
+            Some(currency)
+        }
+        _ => None,
+    }
+}
+
+/// Parse a non-negative decimal such as "12.345" into (mantissa, scale).
+///
+/// Example: parse_decimal(b"12.345") returns Some((12345, 3))
+fn parse_decimal(value: &[u8]) -> Option<(u64, u8)> {
+    let (int_part, frac_part) = match value.iter().position(|&b| b == b'.') {
+        Some(dot) => (&value[..dot], &value[dot + 1..]),
+        None => (value, &[][..]),
+    };
+    if int_part.is_empty() || frac_part.len() > MAX_PRICE_SCALE as usize {
+        return None;
+    }
+    if value.contains(&b'.') && frac_part.is_empty() {
+        return None;
+    }
+
+    let mut mantissa: u64 = 0;
+    for &b in int_part.iter().chain(frac_part.iter()) {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        mantissa = mantissa.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+    }
+    Some((mantissa, frac_part.len() as u8))
+}
+
+/// Parse the optional oracle condition block.
+/// Returns Ok(None) if no `oracle_owner` key is present.
+fn parse_oracle_condition(data: &[u8]) -> Result<Option<OracleCondition>, i32> {
+    let owner_hex = match find_value(data, b"oracle_owner") {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let mut owner = [0u8; ACCOUNT_ID_SIZE];
+    if owner_hex.len() != ACCOUNT_ID_SIZE * 2 {
+        return Err(ERR_BAD_CONFIG);
+    }
+    decode_hex(owner_hex, &mut owner).ok_or(ERR_BAD_CONFIG)?;
+
+    let document_id = find_value(data, b"oracle_document")
+        .and_then(parse_u32)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let base = find_value(data, b"oracle_base")
+        .and_then(parse_currency)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let quote = find_value(data, b"oracle_quote")
+        .and_then(parse_currency)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let comparison = match find_value(data, b"oracle_cmp") {
+        Some(b"gt") => PriceComparison::Greater,
+        Some(b"ge") => PriceComparison::GreaterOrEqual,
+        Some(b"lt") => PriceComparison::Less,
+        Some(b"le") => PriceComparison::LessOrEqual,
+        _ => return Err(ERR_BAD_CONFIG),
+    };
+    let (price_mantissa, price_scale) = find_value(data, b"oracle_price")
+        .and_then(parse_decimal)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let max_age = find_value(data, b"oracle_max_age")
+        .and_then(parse_u32)
+        .ok_or(ERR_BAD_CONFIG)?;
+
+    Ok(Some(OracleCondition {
+        owner,
+        document_id,
+        base,
+        quote,
+        comparison,
+        price_mantissa,
+        price_scale,
+        max_age,
+    }))
+}
+
+/// Compare two decimals given as (mantissa, scale) pairs.
+/// Both scales must be <= MAX_PRICE_SCALE so the rescaled values fit in u128.
+fn compare_decimal(a: (u64, u8), b: (u64, u8)) -> core::cmp::Ordering {
+    let (a_mantissa, a_scale) = a;
+    let (b_mantissa, b_scale) = b;
+    // Bring both values to the larger scale before comparing
+    let a_wide = a_mantissa as u128 * 10u128.pow(b_scale.saturating_sub(a_scale) as u32);
+    let b_wide = b_mantissa as u128 * 10u128.pow(a_scale.saturating_sub(b_scale) as u32);
+    a_wide.cmp(&b_wide)
+}
+
+/// Evaluate the oracle condition against a reading taken from the ledger.
+/// `price` is None if the Oracle object or the asset pair was not found.
+/// `now_unix` is the parent ledger close time converted to Unix seconds.
+fn check_oracle_condition(condition: &OracleCondition, price: Option<OraclePrice>, now_unix: u32) -> i32 {
+    let price = match price {
+        Some(p) => p,
+        None => return ERR_PRICE_CONDITION,
+    };
+    if price.scale > MAX_PRICE_SCALE {
+        return ERR_PRICE_CONDITION;
+    }
+
+    // A timestamp slightly ahead of the ledger clock is not stale
+    let age = now_unix.saturating_sub(price.last_update_time);
+    if age > condition.max_age {
+        return ERR_ORACLE_STALE;
+    }
+
+    let ordering = compare_decimal(
+        (price.asset_price, price.scale),
+        (condition.price_mantissa, condition.price_scale),
+    );
+    let satisfied = match condition.comparison {
+        PriceComparison::Greater => ordering.is_gt(),
+        PriceComparison::GreaterOrEqual => ordering.is_ge(),
+        PriceComparison::Less => ordering.is_lt(),
+        PriceComparison::LessOrEqual => ordering.is_le(),
+    };
+
+    if satisfied {
+        SUCCESS
+    } else {
+        ERR_PRICE_CONDITION
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// CREDENTIAL CONDITION (XLS-70)
+//
+// Optional KYC gate: the escrow Destination must hold an accepted,
+// unexpired Credential of a given type from a given issuer. Configured with:
+//   credential_issuer — 40-char hex AccountID of the issuer
+//   credential_type   — CredentialType as hex (1..64 bytes)
+//   credential_notary — "1" to also require it of the submitting notary
+//
+// The block is enabled by the presence of `credential_issuer`.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Parsed credential condition block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CredentialCondition {
+    issuer: [u8; ACCOUNT_ID_SIZE],
+    credential_type: [u8; MAX_CREDENTIAL_TYPE_SIZE],
+    type_len: usize,
+    require_notary: bool,
+}
+
+impl CredentialCondition {
+    fn credential_type(&self) -> &[u8] {
+        &self.credential_type[..self.type_len]
+    }
+}
+
+/// The fields of a Credential ledger object that decide whether it is valid.
+/// `expiration` is in Ripple epoch seconds, as stored on the ledger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CredentialState {
+    flags: u32,
+    expiration: Option<u32>,
+}
+
+/// Parse the optional credential condition block.
+/// Returns Ok(None) if no `credential_issuer` key is present.
+fn parse_credential_condition(data: &[u8]) -> Result<Option<CredentialCondition>, i32> {
+    let issuer_hex = match find_value(data, b"credential_issuer") {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let mut issuer = [0u8; ACCOUNT_ID_SIZE];
+    if issuer_hex.len() != ACCOUNT_ID_SIZE * 2 {
+        return Err(ERR_BAD_CONFIG);
+    }
+    decode_hex(issuer_hex, &mut issuer).ok_or(ERR_BAD_CONFIG)?;
+
+    let type_hex = find_value(data, b"credential_type").ok_or(ERR_BAD_CONFIG)?;
+    let mut credential_type = [0u8; MAX_CREDENTIAL_TYPE_SIZE];
+    let type_len = decode_hex(type_hex, &mut credential_type).ok_or(ERR_BAD_CONFIG)?;
+    if type_len == 0 {
+        return Err(ERR_BAD_CONFIG);
+    }
+
+    let require_notary = match find_value(data, b"credential_notary") {
+        None | Some(b"0") => false,
+        Some(b"1") => true,
+        Some(_) => return Err(ERR_BAD_CONFIG),
+    };
+
+    Ok(Some(CredentialCondition {
+        issuer,
+        credential_type,
+        type_len,
+        require_notary,
+    }))
+}
+
+/// Check a credential read from the ledger.
+/// `state` is None if no Credential object exists for (subject, issuer, type).
+/// `now` is the parent ledger close time in Ripple epoch seconds.
+fn check_credential(state: Option<CredentialState>, now: u32) -> i32 {
+    let state = match state {
+        Some(s) => s,
+        None => return ERR_NO_CREDENTIAL,
+    };
+
+    // Issued but not yet accepted by the subject
+    if state.flags & LSF_ACCEPTED == 0 {
+        return ERR_NO_CREDENTIAL;
+    }
+
+    // The ledger treats a credential as expired once close time passes Expiration
+    match state.expiration {
+        Some(expiration) if now > expiration => ERR_NO_CREDENTIAL,
+        _ => SUCCESS,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// HASH-LOCK CONDITION (atomic swaps)
+//
+// `hashlock=<64 hex>` holds a SHA-256 digest. The EscrowFinish unlocks it by
+// carrying the preimage in its first Memo's MemoData. `hashlock_mode`
+// decides how it combines with the notary threshold:
+//   and — preimage AND threshold must both hold (default)
+//   or  — preimage OR threshold is enough (e.g. preimage OR 2-of-3)
+// ═══════════════════════════════════════════════════════════════════════
+
+/// How a hash-lock combines with the approval threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HashlockMode {
+    And,
+    Or,
+}
+
+/// Parsed hash-lock configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hashlock {
+    digest: [u8; sha256::DIGEST_SIZE],
+    mode: HashlockMode,
+}
+
+/// Parse the optional hash-lock configuration.
+/// Returns Ok(None) if no `hashlock` key is present.
+fn parse_hashlock(data: &[u8]) -> Result<Option<Hashlock>, i32> {
+    let digest_hex = match find_value(data, b"hashlock") {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let mut digest = [0u8; sha256::DIGEST_SIZE];
+    if digest_hex.len() != sha256::DIGEST_SIZE * 2 {
+        return Err(ERR_BAD_CONFIG);
+    }
+    decode_hex(digest_hex, &mut digest).ok_or(ERR_BAD_CONFIG)?;
+
+    let mode = match find_value(data, b"hashlock_mode") {
+        None | Some(b"and") => HashlockMode::And,
+        Some(b"or") => HashlockMode::Or,
+        Some(_) => return Err(ERR_BAD_CONFIG),
+    };
+
+    Ok(Some(Hashlock { digest, mode }))
+}
+
+/// Check a supplied preimage against the hash-lock digest.
+/// Empty or oversized preimages are rejected before hashing.
+fn check_preimage(lock: &Hashlock, preimage: Option<&[u8]>) -> i32 {
+    match preimage {
+        Some(p) if !p.is_empty() && p.len() <= MAX_PREIMAGE_SIZE => {
+            if sha256::sha256(p) == lock.digest {
+                SUCCESS
+            } else {
+                ERR_BAD_PREIMAGE
+            }
+        }
+        _ => ERR_BAD_PREIMAGE,
+    }
+}
+
+/// Combine the hash-lock with the result of the approval threshold check.
+///
+/// In `or` mode a missing preimage reports the threshold's own result, so a
+/// plain notary-driven finish still sees `not_approved` rather than
+/// `bad_preimage`.
+fn check_hashlock(lock: &Hashlock, preimage: Option<&[u8]>, approval_result: i32) -> i32 {
+    let preimage_result = check_preimage(lock, preimage);
+    match lock.mode {
+        HashlockMode::And => {
+            if approval_result != SUCCESS {
+                approval_result
+            } else {
+                preimage_result
+            }
+        }
+        HashlockMode::Or => {
+            if approval_result == SUCCESS || preimage_result == SUCCESS {
+                SUCCESS
+            } else if preimage.is_some() {
+                preimage_result
+            } else {
+                approval_result
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// CROSS-ESCROW DEPENDENCY (milestone sequencing)
+//
+// `depends_on=<owner hex>:<sequence>` names another escrow by its owner
+// AccountID and EscrowCreate sequence. This escrow can't finish until that
+// one is done: its ledger object no longer exists, since a finished
+// escrow is deleted, or it still exists but its own audit trail shows
+// `last_result=approved`.
+//
+// A missing object is ambiguous all the same: the escrow was finished,
+// but equally it was cancelled, or the sequence is mistyped and it never
+// existed. Where releasing after a refund would be wrong, as for an
+// atomic swap, `depends_on_missing=deny` refuses a missing object and
+// only a still-present escrow with `last_result=approved` satisfies it.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// The escrow this one depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Dependency {
+    owner: [u8; ACCOUNT_ID_SIZE],
+    sequence: u32,
+    /// `depends_on_missing=deny`: a missing escrow object doesn't count
+    missing_denied: bool,
+}
+
+impl Dependency {
+    /// Ledger index of the escrow object this dependency names.
+    fn keylet(&self) -> [u8; keylet::KEYLET_SIZE] {
+        keylet::escrow_keylet(&self.owner, self.sequence)
+    }
+}
+
+/// Parse the optional `depends_on` key.
+/// Returns Ok(None) if it is not present.
+fn parse_dependency(data: &[u8]) -> Result<Option<Dependency>, i32> {
+    let value = match find_value(data, b"depends_on") {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+
+    let colon = value.iter().position(|&b| b == b':').ok_or(ERR_BAD_CONFIG)?;
+    let owner_hex = &value[..colon];
+    if owner_hex.len() != ACCOUNT_ID_SIZE * 2 {
+        return Err(ERR_BAD_CONFIG);
+    }
+    let mut owner = [0u8; ACCOUNT_ID_SIZE];
+    decode_hex(owner_hex, &mut owner).ok_or(ERR_BAD_CONFIG)?;
+    let sequence = parse_u32(&value[colon + 1..]).ok_or(ERR_BAD_CONFIG)?;
+    let missing_denied = match find_value(data, b"depends_on_missing") {
+        None => false,
+        Some(b"deny") => true,
+        Some(_) => return Err(ERR_BAD_CONFIG),
+    };
+
+    Ok(Some(Dependency { owner, sequence, missing_denied }))
+}
+
+/// Check the dependency given what the ledger holds at its index.
+/// `dependency_data` is None if the escrow object no longer exists, or its
+/// Data field if it does.
+fn check_dependency(dependency: &Dependency, dependency_data: Option<&[u8]>) -> i32 {
+    match dependency_data {
+        None if dependency.missing_denied => ERR_DEPENDENCY,
+        None => SUCCESS,
+        Some(data) if find_value(data, b"last_result") == Some(b"approved") => SUCCESS,
+        Some(_) => ERR_DEPENDENCY,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// CONFIG BINDING — data tied to the escrow it lives in
+//
+// Nothing else ties the contract data to one escrow: a config prepared
+// for escrow A works verbatim if pasted into escrow B. Each optional key
+// below pins a field of the escrow, and finish() denies with
+// ERR_BAD_CONFIG if the escrow doesn't match:
+//
+//   owner           — Account (40 hex)
+//   destination     — Destination (40 hex)
+//   destination_tag — DestinationTag; an escrow without one doesn't match
+//   source_tag      — SourceTag; likewise
+//   max_amount      — Amount must be XRP and at most this many drops
+// ═══════════════════════════════════════════════════════════════════════
+
+/// The escrow fields a config can be bound to, as read from the host.
+#[derive(Clone, Copy, Debug)]
+struct EscrowFields {
+    owner: [u8; ACCOUNT_ID_SIZE],
+    destination: [u8; ACCOUNT_ID_SIZE],
+    destination_tag: Option<u32>,
+    source_tag: Option<u32>,
+    /// None for a token escrow
+    amount_drops: Option<u64>,
+}
+
+/// Check every binding key present in `data` against the escrow.
+fn check_config_binding(data: &[u8], escrow: &EscrowFields) -> i32 {
+    for (key, actual) in [(b"owner" as &[u8], &escrow.owner), (b"destination", &escrow.destination)] {
+        if let Some(hex) = find_value(data, key) {
+            let mut bound = [0u8; ACCOUNT_ID_SIZE];
+            if hex.len() != ACCOUNT_ID_SIZE * 2
+                || decode_hex(hex, &mut bound) != Some(ACCOUNT_ID_SIZE)
+                || bound != *actual
+            {
+                return ERR_BAD_CONFIG;
+            }
+        }
+    }
+    for (key, actual) in [(b"destination_tag" as &[u8], escrow.destination_tag), (b"source_tag", escrow.source_tag)] {
+        if let Some(value) = find_value(data, key) {
+            match parse_u32(value) {
+                Some(tag) if Some(tag) == actual => {}
+                _ => return ERR_BAD_CONFIG,
+            }
+        }
+    }
+    if let Some(value) = find_value(data, b"max_amount") {
+        match (parse_u64(value), escrow.amount_drops) {
+            (Some(max), Some(amount)) if amount <= max => {}
+            _ => return ERR_BAD_CONFIG,
+        }
+    }
+    SUCCESS
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// CONFLICT OF INTEREST — keeping notaries independent of the parties
+//
+// Notary AccountIDs must be distinct within a set: a duplicate would let
+// one account hold two indexes. `notary_exclude=owner,destination` also
+// refuses a notary set containing those parties; it relies on the
+// `owner` / `destination` binding keys, which finish() verifies against
+// the escrow, so it can be enforced wherever notaries are looked up.
+//
+// `require_consent=owner,destination` asks for the parties' own approval
+// on top of the notaries'. A party's set_approval records
+// `consent_<party>=<ledger>` instead of a notary approval; it never
+// counts toward the threshold, and finish() denies without it.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Consent key of each party role.
+const CONSENT_KEYS: [(u8, &[u8]); 2] = [
+    (FINISH_BY_OWNER, b"consent_owner"),
+    (FINISH_BY_DESTINATION, b"consent_destination"),
+];
+
+/// Parse a comma-separated list of parties (owner, destination).
+fn parse_party_list(value: &[u8]) -> Option<u8> {
+    parse_finish_by(value).filter(|roles| roles & !(FINISH_BY_OWNER | FINISH_BY_DESTINATION) == 0)
+}
+
+/// Accounts named in `notary_exclude`, read from the binding keys.
+fn excluded_parties(data: &[u8]) -> Result<([[u8; ACCOUNT_ID_SIZE]; 2], usize), i32> {
+    let excluded = match find_value(data, b"notary_exclude") {
+        Some(v) => parse_party_list(v).ok_or(ERR_BAD_CONFIG)?,
+        None => 0,
+    };
+    let mut parties = [[0u8; ACCOUNT_ID_SIZE]; 2];
+    let mut party_count = 0;
+    for (role, key) in [(FINISH_BY_OWNER, b"owner" as &[u8]), (FINISH_BY_DESTINATION, b"destination")] {
+        if excluded & role != 0 {
+            let hex = find_value(data, key).ok_or(ERR_BAD_CONFIG)?;
+            if hex.len() != ACCOUNT_ID_SIZE * 2 || decode_hex(hex, &mut parties[party_count]) != Some(ACCOUNT_ID_SIZE) {
+                return Err(ERR_BAD_CONFIG);
+            }
+            party_count += 1;
+        }
+    }
+    Ok((parties, party_count))
+}
+
+/// Check one notary set (`<prefix>0..<prefix>N`) for duplicates and
+/// excluded parties.
+fn check_notary_conflicts(data: &[u8], count: u8, prefix: &[u8]) -> Result<(), i32> {
+    let (parties, party_count) = excluded_parties(data)?;
+
+    let mut seen = [[0u8; ACCOUNT_ID_SIZE]; MAX_NOTARIES];
+    let mut seen_count = 0;
+    let mut key_buf = [0u8; 24];
+    for i in 0..count {
+        let key_len = build_indexed_key(prefix, i, &mut key_buf);
+        let mut notary = [0u8; ACCOUNT_ID_SIZE];
+        match find_value(data, &key_buf[..key_len]) {
+            Some(hex) if decode_hex(hex, &mut notary) == Some(ACCOUNT_ID_SIZE) => {}
+            // An unreadable entry matches nobody (see find_notary_index)
+            _ => continue,
+        }
+        if seen[..seen_count].contains(&notary) || parties[..party_count].contains(&notary) {
+            return Err(ERR_BAD_CONFIG);
+        }
+        seen[seen_count] = notary;
+        seen_count += 1;
+    }
+    Ok(())
+}
+
+/// The party role whose consent `caller` gives, if consent is required
+/// from them. None means the caller is handled as a notary.
+fn consent_role(
+    data: &[u8],
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<Option<u8>, i32> {
+    let required = match find_value(data, b"require_consent") {
+        Some(v) => parse_party_list(v).ok_or(ERR_BAD_CONFIG)?,
+        None => return Ok(None),
+    };
+    if required & FINISH_BY_OWNER != 0 && caller == owner {
+        Ok(Some(FINISH_BY_OWNER))
+    } else if required & FINISH_BY_DESTINATION != 0 && caller == destination {
+        Ok(Some(FINISH_BY_DESTINATION))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Record (`given`) or withdraw a party's consent.
+fn record_consent(
+    existing_data: &[u8],
+    existing_len: usize,
+    role: u8,
+    given: bool,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    let key = CONSENT_KEYS
+        .iter()
+        .find(|(r, _)| *r == role)
+        .map(|(_, k)| *k)
+        .ok_or(ERR_BAD_CONFIG)?;
+    if given && find_value(existing_data, key).is_some() {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| k == key);
+    if given {
+        let mut ledger_buf = [0u8; 10];
+        let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+        if pos > 0 {
+            pos = write_separator(&mut new_data, pos);
+        }
+        pos = write_entry(&mut new_data, pos, key, &ledger_buf[..ledger_len]);
+    }
+    Ok((new_data, pos))
+}
+
+/// Every consent `require_consent` asks for has been given.
+fn check_consent(data: &[u8]) -> i32 {
+    let required = match find_value(data, b"require_consent") {
+        Some(v) => match parse_party_list(v) {
+            Some(r) => r,
+            None => return ERR_BAD_CONFIG,
+        },
+        None => return SUCCESS,
+    };
+    for (role, key) in CONSENT_KEYS {
+        if required & role != 0 && find_value(data, key).is_none() {
+            return ERR_NOT_APPROVED;
+        }
+    }
+    SUCCESS
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// DESTINATION ACKNOWLEDGEMENT — proof the payee is controlled
+//
+// With `require_destination_ack=1`, funds only move once the escrow's
+// Destination has itself called acknowledge(), which proves someone holds
+// its keys — a mistyped address can't. The ack is recorded like an
+// approval (dest_ack / dest_ack_seq / dest_ack_ledger) but never counts
+// toward any threshold, and a finish denied for lack of it is audited as
+// `destination_unacked`, apart from approval denials.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Whether the escrow requires a destination acknowledgement.
+fn destination_ack_required(data: &[u8]) -> Result<bool, i32> {
+    match find_value(data, b"require_destination_ack") {
+        None | Some(b"0") => Ok(false),
+        Some(b"1") => Ok(true),
+        Some(_) => Err(ERR_BAD_CONFIG),
+    }
+}
+
+/// Record the Destination's acknowledgement. Only the escrow's Destination
+/// may acknowledge, once, and only when the escrow asks for it.
+fn record_destination_ack(
+    existing_data: &[u8],
+    existing_len: usize,
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+    tx_sequence: u32,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    if !destination_ack_required(existing_data)? {
+        return Err(ERR_BAD_CONFIG);
+    }
+    if caller != destination {
+        return Err(ERR_WRONG_ACCOUNT);
+    }
+    if find_value(existing_data, b"dest_ack").is_some() {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == b"dest_ack_seq" || k == b"dest_ack_ledger"
+    });
+
+    let mut caller_hex = [0u8; ACCOUNT_ID_SIZE * 2];
+    encode_hex(caller, &mut caller_hex);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"dest_ack", &caller_hex);
+
+    let mut buf = [0u8; 10];
+    let len = format_u32(tx_sequence, &mut buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"dest_ack_seq", &buf[..len]);
+    let len = format_u32(current_ledger, &mut buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"dest_ack_ledger", &buf[..len]);
+
+    Ok((new_data, pos))
+}
+
+/// Release check: the acknowledgement is in, if one is required.
+fn check_destination_ack(data: &[u8]) -> i32 {
+    match destination_ack_required(data) {
+        Ok(false) => SUCCESS,
+        Ok(true) if find_value(data, b"dest_ack").is_some() => SUCCESS,
+        Ok(true) => ERR_DESTINATION_ACK,
+        Err(code) => code,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// GOVERNANCE PROPOSALS — config changes by notary vote
+//
+// With `gov_threshold=M`, a notary can propose changing one config key
+// through propose(), with a memo of "id=<n>;key=<key>;value=<value>". The
+// proposer's vote counts; other notaries vote through set_approval() with
+// a memo of "proposal=<n>". Once M votes are in (`prop_quorum_ledger`) and
+// `gov_delay_ledgers` more ledgers have passed (default 8640, ~12 hours),
+// the next entry point applies the change.
+//
+// A proposal that hasn't passed by `prop_expiry_ledger` (proposal ledger
+// plus `gov_expiry_ledgers`) expires and frees the slot; its proposer can
+// also withdraw it earlier with a memo of "withdraw=<n>".
+//
+// Replacing a notary (or lowering the notary count) clears the vacated
+// slots' approvals, deputies and votes and recounts `approval_count`.
+// The tally is then re-run against the new threshold: `quorum_ledger` is
+// set or cleared and `state` follows. A released or aborted escrow's
+// config no longer changes.
+//
+// Only one proposal is open at a time and ids must increase. Keys the
+// contract manages itself are never changeable; the creator can freeze
+// more with `immutable=<key>,<key>,...`. Each step is recorded as
+// `gov_last=<proposed|voted|withdrawn|applied>:<id>` and `gov_last_ledger`.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Keys written by the contract itself, or that set the rules proposals,
+/// aborts, config binding and the dead-man switch run under; never
+/// changeable by proposal.
+const RESERVED_KEYS: [&[u8]; 34] = [
+    b"approval_count",
+    b"last_result",
+    b"last_attempt_seq",
+    b"quorum_ledger",
+    b"last_activity_ledger",
+    b"notary_set",
+    b"notary_set_ledger",
+    b"state",
+    b"immutable",
+    b"pending_code_hash",
+    b"active_code_hash",
+    b"template_used",
+    b"last_revocation_policy",
+    b"last_revocation_ledger",
+    b"approval_round",
+    b"round_ledger",
+    b"gov_delay_ledgers",
+    b"gov_expiry_ledgers",
+    b"created_ledger",
+    b"notaries_from",
+    b"require_consent",
+    b"notary_exclude",
+    b"owner",
+    b"destination",
+    b"require_destination_ack",
+    b"revocation_policy",
+    b"revocation_window_ledgers",
+    b"commit_deadline",
+    b"reveal_deadline",
+    b"gov_threshold",
+    b"abort_threshold",
+    b"destination_tag",
+    b"source_tag",
+    b"max_amount",
+];
+
+/// Key prefixes written by the contract itself. The upgrade policy only
+/// changes by upgrading (see src/upgrade.rs).
+const RESERVED_PREFIXES: [&[u8]; 17] = [
+    b"approval_",
+    b"abort_vote",
+    b"consent_",
+    b"dest_ack",
+    b"signer_approval_",
+    b"approver_",
+    b"approve_seq_",
+    b"approve_ledger_",
+    b"approve_deputy_",
+    b"approve_round_",
+    b"round_vote_",
+    b"vote_commit_",
+    b"vote_reveal_",
+    b"deputy_",
+    b"prop_",
+    b"gov_last",
+    b"upgrade_",
+];
+
+/// Per-notary keys (`<prefix><index>`) that belong to whoever holds the slot.
+const NOTARY_SLOT_PREFIXES: [&[u8]; 12] = [
+    b"approval_",
+    b"approver_",
+    b"approve_seq_",
+    b"approve_ledger_",
+    b"approve_deputy_",
+    b"approve_round_",
+    b"deputy_",
+    b"deputy_until_",
+    b"round_vote_",
+    b"vote_commit_",
+    b"vote_reveal_",
+    b"abort_vote_",
+];
+
+/// A proposed config change, parsed from the propose() memo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Proposal<'a> {
+    id: u32,
+    key: &'a [u8],
+    value: &'a [u8],
+}
+
+/// Parse "id=<n>;key=<key>;value=<value>".
+/// Keys are lowercase ASCII, digits and '_'; values are printable ASCII
+/// without the ';' and '=' delimiters.
+fn parse_proposal(memo: &[u8]) -> Result<Proposal<'_>, i32> {
+    let id = find_value(memo, b"id").and_then(parse_u32).ok_or(ERR_PROPOSAL)?;
+    let key = find_value(memo, b"key").ok_or(ERR_PROPOSAL)?;
+    let value = find_value(memo, b"value").ok_or(ERR_PROPOSAL)?;
+
+    let key_ok = !key.is_empty()
+        && key.len() <= MAX_PROPOSAL_KEY_SIZE
+        && key.iter().all(|&b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+    let value_ok = !value.is_empty()
+        && value.len() <= MAX_PROPOSAL_VALUE_SIZE
+        && value.iter().all(|&b| b.is_ascii_graphic() && b != b';' && b != b'=');
+    if !key_ok || !value_ok {
+        return Err(ERR_PROPOSAL);
+    }
+
+    Ok(Proposal { id, key, value })
+}
+
+/// Refuse keys the contract manages or the creator declared immutable.
+fn check_key_mutable(data: &[u8], key: &[u8]) -> Result<(), i32> {
+    let reserved = RESERVED_KEYS.contains(&key)
+        || RESERVED_PREFIXES.iter().any(|p| key.starts_with(p));
+    let declared = find_value(data, b"immutable")
+        .map(|list| list.split(|&b| b == b',').any(|k| k == key))
+        .unwrap_or(false);
+
+    if reserved || declared {
+        Err(ERR_IMMUTABLE_KEY)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sanity-check the parts of the config a proposal could break: notary
+/// counts and thresholds, tiers, decay schedule, backup set and finish_by.
+fn validate_config(data: &[u8]) -> Result<(), i32> {
+    let count = find_value(data, b"notary_count")
+        .and_then(parse_u8_digit)
+        .ok_or(ERR_BAD_CONFIG)?;
+    if count == 0 || count as usize > MAX_NOTARIES {
+        return Err(ERR_BAD_CONFIG);
+    }
+    check_notary_conflicts(data, count, b"notary_")?;
+    for key in [b"threshold" as &[u8], b"gov_threshold", b"abort_threshold"] {
+        if let Some(v) = find_value(data, key) {
+            match parse_u8_digit(v) {
+                Some(t) if (1..=count).contains(&t) => {}
+                _ => return Err(ERR_BAD_CONFIG),
+            }
+        }
+    }
+    for key in [b"gov_delay_ledgers" as &[u8], b"gov_expiry_ledgers"] {
+        if let Some(v) = find_value(data, key) {
+            parse_u32(v).ok_or(ERR_BAD_CONFIG)?;
+        }
+    }
+    if let Some(v) = find_value(data, b"finish_by") {
+        parse_finish_by(v).ok_or(ERR_BAD_CONFIG)?;
+    }
+    if find_value(data, b"backup_notary_count").is_some() {
+        validate_backup_set(data)?;
+    }
+    validate_tiers(data)?;
+    validate_decay_schedule(data)?;
+    // Governance only exists in data-notary mode, so this refuses a switch
+    parse_notaries_from(data)?;
+    parse_release_policy(data)?;
+    parse_revocation_policy(data)?;
+    parse_commit_reveal(data)?;
+    // Templates only expand at creation; a proposed one would clash with
+    // the keys it expanded into
+    if find_value(data, b"template").is_some() {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(())
+}
+
+/// Votes required to pass a proposal. Governance is off without `gov_threshold`.
+fn gov_threshold(data: &[u8]) -> Result<u8, i32> {
+    match find_value(data, b"gov_threshold").and_then(parse_u8_digit) {
+        Some(t) if t >= 1 => Ok(t),
+        _ => Err(ERR_BAD_CONFIG),
+    }
+}
+
+/// The open proposal's id, or None if there is none or it expired before
+/// passing. Proposals without `prop_expiry_ledger` never expire.
+fn open_proposal_id(data: &[u8], current_ledger: u32) -> Result<Option<u32>, i32> {
+    let id = match find_value(data, b"prop_id") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => return Ok(None),
+    };
+    if find_value(data, b"prop_quorum_ledger").is_some() {
+        return Ok(Some(id));
+    }
+    match find_value(data, b"prop_expiry_ledger") {
+        Some(v) if current_ledger > parse_u32(v).ok_or(ERR_BAD_CONFIG)? => Ok(None),
+        _ => Ok(Some(id)),
+    }
+}
+
+/// Append the governance audit entries: gov_last=<action>:<id>;gov_last_ledger=<ledger>
+fn write_gov_audit(data: &mut [u8], mut pos: usize, action: &[u8], id: u32, current_ledger: u32) -> usize {
+    let mut value = [0u8; 24];
+    value[..action.len()].copy_from_slice(action);
+    value[action.len()] = b':';
+    let id_len = format_u32(id, &mut value[action.len() + 1..]);
+
+    if pos > 0 {
+        pos = write_separator(data, pos);
+    }
+    pos = write_entry(data, pos, b"gov_last", &value[..action.len() + 1 + id_len]);
+    pos = write_separator(data, pos);
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    write_entry(data, pos, b"gov_last_ledger", &ledger_buf[..ledger_len])
+}
+
+/// Append prop_votes=<votes>, and prop_quorum_ledger if `votes` reaches the
+/// governance threshold for the first time.
+fn write_prop_votes(
+    data: &mut [u8],
+    mut pos: usize,
+    existing_data: &[u8],
+    votes: u8,
+    threshold: u8,
+    current_ledger: u32,
+) -> usize {
+    pos = write_separator(data, pos);
+    pos = write_entry(data, pos, b"prop_votes", &[b'0' + votes]);
+    if votes >= threshold && find_value(existing_data, b"prop_quorum_ledger").is_none() {
+        let mut ledger_buf = [0u8; 10];
+        let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+        pos = write_separator(data, pos);
+        pos = write_entry(data, pos, b"prop_quorum_ledger", &ledger_buf[..ledger_len]);
+    }
+    pos
+}
+
+/// Open a proposal, counting the proposer's vote.
+///
+/// Refused with ERR_PROPOSAL if another proposal is open or the id doesn't
+/// increase past `gov_last_id` and any expired proposal, ERR_IMMUTABLE_KEY
+/// for protected keys, and ERR_BAD_CONFIG if the change would leave the
+/// config invalid. An expired proposal is replaced.
+fn record_proposal(
+    existing_data: &[u8],
+    existing_len: usize,
+    proposal: &Proposal,
+    proposer_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    let threshold = gov_threshold(existing_data)?;
+    if open_proposal_id(existing_data, current_ledger)?.is_some() {
+        return Err(ERR_PROPOSAL);
+    }
+    for key in [b"gov_last_id" as &[u8], b"prop_id"] {
+        if let Some(last) = find_value(existing_data, key) {
+            let last = parse_u32(last).ok_or(ERR_BAD_CONFIG)?;
+            if proposal.id <= last {
+                return Err(ERR_PROPOSAL);
+            }
+        }
+    }
+    let expiry = match find_value(existing_data, b"gov_expiry_ledgers") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => DEFAULT_GOV_EXPIRY_LEDGERS,
+    };
+    check_key_mutable(existing_data, proposal.key)?;
+
+    let (candidate, candidate_len) = replace_entry(existing_data, existing_len, proposal.key, proposal.value);
+    validate_config(&candidate[..candidate_len])?;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k.starts_with(b"prop_") || k == b"gov_last" || k == b"gov_last_ledger"
+    });
+
+    let mut num_buf = [0u8; 10];
+    let num_len = format_u32(proposal.id, &mut num_buf);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"prop_id", &num_buf[..num_len]);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"prop_key", proposal.key);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"prop_value", proposal.value);
+    pos = write_separator(&mut new_data, pos);
+    let num_len = format_u32(current_ledger, &mut num_buf);
+    pos = write_entry(&mut new_data, pos, b"prop_ledger", &num_buf[..num_len]);
+    pos = write_separator(&mut new_data, pos);
+    let num_len = format_u32(current_ledger.saturating_add(expiry), &mut num_buf);
+    pos = write_entry(&mut new_data, pos, b"prop_expiry_ledger", &num_buf[..num_len]);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"prop_proposer", &[b'0' + proposer_index]);
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"prop_vote_", proposer_index, &mut vote_key_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, &vote_key_buf[..vote_key_len], b"1");
+    pos = write_prop_votes(&mut new_data, pos, existing_data, 1, threshold, current_ledger);
+
+    pos = write_gov_audit(&mut new_data, pos, b"proposed", proposal.id, current_ledger);
+    Ok((new_data, pos))
+}
+
+/// Record a notary's vote for the open proposal `id`.
+fn record_proposal_vote(
+    existing_data: &[u8],
+    existing_len: usize,
+    id: u32,
+    voter_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    let threshold = gov_threshold(existing_data)?;
+    if open_proposal_id(existing_data, current_ledger)? != Some(id) {
+        return Err(ERR_PROPOSAL);
+    }
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"prop_vote_", voter_index, &mut vote_key_buf);
+    let vote_key = &vote_key_buf[..vote_key_len];
+    if find_value(existing_data, vote_key) == Some(b"1") {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+    let votes = find_value(existing_data, b"prop_votes")
+        .and_then(parse_u8_digit)
+        .unwrap_or(0)
+        + 1;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == vote_key || k == b"prop_votes" || k == b"gov_last" || k == b"gov_last_ledger"
+    });
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, vote_key, b"1");
+    pos = write_prop_votes(&mut new_data, pos, existing_data, votes, threshold, current_ledger);
+
+    pos = write_gov_audit(&mut new_data, pos, b"voted", id, current_ledger);
+    Ok((new_data, pos))
+}
+
+/// Withdraw the open proposal `id`. Only its proposer may, and only until
+/// it is applied; the id is used up like an applied one.
+fn record_proposal_withdrawal(
+    existing_data: &[u8],
+    existing_len: usize,
+    id: u32,
+    notary_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    transition(recorded_state(existing_data)?, LifecycleEvent::Annotate)?;
+    if open_proposal_id(existing_data, current_ledger)? != Some(id) {
+        return Err(ERR_PROPOSAL);
+    }
+    if find_value(existing_data, b"prop_proposer").and_then(parse_u8_digit) != Some(notary_index) {
+        return Err(ERR_WRONG_ACCOUNT);
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k.starts_with(b"prop_") || k.starts_with(b"gov_last")
+    });
+    let mut id_buf = [0u8; 10];
+    let id_len = format_u32(id, &mut id_buf);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"gov_last_id", &id_buf[..id_len]);
+
+    pos = write_gov_audit(&mut new_data, pos, b"withdrawn", id, current_ledger);
+    Ok((new_data, pos))
+}
+
+/// The notary index a per-notary key belongs to, if it is one.
+fn notary_slot_index(key: &[u8]) -> Option<usize> {
+    let (&last, stem) = key.split_last()?;
+    if last.is_ascii_digit() && NOTARY_SLOT_PREFIXES.contains(&stem) {
+        Some((last - b'0') as usize)
+    } else {
+        None
+    }
+}
+
+/// Notary slots of the active set that setting `key` to `value` vacates:
+/// the replaced `notary_N`, or those past a lowered `notary_count`.
+fn vacated_notary_slots(data: &[u8], key: &[u8], value: &[u8]) -> Result<[bool; MAX_NOTARIES], i32> {
+    let (count_key, prefix) = notary_set_keys(recorded_notary_set(data)?);
+    let mut vacated = [false; MAX_NOTARIES];
+    if key == count_key {
+        let count = parse_u8_digit(value).ok_or(ERR_BAD_CONFIG)? as usize;
+        for slot in vacated.iter_mut().skip(count) {
+            *slot = true;
+        }
+    } else if let Some(index) = key.strip_prefix(prefix).and_then(parse_u8_digit) {
+        if let Some(slot) = vacated.get_mut(index as usize) {
+            *slot = true;
+        }
+    }
+    Ok(vacated)
+}
+
+/// Apply the open proposal if it has passed and its delay has elapsed.
+/// Returns Ok(None) when nothing changes. `amount_drops` prices tiers.
+///
+/// The changed key is rewritten, all `prop_*` entries are dropped and
+/// `gov_last_id` remembers the applied id so it can't be replayed. Vacated
+/// notary slots are cleared and the approval and abort votes recounted.
+fn apply_due_proposal(
+    existing_data: &[u8],
+    existing_len: usize,
+    amount_drops: Option<u64>,
+    current_ledger: u32,
+) -> Result<Option<([u8; 4096], usize)>, i32> {
+    let state = recorded_state(existing_data)?;
+    if matches!(state, EscrowState::Released | EscrowState::Aborted) {
+        return Ok(None);
+    }
+    let quorum_ledger = match find_value(existing_data, b"prop_quorum_ledger") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => return Ok(None),
+    };
+    let delay = match find_value(existing_data, b"gov_delay_ledgers") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => DEFAULT_GOV_DELAY_LEDGERS,
+    };
+    if current_ledger < quorum_ledger.saturating_add(delay) {
+        return Ok(None);
+    }
+
+    let id = find_value(existing_data, b"prop_id").and_then(parse_u32).ok_or(ERR_BAD_CONFIG)?;
+    let key = find_value(existing_data, b"prop_key").ok_or(ERR_BAD_CONFIG)?;
+    let value = find_value(existing_data, b"prop_value").ok_or(ERR_BAD_CONFIG)?;
+    let vacated = vacated_notary_slots(existing_data, key, value)?;
+    let recount = vacated.contains(&true);
+    let round = approval_round(existing_data)?;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == key
+            || k.starts_with(b"prop_")
+            || k.starts_with(b"gov_last")
+            || (recount && (k == b"approval_count" || k == b"abort_votes"))
+            || notary_slot_index(k).is_some_and(|i| vacated.get(i) == Some(&true))
+            || k == b"quorum_ledger"
+            || k == b"state"
+    });
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, key, value);
+
+    if recount {
+        let mut approvals: u8 = 0;
+        let mut abort_votes: u8 = 0;
+        let mut key_buf = [0u8; 16];
+        for i in 0..MAX_NOTARIES as u8 {
+            if has_current_approval(&new_data[..pos], i, round) {
+                approvals += 1;
+            }
+            let key_len = build_indexed_key(b"abort_vote_", i, &mut key_buf);
+            if find_value(&new_data[..pos], &key_buf[..key_len]).is_some() {
+                abort_votes += 1;
+            }
+        }
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, b"approval_count", &[b'0' + approvals]);
+        if find_value(existing_data, b"abort_votes").is_some() {
+            pos = write_separator(&mut new_data, pos);
+            pos = write_entry(&mut new_data, pos, b"abort_votes", &[b'0' + abort_votes]);
+        }
+    }
+
+    // A quorum held before the change keeps its challenge period; one the
+    // change creates starts it now
+    let old_threshold = required_threshold(existing_data, amount_drops, current_ledger)?;
+    let was_quorum = Tally::of(existing_data, old_threshold).quorum;
+    let threshold = required_threshold(&new_data[..pos], amount_drops, current_ledger)?;
+    let tally = Tally::of(&new_data[..pos], threshold);
+    let mut ledger_buf = [0u8; 10];
+    let quorum_since = match find_value(existing_data, b"quorum_ledger") {
+        Some(v) if tally.quorum => Some(v),
+        None if tally.quorum && !was_quorum => {
+            let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+            Some(&ledger_buf[..ledger_len])
+        }
+        _ => None,
+    };
+    if let Some(since) = quorum_since {
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, b"quorum_ledger", since);
+    }
+    let next = transition(state, LifecycleEvent::Reconfigured(tally))?;
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"state", next.as_bytes());
+
+    let mut id_buf = [0u8; 10];
+    let id_len = format_u32(id, &mut id_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"gov_last_id", &id_buf[..id_len]);
+
+    pos = write_gov_audit(&mut new_data, pos, b"applied", id, current_ledger);
+    Ok(Some((new_data, pos)))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// MULTISIGNED FINISH — signers count as approvals
+//
+// If the EscrowFinish is multisigned, every entry of its Signers array
+// that is a notary of the active set counts as an approval for this
+// invocation, on top of the approvals already stored. One multisigned
+// transaction can therefore both reach quorum and release. Signer
+// approvals are not persisted, and without a recorded quorum_ledger a
+// configured challenge period still holds them back.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Count distinct notaries that approved: stored approvals of the current
+/// round plus notaries among `signers`. Data notary list only; a SignerList
+/// escrow is weighed by signer_list_weight instead.
+fn count_approvals_with_signers(data: &[u8], signers: &[[u8; ACCOUNT_ID_SIZE]]) -> Result<u8, i32> {
+    let mut approved: u8 = 0; // bit N set = notary N approved
+    // Under commit-reveal only revealed votes count, never a signature
+    let signers = if parse_commit_reveal(data)?.is_some() { &[] } else { signers };
+    let round = approval_round(data)?;
+    for i in 0..MAX_NOTARIES as u8 {
+        if has_current_approval(data, i, round) {
+            approved |= 1 << i;
+        }
+    }
+    for signer in signers {
+        match check_listed_notary(data, signer) {
+            Ok(index) => approved |= 1 << index,
+            Err(ERR_WRONG_ACCOUNT) => {}
+            Err(code) => return Err(code),
+        }
+    }
+    Ok(approved.count_ones() as u8)
+}
+
+/// Threshold check for finish(). Without signers this is exactly
+/// check_tiered_threshold; with signers, their approvals are added.
+/// With a `signer_list`, the SignerList quorum applies instead.
+fn check_threshold_with_signers(
+    data: &[u8],
+    signer_list: Option<&SignerList>,
+    amount_drops: Option<u64>,
+    current_ledger: u32,
+    signers: &[[u8; ACCOUNT_ID_SIZE]],
+) -> i32 {
+    if let Some(list) = signer_list {
+        return check_signer_list_quorum(data, list, signers);
+    }
+    if signers.is_empty() {
+        return check_tiered_threshold(data, amount_drops, current_ledger);
+    }
+    let threshold = match required_threshold(data, amount_drops, current_ledger) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+    match count_approvals_with_signers(data, signers) {
+        Ok(count) if count >= threshold => SUCCESS,
+        Ok(_) => ERR_NOT_APPROVED,
+        Err(code) => code,
+    }
+}
+
+/// finish_by check that also accepts a multisigned transaction when one of
+/// its signers may finish (e.g. a notary signing for a shared account).
+fn check_finish_submitter_or_signers(
+    data: &[u8],
+    signer_list: Option<&SignerList>,
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    signers: &[[u8; ACCOUNT_ID_SIZE]],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    match check_submitter_roles(data, signer_list, caller, owner, destination) {
+        Err(ERR_WRONG_ACCOUNT)
+            if signers
+                .iter()
+                .any(|s| check_submitter_roles(data, signer_list, s, owner, destination).is_ok()) =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// SIGNERLIST NOTARIES — membership read from the ledger
+//
+// With `notaries_from=signerlist:<account hex>`, the notaries are the
+// SignerEntries of that account's SignerList, read at every invocation.
+// Approvals are weighted by SignerWeight and release needs SignerQuorum,
+// so editing the SignerList changes every escrow that points at it
+// without touching their data.
+//
+// Approvals are stored per account as `signer_approval_<account hex>=1`
+// and only count while that account is still in the list; stale ones are
+// dropped on the next approval or revocation. The mode replaces the data
+// notary list and every feature keyed by notary index (tiers, decay, the
+// backup set, governance and upgrade votes), so those keys are rejected.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Key prefix of a SignerList member's stored approval
+const SIGNER_APPROVAL_PREFIX: &[u8] = b"signer_approval_";
+
+/// Keys that configure the data notary list or index-keyed features.
+const SIGNERLIST_CONFLICTING_KEYS: [&[u8]; 9] = [
+    b"notary_count",
+    b"threshold",
+    b"backup_notary_count",
+    b"inactivity_ledgers",
+    b"tier_0",
+    b"decay_0",
+    b"gov_threshold",
+    b"upgrade_threshold",
+    b"abort_threshold",
+];
+
+/// Notaries read from a SignerList ledger object.
+#[derive(Clone, Copy, Debug)]
+struct SignerList {
+    accounts: [[u8; ACCOUNT_ID_SIZE]; MAX_SIGNERS],
+    weights: [u16; MAX_SIGNERS],
+    len: usize,
+    quorum: u32,
+}
+
+impl SignerList {
+    /// SignerWeight of `account`, or None if it is not a member.
+    fn weight_of(&self, account: &[u8; ACCOUNT_ID_SIZE]) -> Option<u16> {
+        self.accounts[..self.len]
+            .iter()
+            .position(|a| a == account)
+            .map(|i| self.weights[i])
+    }
+
+    fn contains(&self, account: &[u8; ACCOUNT_ID_SIZE]) -> bool {
+        self.weight_of(account).is_some()
+    }
+}
+
+/// Parse `notaries_from`. Returns the SignerList owner, or None when the
+/// escrow uses its own notary list.
+fn parse_notaries_from(data: &[u8]) -> Result<Option<[u8; ACCOUNT_ID_SIZE]>, i32> {
+    let value = match find_value(data, b"notaries_from") {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let hex = value.strip_prefix(b"signerlist:").ok_or(ERR_BAD_CONFIG)?;
+    let mut account = [0u8; ACCOUNT_ID_SIZE];
+    if hex.len() != ACCOUNT_ID_SIZE * 2 || decode_hex(hex, &mut account) != Some(ACCOUNT_ID_SIZE) {
+        return Err(ERR_BAD_CONFIG);
+    }
+    if SIGNERLIST_CONFLICTING_KEYS.iter().any(|k| find_value(data, k).is_some()) {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(Some(account))
+}
+
+/// Membership check against whichever notary source the escrow uses.
+fn check_notary_membership(
+    data: &[u8],
+    signer_list: Option<&SignerList>,
+    account: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    match signer_list {
+        Some(list) if list.contains(account) => Ok(()),
+        Some(_) => Err(ERR_WRONG_ACCOUNT),
+        None => check_listed_notary(data, account).map(|_| ()),
+    }
+}
+
+/// Build `signer_approval_<account hex>` into `out`; returns its length.
+fn signer_approval_key(account: &[u8; ACCOUNT_ID_SIZE], out: &mut [u8; 56]) -> usize {
+    let prefix_len = SIGNER_APPROVAL_PREFIX.len();
+    out[..prefix_len].copy_from_slice(SIGNER_APPROVAL_PREFIX);
+    prefix_len + encode_hex(account, &mut out[prefix_len..]).unwrap_or(0)
+}
+
+/// Whether `key` is a stored approval of an account no longer in the list.
+fn is_stale_signer_approval(key: &[u8], list: &SignerList) -> bool {
+    let hex = match key.strip_prefix(SIGNER_APPROVAL_PREFIX) {
+        Some(h) => h,
+        None => return false,
+    };
+    let mut account = [0u8; ACCOUNT_ID_SIZE];
+    decode_hex(hex, &mut account) != Some(ACCOUNT_ID_SIZE) || !list.contains(&account)
+}
+
+/// Total SignerWeight of members with a stored approval or among `signers`.
+/// Returns (members approved, weight).
+fn signer_list_weight(data: &[u8], list: &SignerList, signers: &[[u8; ACCOUNT_ID_SIZE]]) -> (u8, u32) {
+    let mut members: u8 = 0;
+    let mut weight: u32 = 0;
+    let mut key_buf = [0u8; 56];
+    for i in 0..list.len {
+        let account = &list.accounts[i];
+        let key_len = signer_approval_key(account, &mut key_buf);
+        if find_value(data, &key_buf[..key_len]) == Some(b"1") || signers.contains(account) {
+            members = members.saturating_add(1);
+            weight += list.weights[i] as u32;
+        }
+    }
+    (members, weight)
+}
+
+/// Release check for a SignerList escrow: approved weight must reach the quorum.
+fn check_signer_list_quorum(data: &[u8], list: &SignerList, signers: &[[u8; ACCOUNT_ID_SIZE]]) -> i32 {
+    let (_, weight) = signer_list_weight(data, list, signers);
+    if list.quorum > 0 && weight >= list.quorum {
+        SUCCESS
+    } else {
+        ERR_NOT_APPROVED
+    }
+}
+
+/// Lifecycle tally of a SignerList escrow.
+fn signer_list_tally(data: &[u8], list: &SignerList) -> Tally {
+    let (members, weight) = signer_list_weight(data, list, &[]);
+    Tally { approvals: members, quorum: list.quorum > 0 && weight >= list.quorum }
+}
+
+/// Rebuild the data with `caller`'s approval set (`approved`) or removed,
+/// dropping stale approvals. `quorum_ledger` is recorded when the approved
+/// weight first reaches the quorum and cleared when it falls below.
+/// Withdrawing an approval is subject to `revocation_policy`; signer
+/// approvals carry no ledger, so the window policy isn't available here.
+fn record_signer_list_vote(
+    existing_data: &[u8],
+    existing_len: usize,
+    list: &SignerList,
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    approved: bool,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    if !list.contains(caller) {
+        return Err(ERR_WRONG_ACCOUNT);
+    }
+    let mut key_buf = [0u8; 56];
+    let key_len = signer_approval_key(caller, &mut key_buf);
+    let key = &key_buf[..key_len];
+    let had_approved = find_value(existing_data, key) == Some(b"1");
+    if approved && had_approved {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+    let withdrawn = !approved && had_approved;
+    let policy = parse_revocation_policy(existing_data)?;
+    if withdrawn {
+        check_revocation_allowed(existing_data, policy, None, current_ledger)?;
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == key || k == b"last_activity_ledger" || k == b"quorum_ledger" || is_stale_signer_approval(k, list)
+            || (withdrawn && (k == b"last_revocation_policy" || k == b"last_revocation_ledger"))
+    });
+    if approved {
+        if pos > 0 {
+            pos = write_separator(&mut new_data, pos);
+        }
+        pos = write_entry(&mut new_data, pos, key, b"1");
+    }
+
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"last_activity_ledger", &ledger_buf[..ledger_len]);
+
+    // Keep an existing quorum_ledger while quorum holds, start one if new
+    if check_signer_list_quorum(&new_data[..pos], list, &[]) == SUCCESS {
+        let quorum_ledger = find_value(existing_data, b"quorum_ledger").unwrap_or(&ledger_buf[..ledger_len]);
+        pos = write_separator(&mut new_data, pos);
+        pos = write_entry(&mut new_data, pos, b"quorum_ledger", quorum_ledger);
+    }
+    if withdrawn {
+        pos = write_revocation_audit(&mut new_data, pos, policy, current_ledger);
+    }
+
+    Ok((new_data, pos))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// RELEASE POLICY — expression in place of the fixed condition sequence
+//
+// The language itself lives in src/expr.rs. This is the glue to the
+// contract data: the `policy` key, and the `guardian` check it adds.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Parse the `policy` key. Returns None for escrows using the fixed
+/// condition sequence. `hashlock_mode` only combines the fixed sequence,
+/// so setting it alongside a policy is rejected rather than ignored.
+fn parse_release_policy(data: &[u8]) -> Result<Option<expr::Policy>, i32> {
+    let source = match find_value(data, b"policy") {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if find_value(data, b"hashlock_mode").is_some() {
+        return Err(ERR_BAD_CONFIG);
+    }
+    expr::parse_policy(source).map(Some)
+}
+
+/// The policy's `guardian` check: the configured guardian submitted the
+/// EscrowFinish or is among its Signers.
+fn check_guardian(data: &[u8], caller: &[u8; ACCOUNT_ID_SIZE], signers: &[[u8; ACCOUNT_ID_SIZE]]) -> i32 {
+    let hex = match find_value(data, b"guardian") {
+        Some(h) if h.len() == ACCOUNT_ID_SIZE * 2 => h,
+        _ => return ERR_BAD_CONFIG,
+    };
+    let mut guardian = [0u8; ACCOUNT_ID_SIZE];
+    if decode_hex(hex, &mut guardian) != Some(ACCOUNT_ID_SIZE) {
+        return ERR_BAD_CONFIG;
+    }
+    if *caller == guardian || signers.contains(&guardian) {
+        SUCCESS
+    } else {
+        ERR_WRONG_ACCOUNT
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// HOST HELPERS — thin wrappers over xrpl-wasm-stdlib (WASM only)
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Write rebuilt contract data back to the current escrow.
+#[cfg(target_arch = "wasm32")]
+fn write_contract_data(new_data: &[u8], new_len: usize) -> i32 {
+    let mut update = ContractData {
+        data: [0u8; XRPL_CONTRACT_DATA_SIZE],
+        len: new_len,
+    };
+    update.data[..new_len].copy_from_slice(&new_data[..new_len]);
+    match <xrpl_wasm_stdlib::core::ledger_objects::current_escrow::CurrentEscrow as CurrentEscrowFields>::update_current_escrow_data(update) {
+        xrpl_wasm_stdlib::host::Result::Ok(_) => SUCCESS,
+        xrpl_wasm_stdlib::host::Result::Err(_) => ERR_DATA_READ,
+    }
+}
+
+/// Record a finish attempt in the audit fields and pass `result_code` through.
+#[cfg(target_arch = "wasm32")]
+fn audit_and_return(data: &[u8], data_len: usize, result_code: i32, tx_seq: u32) -> i32 {
+    let (audit_data, audit_len) = record_audit(data, data_len, result_code, tx_seq);
+    let _ = write_contract_data(&audit_data, audit_len);
+    result_code
+}
+
+/// Read the escrow's data with any template expanded, the dead-man switch
+/// and any due governance change applied — the preamble every entry point
+/// runs before it looks at the data (contract.rs `load` in the XLS-101 build).
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+fn load_escrow_data(current_ledger: u32) -> Result<([u8; 4096], usize), i32> {
+    let contract_data = match get_current_escrow().get_data() {
+        xrpl_wasm_stdlib::host::Result::Ok(data) => data,
+        xrpl_wasm_stdlib::host::Result::Err(_) => {
+            let _ = trace("!!! Failed to read contract data");
+            return Err(ERR_DATA_READ);
+        }
+    };
+    let mut data = [0u8; 4096];
+    let mut len = contract_data.len.min(data.len());
+    data[..len].copy_from_slice(&contract_data.data[..len]);
+
+    if let Some((d, l)) = template::expand_template(&data[..len], len)? {
+        data = d;
+        len = l;
+    }
+    if let Some((d, l)) = apply_inactivity_switch(&data[..len], len, current_ledger)? {
+        let _ = trace("    Backup notary set is now authoritative");
+        data = d;
+        len = l;
+    }
+    if let Some((d, l)) = apply_due_proposal(&data[..len], len, escrow_amount_drops()?, current_ledger)? {
+        let _ = trace("    Governance proposal applied");
+        data = d;
+        len = l;
+    }
+    Ok((data, len))
+}
+
+/// Read the first Memo's MemoData from the current transaction into `out`.
+/// Returns the number of bytes written, or None if the transaction has no memo.
+#[cfg(target_arch = "wasm32")]
+fn read_memo_data(out: &mut [u8]) -> Option<usize> {
+    use xrpl_wasm_stdlib::core::locator::Locator;
+    use xrpl_wasm_stdlib::host::get_tx_nested_field;
+    use xrpl_wasm_stdlib::sfield;
+
+    let mut locator = Locator::new();
+    locator.pack(sfield::Memos);
+    locator.pack(0);
+    locator.pack(sfield::MemoData);
+    let len = unsafe { get_tx_nested_field(locator.get_addr(), locator.num_packed_bytes(), out.as_mut_ptr(), out.len()) };
+    if len < 0 {
+        None
+    } else {
+        Some(len as usize)
+    }
+}
+
+/// Read the Account of each entry in the transaction's Signers array.
+/// Returns how many were read — 0 for a singly-signed transaction.
+#[cfg(target_arch = "wasm32")]
+fn read_signers(out: &mut [[u8; ACCOUNT_ID_SIZE]; MAX_SIGNERS]) -> Result<usize, i32> {
+    use xrpl_wasm_stdlib::core::locator::Locator;
+    use xrpl_wasm_stdlib::host::{get_tx_array_len, get_tx_nested_field};
+    use xrpl_wasm_stdlib::sfield;
+
+    let count = unsafe { get_tx_array_len(sfield::Signers) };
+    if count <= 0 {
+        return Ok(0);
+    }
+    let count = (count as usize).min(MAX_SIGNERS);
+
+    for (i, account) in out.iter_mut().enumerate().take(count) {
+        let mut locator = Locator::new();
+        locator.pack(sfield::Signers);
+        locator.pack(i as i32);
+        locator.pack(sfield::Account);
+        let len = unsafe {
+            get_tx_nested_field(locator.get_addr(), locator.num_packed_bytes(), account.as_mut_ptr(), account.len())
+        };
+        if len != ACCOUNT_ID_SIZE as i32 {
+            return Err(ERR_HOST_CALL);
+        }
+    }
+    Ok(count)
+}
+
+/// Read the notaries of a `notaries_from` escrow from its SignerList.
+/// Returns Ok(None) for an escrow with its own notary list. A configured
+/// SignerList that no longer exists is ERR_BAD_CONFIG: nobody can approve.
+#[cfg(target_arch = "wasm32")]
+fn load_signer_list(data: &[u8]) -> Result<Option<SignerList>, i32> {
+    use xrpl_wasm_stdlib::core::locator::Locator;
+    use xrpl_wasm_stdlib::host::{cache_ledger_obj, get_ledger_obj_array_len, get_ledger_obj_field, get_ledger_obj_nested_field};
+    use xrpl_wasm_stdlib::sfield;
+
+    let owner = match parse_notaries_from(data)? {
+        Some(o) => o,
+        None => return Ok(None),
+    };
+    let index = keylet::signer_list_keylet(&owner);
+    let slot = unsafe { cache_ledger_obj(index.as_ptr(), index.len(), 0) };
+    if slot < 0 {
+        return Err(ERR_BAD_CONFIG);
+    }
+
+    let mut quorum_buf = [0u8; 4];
+    if unsafe { get_ledger_obj_field(slot, sfield::SignerQuorum, quorum_buf.as_mut_ptr(), quorum_buf.len()) } < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+    let mut list = SignerList {
+        accounts: [[0u8; ACCOUNT_ID_SIZE]; MAX_SIGNERS],
+        weights: [0u16; MAX_SIGNERS],
+        len: 0,
+        quorum: u32::from_le_bytes(quorum_buf),
+    };
+
+    let count = unsafe { get_ledger_obj_array_len(slot, sfield::SignerEntries) };
+    if count < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+    for i in 0..(count as usize).min(MAX_SIGNERS) {
+        let mut locator = Locator::new();
+        locator.pack(sfield::SignerEntries);
+        locator.pack(i as i32);
+        locator.pack(sfield::Account);
+        let account = &mut list.accounts[i];
+        if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), account.as_mut_ptr(), account.len()) }
+            != ACCOUNT_ID_SIZE as i32
+        {
+            return Err(ERR_HOST_CALL);
+        }
+
+        let mut weight_buf = [0u8; 2];
+        let mut locator = Locator::new();
+        locator.pack(sfield::SignerEntries);
+        locator.pack(i as i32);
+        locator.pack(sfield::SignerWeight);
+        if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), weight_buf.as_mut_ptr(), weight_buf.len()) } < 0 {
+            return Err(ERR_HOST_CALL);
+        }
+        list.weights[i] = u16::from_le_bytes(weight_buf);
+        list.len = i + 1;
+    }
+    Ok(Some(list))
+}
+
+/// Read the Data field of the escrow object at `index`.
+/// Returns Ok(None) if no object exists there; an escrow without a Data
+/// field reads as empty data.
+#[cfg(target_arch = "wasm32")]
+fn read_escrow_data(
+    index: &[u8; keylet::KEYLET_SIZE],
+) -> Result<Option<([u8; XRPL_CONTRACT_DATA_SIZE], usize)>, i32> {
+    use xrpl_wasm_stdlib::host::{cache_ledger_obj, get_ledger_obj_field};
+    use xrpl_wasm_stdlib::sfield;
+
+    let slot = unsafe { cache_ledger_obj(index.as_ptr(), index.len(), 0) };
+    if slot < 0 {
+        return Ok(None);
+    }
+
+    let mut buf = [0u8; XRPL_CONTRACT_DATA_SIZE];
+    let len = unsafe { get_ledger_obj_field(slot, sfield::Data, buf.as_mut_ptr(), buf.len()) };
+    Ok(Some((buf, len.max(0) as usize)))
+}
+
+/// Sequence number of the ledger currently being built.
+#[cfg(target_arch = "wasm32")]
+fn current_ledger_sequence() -> Result<u32, i32> {
+    let mut buf = [0u8; 4];
+    if unsafe { xrpl_wasm_stdlib::host::get_ledger_sqn(buf.as_mut_ptr(), buf.len()) } < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// The current escrow's Amount in drops, or None for a token escrow.
+#[cfg(target_arch = "wasm32")]
+fn escrow_amount_drops() -> Result<Option<u64>, i32> {
+    match get_current_escrow().get_amount() {
+        xrpl_wasm_stdlib::host::Result::Ok(TokenAmount::XRP { num_drops }) => Ok(Some(num_drops as u64)),
+        xrpl_wasm_stdlib::host::Result::Ok(_) => Ok(None),
+        xrpl_wasm_stdlib::host::Result::Err(_) => Err(ERR_HOST_CALL),
+    }
+}
+
+/// Read an optional UInt32 field of the current escrow (e.g. DestinationTag).
+/// The host reports an absent field as an error, which reads as None.
+#[cfg(target_arch = "wasm32")]
+fn escrow_optional_u32(field: i32) -> Option<u32> {
+    let mut buf = [0u8; 4];
+    let len = unsafe { xrpl_wasm_stdlib::host::get_current_ledger_obj_field(field, buf.as_mut_ptr(), buf.len()) };
+    if len == buf.len() as i32 {
+        Some(u32::from_le_bytes(buf))
+    } else {
+        None
+    }
+}
+
+/// Parent ledger close time in Ripple epoch seconds.
+#[cfg(target_arch = "wasm32")]
+fn parent_ledger_time() -> Result<u32, i32> {
+    let mut buf = [0u8; 4];
+    if unsafe { xrpl_wasm_stdlib::host::get_parent_ledger_time(buf.as_mut_ptr(), buf.len()) } < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Parent ledger close time, converted from the Ripple epoch to Unix seconds.
+#[cfg(target_arch = "wasm32")]
+fn current_unix_time() -> Result<u32, i32> {
+    parent_ledger_time().map(|t| t.saturating_add(RIPPLE_EPOCH_OFFSET))
+}
+
+/// Read the Credential object for `subject` described by `condition`.
+/// Returns Ok(None) if it does not exist.
+#[cfg(target_arch = "wasm32")]
+fn read_credential(
+    subject: &[u8; ACCOUNT_ID_SIZE],
+    condition: &CredentialCondition,
+) -> Result<Option<CredentialState>, i32> {
+    use xrpl_wasm_stdlib::core::types::account_id::AccountID;
+    use xrpl_wasm_stdlib::core::types::keylets::credential_keylet;
+    use xrpl_wasm_stdlib::host::{cache_ledger_obj, get_ledger_obj_field};
+    use xrpl_wasm_stdlib::sfield;
+
+    let keylet = match credential_keylet(
+        &AccountID(*subject),
+        &AccountID(condition.issuer),
+        condition.credential_type(),
+    ) {
+        xrpl_wasm_stdlib::host::Result::Ok(k) => k,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return Err(ERR_HOST_CALL),
+    };
+    let slot = unsafe { cache_ledger_obj(keylet.as_ptr(), keylet.len(), 0) };
+    if slot < 0 {
+        return Ok(None);
+    }
+
+    let mut flags_buf = [0u8; 4];
+    if unsafe { get_ledger_obj_field(slot, sfield::Flags, flags_buf.as_mut_ptr(), flags_buf.len()) } < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+
+    // Expiration is optional — a missing field means the credential never expires
+    let mut exp_buf = [0u8; 4];
+    let expiration = if unsafe { get_ledger_obj_field(slot, sfield::Expiration, exp_buf.as_mut_ptr(), exp_buf.len()) } < 0 {
+        None
+    } else {
+        Some(u32::from_le_bytes(exp_buf))
+    };
+
+    Ok(Some(CredentialState {
+        flags: u32::from_le_bytes(flags_buf),
+        expiration,
+    }))
+}
+
+/// Read the configured asset pair from the Oracle ledger object.
+/// Returns Ok(None) if the Oracle does not exist or does not quote the pair.
+#[cfg(target_arch = "wasm32")]
+fn read_oracle_price(condition: &OracleCondition) -> Result<Option<OraclePrice>, i32> {
+    use xrpl_wasm_stdlib::core::locator::Locator;
+    use xrpl_wasm_stdlib::core::types::account_id::AccountID;
+    use xrpl_wasm_stdlib::core::types::keylets::oracle_keylet;
+    use xrpl_wasm_stdlib::host::{cache_ledger_obj, get_ledger_obj_field, get_ledger_obj_nested_field};
+    use xrpl_wasm_stdlib::sfield;
+
+    /// XLS-47 caps PriceDataSeries at 10 entries
+    const MAX_PRICE_DATA: usize = 10;
+
+    let keylet = match oracle_keylet(&AccountID(condition.owner), condition.document_id) {
+        xrpl_wasm_stdlib::host::Result::Ok(k) => k,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return Err(ERR_HOST_CALL),
+    };
+    let slot = unsafe { cache_ledger_obj(keylet.as_ptr(), keylet.len(), 0) };
+    if slot < 0 {
+        return Ok(None);
+    }
+
+    let mut time_buf = [0u8; 4];
+    if unsafe { get_ledger_obj_field(slot, sfield::LastUpdateTime, time_buf.as_mut_ptr(), time_buf.len()) } < 0 {
+        return Err(ERR_HOST_CALL);
+    }
+    let last_update_time = u32::from_le_bytes(time_buf);
+
+    for i in 0..MAX_PRICE_DATA {
+        let mut base = [0u8; CURRENCY_SIZE];
+        let mut quote = [0u8; CURRENCY_SIZE];
+
+        let mut locator = Locator::new();
+        locator.pack(sfield::PriceDataSeries);
+        locator.pack(i as i32);
+        locator.pack(sfield::BaseAsset);
+        if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), base.as_mut_ptr(), base.len()) } < 0 {
+            break; // ran off the end of the series
+        }
+
+        let mut locator = Locator::new();
+        locator.pack(sfield::PriceDataSeries);
+        locator.pack(i as i32);
+        locator.pack(sfield::QuoteAsset);
+        if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), quote.as_mut_ptr(), quote.len()) } < 0 {
+            continue;
+        }
+
+        if base != condition.base || quote != condition.quote {
+            continue;
+        }
+
+        // AssetPrice is omitted when the provider has no current price
+        let mut price_buf = [0u8; 8];
+        let mut locator = Locator::new();
+        locator.pack(sfield::PriceDataSeries);
+        locator.pack(i as i32);
+        locator.pack(sfield::AssetPrice);
+        if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), price_buf.as_mut_ptr(), price_buf.len()) } < 0 {
+            return Ok(None);
+        }
+
+        let mut scale_buf = [0u8; 1];
+        let mut locator = Locator::new();
+        locator.pack(sfield::PriceDataSeries);
+        locator.pack(i as i32);
+        locator.pack(sfield::Scale);
+        let scale = if unsafe { get_ledger_obj_nested_field(slot, locator.get_addr(), locator.num_packed_bytes(), scale_buf.as_mut_ptr(), scale_buf.len()) } < 0 {
+            0 // Scale defaults to 0 when absent
+        } else {
+            scale_buf[0]
+        };
+
+        return Ok(Some(OraclePrice {
+            asset_price: u64::from_le_bytes(price_buf),
+            scale,
+            last_update_time,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Policy inputs for finish(), read from the host on demand.
+#[cfg(target_arch = "wasm32")]
+struct FinishInputs<'a> {
+    data: &'a [u8],
+    signer_list: Option<&'a SignerList>,
+    signers: &'a [[u8; ACCOUNT_ID_SIZE]],
+    caller: &'a [u8; ACCOUNT_ID_SIZE],
+    destination: &'a [u8; ACCOUNT_ID_SIZE],
+    amount_drops: Option<u64>,
+    current_ledger: u32,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl expr::Inputs for FinishInputs<'_> {
+    fn value(&mut self, term: expr::Term) -> Result<u64, i32> {
+        match term {
+            expr::Term::Approvals => match self.signer_list {
+                Some(list) => Ok(signer_list_weight(self.data, list, self.signers).1 as u64),
+                None => count_approvals_with_signers(self.data, self.signers).map(u64::from),
+            },
+            expr::Term::Ledger => Ok(self.current_ledger as u64),
+            expr::Term::Time => current_unix_time().map(u64::from),
+        }
+    }
+
+    fn check(&mut self, check: expr::Check) -> i32 {
+        let data = self.data;
+        match check {
+            expr::Check::Threshold => check_threshold_with_signers(
+                data, self.signer_list, self.amount_drops, self.current_ledger, self.signers,
+            ),
+            expr::Check::Challenge => check_challenge_period(data, self.current_ledger),
+            expr::Check::Preimage => match parse_hashlock(data) {
+                Ok(Some(lock)) => {
+                    let mut preimage_buf = [0u8; MAX_PREIMAGE_SIZE + 1];
+                    let preimage = read_memo_data(&mut preimage_buf).map(|len| &preimage_buf[..len.min(preimage_buf.len())]);
+                    check_preimage(&lock, preimage)
+                }
+                Ok(None) => ERR_BAD_CONFIG,
+                Err(code) => code,
+            },
+            expr::Check::Guardian => check_guardian(data, self.caller, self.signers),
+            expr::Check::Credential => match parse_credential_condition(data) {
+                Ok(Some(condition)) => {
+                    let now = match parent_ledger_time() {
+                        Ok(t) => t,
+                        Err(code) => return code,
+                    };
+                    match read_credential(self.destination, &condition) {
+                        Ok(state) => check_credential(state, now),
+                        Err(code) => code,
+                    }
+                }
+                Ok(None) => ERR_BAD_CONFIG,
+                Err(code) => code,
+            },
+            expr::Check::Oracle => match parse_oracle_condition(data) {
+                Ok(Some(condition)) => {
+                    let price = match read_oracle_price(&condition) {
+                        Ok(p) => p,
+                        Err(code) => return code,
+                    };
+                    match current_unix_time() {
+                        Ok(now) => check_oracle_condition(&condition, price, now),
+                        Err(code) => code,
+                    }
+                }
+                Ok(None) => ERR_BAD_CONFIG,
+                Err(code) => code,
+            },
+            expr::Check::Dependency => match parse_dependency(data) {
+                Ok(Some(dep)) => match read_escrow_data(&dep.keylet()) {
+                    Ok(Some((dep_data, dep_len))) => check_dependency(&dep, Some(&dep_data[..dep_len])),
+                    Ok(None) => check_dependency(&dep, None),
+                    Err(code) => code,
+                },
+                Ok(None) => ERR_BAD_CONFIG,
+                Err(code) => code,
+            },
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// WASM ENTRY POINTS
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Main entry point — called by rippled when someone submits EscrowFinish.
+/// Checks all conditions and returns positive to release funds.
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn finish() -> i32 {
+    let tx = get_current_escrow_finish();
+    let escrow = get_current_escrow();
+
+    // ─── Read transaction account (who is calling finish?) ───
+    let _ = trace(">>> Condition 1: caller authorization");
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => {
+            let _ = trace("!!! Failed to read tx account");
+            return ERR_HOST_CALL;
+        }
+    };
+
+    // ─── Read contract data: template, dead-man switch, governance ───
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    // ─── Lifecycle: only an open escrow can be released ───
+    let (released_data, released_len) = match record_transition(data, data_len, LifecycleEvent::Release) {
+        Ok(r) => r,
+        Err(ERR_ABORTED) => {
+            let _ = trace("!!! Escrow was aborted by notary vote");
+            return audit_and_return(data, data_len, ERR_ABORTED, tx.get_sequence().unwrap_or(0));
+        }
+        Err(code) => {
+            let _ = trace("!!! Escrow is not open for release");
+            return code;
+        }
+    };
+
+    // ─── Read escrow parties (for the finish_by policy) ───
+    let owner = match escrow.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => {
+            let _ = trace("!!! Failed to read escrow account");
+            return ERR_HOST_CALL;
+        }
+    };
+    let destination = match escrow.get_destination() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => {
+            let _ = trace("!!! Failed to read escrow destination");
+            return ERR_HOST_CALL;
+        }
+    };
+
+    // ─── Config binding: the data must belong to this escrow ───
+    let amount_drops = match escrow_amount_drops() {
+        Ok(a) => a,
+        Err(code) => {
+            let _ = trace("!!! Failed to read escrow amount");
+            return code;
+        }
+    };
+    let escrow_fields = EscrowFields {
+        owner: owner.0,
+        destination: destination.0,
+        destination_tag: escrow_optional_u32(xrpl_wasm_stdlib::sfield::DestinationTag),
+        source_tag: escrow_optional_u32(xrpl_wasm_stdlib::sfield::SourceTag),
+        amount_drops,
+    };
+    let binding_result = check_config_binding(data, &escrow_fields);
+    if binding_result != SUCCESS {
+        let _ = trace("!!! Contract data is bound to a different escrow");
+        return binding_result;
+    }
+
+    // ─── Multisigned finish: signers that are notaries count as approvals ───
+    let mut signer_buf = [[0u8; ACCOUNT_ID_SIZE]; MAX_SIGNERS];
+    let signers = match read_signers(&mut signer_buf) {
+        Ok(n) => &signer_buf[..n],
+        Err(code) => return code,
+    };
+
+    // ─── notaries_from: membership, weights and quorum from a SignerList ───
+    let signer_list = match load_signer_list(data) {
+        Ok(l) => l,
+        Err(code) => {
+            let _ = trace("!!! Failed to read notary SignerList");
+            return code;
+        }
+    };
+
+    // ─── Check caller (or a signer) is allowed to finish (notary by default) ───
+    match check_finish_submitter_or_signers(data, signer_list.as_ref(), &caller.0, signers, &owner.0, &destination.0) {
+        Ok(()) => { let _ = trace("    OK caller may finish"); }
+        Err(code) => {
+            let _ = trace("!!! Caller is not allowed to finish");
+            return code;
+        }
+    }
+
+    // ─── Condition 2: time-lock via FinishAfter ───
+    let _ = trace(">>> Condition 2: time-lock");
+    let finish_after = match escrow.get_finish_after() {
+        xrpl_wasm_stdlib::host::Result::Ok(val) => val,
+        xrpl_wasm_stdlib::host::Result::Err(_) => {
+            let _ = trace("!!! Failed to read finish_after");
+            return ERR_HOST_CALL;
+        }
+    };
+    let time_result = check_time_lock(finish_after);
+    if time_result != SUCCESS {
+        let _ = trace("!!! Time-lock not satisfied");
+        return time_result;
+    }
+    let _ = trace("    OK time-lock passed");
+
+    let tx_seq = tx.get_sequence().unwrap_or(0);
+
+    // ─── Required owner/destination consent (never counts as an approval) ───
+    let consent_result = check_consent(data);
+    if consent_result != SUCCESS {
+        let _ = trace("!!! Required party consent missing");
+        return audit_and_return(data, data_len, consent_result, tx_seq);
+    }
+
+    // ─── Destination acknowledgement (never counts as an approval) ───
+    let ack_result = check_destination_ack(data);
+    if ack_result != SUCCESS {
+        let _ = trace("!!! Destination has not acknowledged");
+        return audit_and_return(data, data_len, ack_result, tx_seq);
+    }
+
+    // ─── A `policy` expression replaces the fixed condition sequence ───
+    let policy = match parse_release_policy(data) {
+        Ok(p) => p,
+        Err(code) => return audit_and_return(data, data_len, code, tx_seq),
+    };
+    if let Some(policy) = policy {
+        let _ = trace(">>> Condition: release policy");
+        let mut inputs = FinishInputs {
+            data,
+            signer_list: signer_list.as_ref(),
+            signers,
+            caller: &caller.0,
+            destination: &destination.0,
+            amount_drops,
+            current_ledger,
+        };
+        return match expr::evaluate(&policy, &mut inputs) {
+            Ok(true) => {
+                let _ = trace("=== POLICY MET — releasing funds ===");
+                audit_and_return(&released_data[..released_len], released_len, SUCCESS, tx_seq)
+            }
+            Ok(false) => {
+                let _ = trace("!!! Release policy not met");
+                audit_and_return(data, data_len, ERR_POLICY, tx_seq)
+            }
+            Err(code) => audit_and_return(data, data_len, code, tx_seq),
+        };
+    }
+
+    // ─── Condition 3: approval threshold (tiered by Amount or decaying by ledger) ───
+    let _ = trace(">>> Condition 3: approval threshold");
+    let mut approval_result =
+        check_threshold_with_signers(data, signer_list.as_ref(), amount_drops, current_ledger, signers);
+
+    // ─── Quorum alone isn't enough: the challenge period must have elapsed ───
+    if approval_result == SUCCESS {
+        approval_result = check_challenge_period(data, current_ledger);
+        if approval_result != SUCCESS {
+            let _ = trace("!!! Challenge period after quorum still running");
+        }
+    }
+
+    // ─── Optional: hash-lock, combined with the threshold by hashlock_mode ───
+    let hashlock = match parse_hashlock(data) {
+        Ok(h) => h,
+        Err(code) => return audit_and_return(data, data_len, code, tx_seq),
+    };
+    if let Some(lock) = hashlock {
+        let _ = trace(">>> Condition: hash-lock preimage");
+        // One spare byte so an oversized memo is detected instead of truncated
+        let mut preimage_buf = [0u8; MAX_PREIMAGE_SIZE + 1];
+        let preimage = read_memo_data(&mut preimage_buf).map(|len| &preimage_buf[..len.min(preimage_buf.len())]);
+        approval_result = check_hashlock(&lock, preimage, approval_result);
+    }
+
+    if approval_result != SUCCESS {
+        let _ = trace("!!! Approval threshold / hash-lock not met");
+        // Write audit trail for the denial
+        return audit_and_return(data, data_len, approval_result, tx_seq);
+    }
+    let _ = trace("    OK approval threshold met");
+
+    // ─── Optional: credential condition (XLS-70) ───
+    let credential_condition = match parse_credential_condition(data) {
+        Ok(c) => c,
+        Err(code) => return audit_and_return(data, data_len, code, tx_seq),
+    };
+    if let Some(condition) = credential_condition {
+        let _ = trace(">>> Condition: credentials");
+        let now = match parent_ledger_time() {
+            Ok(t) => t,
+            Err(code) => return code,
+        };
+
+        let destination_state = match read_credential(&destination.0, &condition) {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let mut credential_result = check_credential(destination_state, now);
+
+        if credential_result == SUCCESS
+            && condition.require_notary
+            && check_notary_membership(data, signer_list.as_ref(), &caller.0).is_ok()
+        {
+            let caller_state = match read_credential(&caller.0, &condition) {
+                Ok(state) => state,
+                Err(code) => return code,
+            };
+            credential_result = check_credential(caller_state, now);
+        }
+
+        if credential_result != SUCCESS {
+            let _ = trace("!!! Required credential missing, unaccepted or expired");
+            return audit_and_return(data, data_len, credential_result, tx_seq);
+        }
+        let _ = trace("    OK credentials valid");
+    }
+
+    // ─── Optional: cross-escrow dependency ───
+    let dependency = match parse_dependency(data) {
+        Ok(d) => d,
+        Err(code) => return audit_and_return(data, data_len, code, tx_seq),
+    };
+    if let Some(dep) = dependency {
+        let _ = trace(">>> Condition: depends_on escrow finished");
+        let dependency_result = match read_escrow_data(&dep.keylet()) {
+            Ok(Some((dep_data, dep_len))) => check_dependency(&dep, Some(&dep_data[..dep_len])),
+            Ok(None) => check_dependency(&dep, None),
+            Err(code) => return code,
+        };
+        if dependency_result != SUCCESS {
+            let _ = trace("!!! Dependency escrow not finished yet");
+            return audit_and_return(data, data_len, dependency_result, tx_seq);
+        }
+        let _ = trace("    OK dependency finished");
+    }
+
+    // ─── Optional: price oracle condition (XLS-47) ───
+    let oracle_condition = match parse_oracle_condition(data) {
+        Ok(c) => c,
+        Err(code) => return audit_and_return(data, data_len, code, tx_seq),
+    };
+    if let Some(condition) = oracle_condition {
+        let _ = trace(">>> Condition: price oracle");
+        let price = match read_oracle_price(&condition) {
+            Ok(p) => p,
+            Err(code) => return code,
+        };
+        let now = match current_unix_time() {
+            Ok(t) => t,
+            Err(code) => return code,
+        };
+        let oracle_result = check_oracle_condition(&condition, price, now);
+        if oracle_result != SUCCESS {
+            let _ = trace("!!! Price oracle condition not met");
+            return audit_and_return(data, data_len, oracle_result, tx_seq);
+        }
+        let _ = trace("    OK price oracle condition met");
+    }
+
+    // ─── All conditions passed ───
+    let _ = trace("=== ALL CONDITIONS MET — releasing funds ===");
+
+    // Record successful release (state=released) in audit trail
+    audit_and_return(&released_data[..released_len], released_len, SUCCESS, tx_seq)
+}
+
+/// Called by a notary to record their approval.
+/// Each notary can only approve once. Requires M-of-N threshold.
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn set_approval() -> i32 {
+    let _ = trace(">>> set_approval called");
+    let tx = get_current_escrow_finish();
+    let escrow = get_current_escrow();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    if recorded_state(data) == Ok(EscrowState::Aborted) {
+        let _ = trace("!!! Escrow was aborted; approvals are closed");
+        return ERR_ABORTED;
+    }
+
+    // ─── A party whose consent is required records it instead of approving ───
+    let owner = match escrow.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    let destination = match escrow.get_destination() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    match consent_role(data, &caller.0, &owner.0, &destination.0) {
+        Ok(Some(role)) => {
+            return match record_consent(data, data_len, role, true, current_ledger) {
+                Ok((d, l)) => write_contract_data(&d, l),
+                Err(code) => code,
+            };
+        }
+        Ok(None) => {}
+        Err(code) => return code,
+    }
+
+    // ─── notaries_from: a weighted approval by a SignerList member ───
+    let signer_list = match load_signer_list(data) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    if let Some(list) = signer_list {
+        let (new_data, new_len) = match record_signer_list_vote(data, data_len, &list, &caller.0, true, current_ledger) {
+            Ok(r) => r,
+            Err(code) => {
+                let _ = trace("!!! Caller not authorized to approve");
+                return code;
+            }
+        };
+        let tally = signer_list_tally(&new_data[..new_len], &list);
+        return match record_transition(&new_data[..new_len], new_len, LifecycleEvent::Approve(tally)) {
+            Ok((d, l)) => write_contract_data(&d, l),
+            Err(code) => code,
+        };
+    }
+
+    // Verify caller is a registered notary and get their index
+    let notary_index = match check_caller_is_notary(data, &caller.0, current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => {
+            let _ = trace("!!! Caller not authorized to approve");
+            return code;
+        }
+    };
+
+    // ─── A memo of "proposal=<id>" makes this a governance vote instead ───
+    let mut memo_buf = [0u8; MAX_GOVERNANCE_MEMO_SIZE];
+    let memo = read_memo_data(&mut memo_buf).map(|len| &memo_buf[..len.min(memo_buf.len())]);
+    if let Some(id) = memo.and_then(|m| find_value(m, b"proposal")) {
+        let id = match parse_u32(id) {
+            Some(i) => i,
+            None => return ERR_PROPOSAL,
+        };
+        let (voted, voted_len) = match record_proposal_vote(data, data_len, id, notary_index, current_ledger) {
+            Ok(r) => r,
+            Err(code) => {
+                let _ = trace("!!! Failed to record proposal vote");
+                return code;
+            }
+        };
+        // Without a delay, the deciding vote applies the change at once
+        let applied = escrow_amount_drops()
+            .and_then(|amount| apply_due_proposal(&voted[..voted_len], voted_len, amount, current_ledger));
+        let result = match applied {
+            Ok(Some((applied, applied_len))) => write_contract_data(&applied, applied_len),
+            Ok(None) => write_contract_data(&voted, voted_len),
+            Err(code) => return code,
+        };
+        if result == SUCCESS {
+            let _ = trace("    OK proposal vote recorded");
+        }
+        return result;
+    }
+
+    let tx_seq = tx.get_sequence().unwrap_or(0);
+    let threshold = match escrow_amount_drops().and_then(|amount| required_threshold(data, amount, current_ledger)) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+
+    // ─── Commit-reveal: a sealed vote now, its reveal after commit_deadline ───
+    let phases = match parse_commit_reveal(data) {
+        Ok(p) => p,
+        Err(code) => return code,
+    };
+    if let Some(phases) = phases {
+        if let Some(digest_hex) = memo.and_then(|m| find_value(m, b"commit")) {
+            let result = match record_commit(data, data_len, &phases, notary_index, digest_hex, current_ledger) {
+                Ok((d, l)) => write_contract_data(&d, l),
+                Err(code) => code,
+            };
+            if result == SUCCESS {
+                let _ = trace("    OK vote committed");
+            }
+            return result;
+        }
+        let (vote, salt, salt_len) = match memo.map(parse_reveal_memo) {
+            Some(Ok(r)) => r,
+            Some(Err(code)) => return code,
+            None => return ERR_REVEAL,
+        };
+        let (revealed, revealed_len) =
+            match record_reveal(data, data_len, &phases, notary_index, (vote, &salt[..salt_len]), current_ledger) {
+                Ok(r) => r,
+                Err(code) => {
+                    let _ = trace("!!! Reveal refused");
+                    return code;
+                }
+            };
+        if vote == 0 {
+            return write_contract_data(&revealed, revealed_len);
+        }
+        let (new_data, new_len) = match record_approval(
+            &revealed[..revealed_len], revealed_len, notary_index, &caller.0, tx_seq, current_ledger, threshold,
+        ) {
+            Ok(r) => r,
+            Err(code) => return code,
+        };
+        let tally = Tally::of(&new_data[..new_len], threshold);
+        return match record_transition(&new_data[..new_len], new_len, LifecycleEvent::Approve(tally)) {
+            Ok((d, l)) => write_contract_data(&d, l),
+            Err(code) => code,
+        };
+    }
+
+    // Record the approval
+    let (new_data, new_len) = match record_approval(
+        data, data_len, notary_index, &caller.0, tx_seq, current_ledger, threshold
+    ) {
+        Ok((d, l)) => (d, l),
+        Err(code) => {
+            let _ = trace("!!! Failed to record approval");
+            return code;
+        }
+    };
+    let tally = Tally::of(&new_data[..new_len], threshold);
+    let (new_data, new_len) = match record_transition(&new_data[..new_len], new_len, LifecycleEvent::Approve(tally)) {
+        Ok(r) => r,
+        Err(code) => {
+            let _ = trace("!!! Escrow is not accepting approvals");
+            return code;
+        }
+    };
+
+    // Write updated data back to the escrow
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK approval recorded");
+    }
+    result
+}
+
+/// Called by a notary to revoke their own approval.
+/// Only the notary who approved can revoke their own approval.
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn revoke_approval() -> i32 {
+    let _ = trace(">>> revoke_approval called");
+    let tx = get_current_escrow_finish();
+    let escrow = get_current_escrow();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    // ─── A consenting party withdraws consent ───
+    let owner = match escrow.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    let destination = match escrow.get_destination() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    match consent_role(data, &caller.0, &owner.0, &destination.0) {
+        Ok(Some(role)) => {
+            return match record_consent(data, data_len, role, false, current_ledger) {
+                Ok((d, l)) => write_contract_data(&d, l),
+                Err(code) => code,
+            };
+        }
+        Ok(None) => {}
+        Err(code) => return code,
+    }
+
+    let signer_list = match load_signer_list(data) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    if let Some(list) = signer_list {
+        let (new_data, new_len) = match record_signer_list_vote(data, data_len, &list, &caller.0, false, current_ledger) {
+            Ok(r) => r,
+            Err(code) => return code,
+        };
+        let tally = signer_list_tally(&new_data[..new_len], &list);
+        return match record_transition(&new_data[..new_len], new_len, LifecycleEvent::Revoke(tally)) {
+            Ok((d, l)) => write_contract_data(&d, l),
+            Err(code) => code,
+        };
+    }
+
+    // Verify caller is a registered notary
+    let notary_index = match check_caller_is_notary(data, &caller.0, current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+
+    let threshold = match escrow_amount_drops().and_then(|amount| required_threshold(data, amount, current_ledger)) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+
+    // Record the revocation
+    let (new_data, new_len) = match record_revocation(data, data_len, notary_index, current_ledger, threshold) {
+        Ok((d, l)) => (d, l),
+        Err(code) => return code,
+    };
+    let tally = Tally::of(&new_data[..new_len], threshold);
+    let (new_data, new_len) = match record_transition(&new_data[..new_len], new_len, LifecycleEvent::Revoke(tally)) {
+        Ok(r) => r,
+        Err(code) => return code,
+    };
+
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK approval revoked");
+    }
+    result
+}
+
+/// Called by a notary to propose a config change (see GOVERNANCE PROPOSALS).
+/// The first Memo's MemoData carries "id=<n>;key=<key>;value=<value>", or
+/// "withdraw=<n>" for the proposer to withdraw proposal n.
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn propose() -> i32 {
+    let _ = trace(">>> propose called");
+    let tx = get_current_escrow_finish();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    let notary_index = match check_caller_is_notary(data, &caller.0, current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+
+    let mut memo_buf = [0u8; MAX_GOVERNANCE_MEMO_SIZE];
+    let memo_len = match read_memo_data(&mut memo_buf) {
+        Some(len) => len.min(memo_buf.len()),
+        None => return ERR_PROPOSAL,
+    };
+    let memo = &memo_buf[..memo_len];
+    if let Some(v) = find_value(memo, b"withdraw") {
+        let id = match parse_u32(v) {
+            Some(id) => id,
+            None => return ERR_PROPOSAL,
+        };
+        return match record_proposal_withdrawal(data, data_len, id, notary_index, current_ledger) {
+            Ok((new_data, new_len)) => write_contract_data(&new_data, new_len),
+            Err(code) => code,
+        };
+    }
+    let proposal = match parse_proposal(memo) {
+        Ok(p) => p,
+        Err(code) => return code,
+    };
+
+    let (new_data, new_len) = match record_proposal(data, data_len, &proposal, notary_index, current_ledger) {
+        Ok(r) => r,
+        Err(code) => {
+            let _ = trace("!!! Proposal refused");
+            return code;
+        }
+    };
+    // A governance threshold of 1 with no delay applies at once
+    let applied = escrow_amount_drops()
+        .and_then(|amount| apply_due_proposal(&new_data[..new_len], new_len, amount, current_ledger));
+    let result = match applied {
+        Ok(Some((applied, applied_len))) => write_contract_data(&applied, applied_len),
+        Ok(None) => write_contract_data(&new_data, new_len),
+        Err(code) => return code,
+    };
+    if result == SUCCESS {
+        let _ = trace("    OK proposal recorded");
+    }
+    result
+}
+
+/// Called by the escrow's Destination to acknowledge it controls the
+/// account (see DESTINATION ACKNOWLEDGEMENT).
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn acknowledge() -> i32 {
+    let _ = trace(">>> acknowledge called");
+    let tx = get_current_escrow_finish();
+    let escrow = get_current_escrow();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    let destination = match escrow.get_destination() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    let tx_seq = tx.get_sequence().unwrap_or(0);
+    let (new_data, new_len) =
+        match record_destination_ack(data, data_len, &caller.0, &destination.0, tx_seq, current_ledger) {
+            Ok(r) => r,
+            Err(code) => {
+                let _ = trace("!!! Acknowledgement refused");
+                return code;
+            }
+        };
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK destination acknowledged");
+    }
+    result
+}
+
+/// Called by a notary to vote for aborting the escrow (see ABORT VOTE).
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn vote_abort() -> i32 {
+    let _ = trace(">>> vote_abort called");
+    let tx = get_current_escrow_finish();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    let notary_index = match check_caller_is_notary(data, &caller.0, current_ledger) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+    let (new_data, new_len) = match record_abort_vote(data, data_len, notary_index, current_ledger) {
+        Ok(r) => r,
+        Err(code) => {
+            let _ = trace("!!! Abort vote refused");
+            return code;
+        }
+    };
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK abort vote recorded");
+    }
+    result
+}
+
+/// Called by a notary to name or withdraw its deputy (see DEPUTY DELEGATION).
+/// The first Memo's MemoData carries "deputy=<hex>;until=<ledger>" or "deputy=none".
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn set_deputy() -> i32 {
+    let _ = trace(">>> set_deputy called");
+    let tx = get_current_escrow_finish();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    // Only the notary itself may delegate — a deputy can't appoint another
+    let notary_index = match check_listed_notary(data, &caller.0) {
+        Ok(idx) => idx,
+        Err(code) => return code,
+    };
+
+    let mut memo_buf = [0u8; MAX_GOVERNANCE_MEMO_SIZE];
+    let memo_len = match read_memo_data(&mut memo_buf) {
+        Some(len) => len.min(memo_buf.len()),
+        None => return ERR_BAD_CONFIG,
+    };
+    let deputy = match parse_deputy_memo(&memo_buf[..memo_len]) {
+        Ok(d) => d,
+        Err(code) => return code,
+    };
+    let (new_data, new_len) = match record_deputy(data, data_len, notary_index, deputy, current_ledger) {
+        Ok(r) => r,
+        Err(code) => {
+            let _ = trace("!!! Deputy refused");
+            return code;
+        }
+    };
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK deputy recorded");
+    }
+    result
+}
+
+/// Called by the Owner to start a new approval round, or by a notary to
+/// vote for one (see APPROVAL ROUNDS).
+#[cfg(all(target_arch = "wasm32", not(feature = "smart-contract")))]
+#[unsafe(no_mangle)]
+pub extern "C" fn new_round() -> i32 {
+    let _ = trace(">>> new_round called");
+    let tx = get_current_escrow_finish();
+    let escrow = get_current_escrow();
+
+    let caller = match tx.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+    let owner = match escrow.get_account() {
+        xrpl_wasm_stdlib::host::Result::Ok(account) => account,
+        xrpl_wasm_stdlib::host::Result::Err(_) => return ERR_HOST_CALL,
+    };
+
+    let current_ledger = match current_ledger_sequence() {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let (loaded, data_len) = match load_escrow_data(current_ledger) {
+        Ok(l) => l,
+        Err(code) => return code,
+    };
+    let data = &loaded[..data_len];
+
+    let recorded = if caller.0 == owner.0 {
+        start_round(data, data_len, current_ledger)
+    } else {
+        let notary_index = match check_caller_is_notary(data, &caller.0, current_ledger) {
+            Ok(idx) => idx,
+            Err(code) => return code,
+        };
+        let threshold = match escrow_amount_drops().and_then(|amount| required_threshold(data, amount, current_ledger)) {
+            Ok(t) => t,
+            Err(code) => return code,
+        };
+        record_round_vote(data, data_len, notary_index, threshold, current_ledger)
+    };
+    let (new_data, new_len) = match recorded {
+        Ok(r) => r,
+        Err(code) => {
+            let _ = trace("!!! New round refused");
+            return code;
+        }
+    };
+    let result = write_contract_data(&new_data, new_len);
+    if result == SUCCESS {
+        let _ = trace("    OK round recorded");
+    }
+    result
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — Run with: cargo test -- --nocapture
+//
+// All contract logic is tested via pure functions that don't require
+// the WASM host. This tests the decision logic exhaustively.
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ─────────────────────────────────────────────────────────────
+    // TEST HELPERS — Build realistic contract data for testing
+    // ─────────────────────────────────────────────────────────────
+
+    /// Create a mock 20-byte AccountID from a simple seed value.
+    /// Each seed produces a unique, deterministic AccountID.
+    fn mock_account(seed: u8) -> [u8; ACCOUNT_ID_SIZE] {
+        let mut id = [0u8; ACCOUNT_ID_SIZE];
+        id[0] = seed;
+        id[19] = seed; // put seed at both ends for distinctness
+        id
+    }
+
+    /// Encode a mock account as hex string bytes.
+    fn mock_account_hex(seed: u8) -> [u8; 40] {
+        let account = mock_account(seed);
+        let mut hex = [0u8; 40];
+        encode_hex(&account, &mut hex).unwrap();
+        hex
+    }
+
+    /// Build contract data for a single-notary escrow (threshold=1).
+    fn single_notary_data(notary_seed: u8) -> (Vec<u8>, [u8; ACCOUNT_ID_SIZE]) {
+        let account = mock_account(notary_seed);
+        let hex = mock_account_hex(notary_seed);
+        let mut data = Vec::new();
+        data.extend_from_slice(b"notary_count=1;threshold=1;notary_0=");
+        data.extend_from_slice(&hex);
+        (data, account)
+    }
+
+    /// Build contract data for a 2-of-3 multi-notary escrow.
     fn multi_notary_data(seeds: [u8; 3]) -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3]) {
         let accounts = [mock_account(seeds[0]), mock_account(seeds[1]), mock_account(seeds[2])];
         let hex0 = mock_account_hex(seeds[0]);
         let hex1 = mock_account_hex(seeds[1]);
         let hex2 = mock_account_hex(seeds[2]);
 
-        let mut data = Vec::new();
-        data.extend_from_slice(b"notary_count=3;threshold=2");
-        data.extend_from_slice(b";notary_0=");
-        data.extend_from_slice(&hex0);
-        data.extend_from_slice(b";notary_1=");
-        data.extend_from_slice(&hex1);
-        data.extend_from_slice(b";notary_2=");
-        data.extend_from_slice(&hex2);
+        let mut data = Vec::new();
+        data.extend_from_slice(b"notary_count=3;threshold=2");
+        data.extend_from_slice(b";notary_0=");
+        data.extend_from_slice(&hex0);
+        data.extend_from_slice(b";notary_1=");
+        data.extend_from_slice(&hex1);
+        data.extend_from_slice(b";notary_2=");
+        data.extend_from_slice(&hex2);
+
+        (data, accounts)
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // find_value TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn find_value_single_entry() {
+        // A data string with just one key=value pair
+        assert_eq!(find_value(b"key=val", b"key"), Some(b"val" as &[u8]));
+    }
+
+    #[test]
+    fn find_value_multiple_entries() {
+        // Standard semicolon-delimited format
+        let data = b"a=1;b=2;c=3";
+        assert_eq!(find_value(data, b"a"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(data, b"b"), Some(b"2" as &[u8]));
+        assert_eq!(find_value(data, b"c"), Some(b"3" as &[u8]));
+    }
+
+    #[test]
+    fn find_value_missing_key() {
+        // Key that doesn't exist returns None
+        assert_eq!(find_value(b"a=1;b=2", b"c"), None);
+    }
+
+    #[test]
+    fn find_value_empty_data() {
+        // Empty data always returns None
+        assert_eq!(find_value(b"", b"key"), None);
+    }
+
+    #[test]
+    fn find_value_empty_value() {
+        // Key exists but value is empty
+        assert_eq!(find_value(b"key=", b"key"), Some(b"" as &[u8]));
+    }
+
+    #[test]
+    fn find_value_partial_key_match() {
+        // "notary" should not match "notary_count"
+        assert_eq!(find_value(b"notary_count=3;notary=bad", b"notary"), Some(b"bad" as &[u8]));
+        assert_eq!(find_value(b"notary_count=3", b"notary"), None);
+    }
+
+    #[test]
+    fn find_value_duplicate_keys_returns_first() {
+        // If duplicate keys exist, first one wins
+        assert_eq!(find_value(b"x=first;x=second", b"x"), Some(b"first" as &[u8]));
+    }
+
+    #[test]
+    fn find_value_value_with_special_chars() {
+        // Values can contain any bytes except semicolons
+        assert_eq!(find_value(b"k=abc123!@#", b"k"), Some(b"abc123!@#" as &[u8]));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // parse_u8_digit TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn parse_digit_valid() {
+        for i in 0..=9u8 {
+            assert_eq!(parse_u8_digit(&[b'0' + i]), Some(i));
+        }
+    }
+
+    #[test]
+    fn parse_digit_invalid() {
+        assert_eq!(parse_u8_digit(b""), None);        // empty
+        assert_eq!(parse_u8_digit(b"10"), None);       // two digits
+        assert_eq!(parse_u8_digit(b"a"), None);        // not a digit
+        assert_eq!(parse_u8_digit(b" "), None);        // space
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // parse_u32 TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn parse_u32_valid() {
+        assert_eq!(parse_u32(b"0"), Some(0));
+        assert_eq!(parse_u32(b"1"), Some(1));
+        assert_eq!(parse_u32(b"42"), Some(42));
+        assert_eq!(parse_u32(b"1000"), Some(1000));
+        assert_eq!(parse_u32(b"4294967295"), Some(u32::MAX));
+    }
+
+    #[test]
+    fn parse_u32_invalid() {
+        assert_eq!(parse_u32(b""), None);
+        assert_eq!(parse_u32(b"abc"), None);
+        assert_eq!(parse_u32(b"12x"), None);
+        assert_eq!(parse_u32(b"-1"), None);
+    }
+
+    #[test]
+    fn parse_u32_overflow() {
+        // One more than u32::MAX should overflow
+        assert_eq!(parse_u32(b"4294967296"), None);
+    }
+
+    #[test]
+    fn parse_u64_values() {
+        assert_eq!(parse_u64(b"0"), Some(0));
+        assert_eq!(parse_u64(b"100000000000"), Some(100_000_000_000));
+        assert_eq!(parse_u64(b"18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_u64(b"18446744073709551616"), None); // overflow
+        assert_eq!(parse_u64(b""), None);
+        assert_eq!(parse_u64(b"1e9"), None);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // HEX ENCODING/DECODING TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn hex_roundtrip() {
+        let original = [0xDE, 0xAD, 0xBE, 0xEF];
+        let mut hex = [0u8; 8];
+        let hex_len = encode_hex(&original, &mut hex).unwrap();
+        assert_eq!(&hex[..hex_len], b"deadbeef");
+
+        let mut decoded = [0u8; 4];
+        let dec_len = decode_hex(&hex[..hex_len], &mut decoded).unwrap();
+        assert_eq!(&decoded[..dec_len], &original);
+    }
+
+    #[test]
+    fn hex_decode_uppercase() {
+        let mut out = [0u8; 2];
+        assert_eq!(decode_hex(b"FF", &mut out), Some(1));  // "FF" = 1 byte
+        assert_eq!(out[0], 0xFF);
+    }
+
+    #[test]
+    fn hex_decode_invalid() {
+        let mut out = [0u8; 4];
+        assert_eq!(decode_hex(b"xyz", &mut out), None);    // odd length
+        assert_eq!(decode_hex(b"gg", &mut out), None);     // invalid chars
+    }
+
+    #[test]
+    fn hex_encode_empty() {
+        let mut out = [0u8; 0];
+        assert_eq!(encode_hex(&[], &mut out), Some(0));
+    }
+
+    #[test]
+    fn hex_encode_buffer_too_small() {
+        let mut out = [0u8; 2]; // need 4 for 2 bytes
+        assert_eq!(encode_hex(&[0xAB, 0xCD], &mut out), None);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // format_u32 TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn format_u32_values() {
+        let mut buf = [0u8; 10];
+
+        let len = format_u32(0, &mut buf);
+        assert_eq!(&buf[..len], b"0");
+
+        let len = format_u32(1, &mut buf);
+        assert_eq!(&buf[..len], b"1");
+
+        let len = format_u32(42, &mut buf);
+        assert_eq!(&buf[..len], b"42");
+
+        let len = format_u32(1000, &mut buf);
+        assert_eq!(&buf[..len], b"1000");
+
+        let len = format_u32(4294967295, &mut buf);
+        assert_eq!(&buf[..len], b"4294967295");
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // build_indexed_key TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn indexed_key_builds_correctly() {
+        let mut buf = [0u8; 16];
+        let len = build_indexed_key(b"notary_", 0, &mut buf);
+        assert_eq!(&buf[..len], b"notary_0");
+
+        let len = build_indexed_key(b"approval_", 3, &mut buf);
+        assert_eq!(&buf[..len], b"approval_3");
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // CALLER AUTHORIZATION TESTS (Security Fix #1, #2, #3)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn single_notary_authorized() {
+        // Authorized notary should be recognized
+        let (data, account) = single_notary_data(0x01);
+        assert_eq!(check_caller_is_notary(&data, &account, 0), Ok(0));
+    }
+
+    #[test]
+    fn single_notary_unauthorized() {
+        // Random account should be rejected
+        let (data, _) = single_notary_data(0x01);
+        let impostor = mock_account(0xFF);
+        assert_eq!(check_caller_is_notary(&data, &impostor, 0), Err(ERR_WRONG_ACCOUNT));
+    }
+
+    #[test]
+    fn multi_notary_all_recognized() {
+        // All three notaries should be recognized with correct indices
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(check_caller_is_notary(&data, &accounts[0], 0), Ok(0));
+        assert_eq!(check_caller_is_notary(&data, &accounts[1], 0), Ok(1));
+        assert_eq!(check_caller_is_notary(&data, &accounts[2], 0), Ok(2));
+    }
+
+    #[test]
+    fn multi_notary_impostor_rejected() {
+        // Account not in the notary list should be rejected
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let impostor = mock_account(0x99);
+        assert_eq!(check_caller_is_notary(&data, &impostor, 0), Err(ERR_WRONG_ACCOUNT));
+    }
+
+    #[test]
+    fn notary_check_no_config() {
+        // Missing notary_count in data should return BAD_CONFIG
+        let data = b"threshold=1";
+        let account = mock_account(0x01);
+        assert_eq!(check_caller_is_notary(data, &account, 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn notary_check_zero_count() {
+        // Zero notaries is invalid config
+        let data = b"notary_count=0;threshold=1";
+        let account = mock_account(0x01);
+        assert_eq!(check_caller_is_notary(data, &account, 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn notary_check_count_exceeds_max() {
+        // More than MAX_NOTARIES is invalid
+        let data = b"notary_count=9;threshold=1";
+        let account = mock_account(0x01);
+        assert_eq!(check_caller_is_notary(data, &account, 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn notary_check_similar_accounts() {
+        // Two accounts that differ by one byte should not cross-match
+        let (data, account) = single_notary_data(0x01);
+        let mut similar = account;
+        similar[10] = 0xFF; // change one byte in the middle
+        assert_eq!(check_caller_is_notary(&data, &similar, 0), Err(ERR_WRONG_ACCOUNT));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // FINISH SUBMITTER POLICY TESTS (finish_by)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn finish_by_parses_role_lists() {
+        assert_eq!(parse_finish_by(b"notary"), Some(FINISH_BY_NOTARY));
+        assert_eq!(parse_finish_by(b"any"), Some(FINISH_BY_ANY));
+        assert_eq!(
+            parse_finish_by(b"notary,destination,owner"),
+            Some(FINISH_BY_NOTARY | FINISH_BY_DESTINATION | FINISH_BY_OWNER)
+        );
+        assert_eq!(parse_finish_by(b""), None);
+        assert_eq!(parse_finish_by(b"notary,"), None);     // trailing empty role
+        assert_eq!(parse_finish_by(b"Notary"), None);      // case-sensitive
+        assert_eq!(parse_finish_by(b"beneficiary"), None);
+    }
+
+    #[test]
+    fn finish_by_defaults_to_notary() {
+        // No finish_by key — original behaviour, notaries only
+        let (data, account) = single_notary_data(0x01);
+        let owner = mock_account(0xA0);
+        let destination = mock_account(0xB0);
+        assert_eq!(check_finish_submitter(&data, &account, &owner, &destination), Ok(()));
+        assert_eq!(
+            check_finish_submitter(&data, &destination, &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+    }
+
+    #[test]
+    fn finish_by_destination_only() {
+        let (mut data, notary) = single_notary_data(0x01);
+        data.extend_from_slice(b";finish_by=destination");
+        let owner = mock_account(0xA0);
+        let destination = mock_account(0xB0);
+
+        assert_eq!(check_finish_submitter(&data, &destination, &owner, &destination), Ok(()));
+        // Notaries are no longer allowed unless listed
+        assert_eq!(
+            check_finish_submitter(&data, &notary, &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+        assert_eq!(
+            check_finish_submitter(&data, &owner, &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+    }
+
+    #[test]
+    fn finish_by_notary_or_destination() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";finish_by=notary,destination");
+        let owner = mock_account(0xA0);
+        let destination = mock_account(0xB0);
+
+        assert_eq!(check_finish_submitter(&data, &accounts[1], &owner, &destination), Ok(()));
+        assert_eq!(check_finish_submitter(&data, &destination, &owner, &destination), Ok(()));
+        assert_eq!(
+            check_finish_submitter(&data, &mock_account(0x99), &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+    }
+
+    #[test]
+    fn finish_by_owner() {
+        let (mut data, _) = single_notary_data(0x01);
+        data.extend_from_slice(b";finish_by=owner");
+        let owner = mock_account(0xA0);
+        let destination = mock_account(0xB0);
+        assert_eq!(check_finish_submitter(&data, &owner, &owner, &destination), Ok(()));
+        assert_eq!(
+            check_finish_submitter(&data, &destination, &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+    }
+
+    #[test]
+    fn finish_by_any_still_requires_quorum() {
+        // Anyone may submit, but the threshold check is independent
+        let (mut data, _) = single_notary_data(0x01);
+        data.extend_from_slice(b";finish_by=any");
+        let stranger = mock_account(0x99);
+        assert_eq!(
+            check_finish_submitter(&data, &stranger, &mock_account(0xA0), &mock_account(0xB0)),
+            Ok(())
+        );
+        assert_eq!(check_approval_threshold(&data), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn finish_by_invalid_policy_is_bad_config() {
+        let (mut data, account) = single_notary_data(0x01);
+        data.extend_from_slice(b";finish_by=everyone");
+        assert_eq!(
+            check_finish_submitter(&data, &account, &mock_account(0xA0), &mock_account(0xB0)),
+            Err(ERR_BAD_CONFIG)
+        );
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // APPROVAL THRESHOLD TESTS (Security Fix #2)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn threshold_met_exactly() {
+        // 2 approvals with threshold=2 should pass
+        let data = b"threshold=2;approval_count=2";
+        assert_eq!(check_approval_threshold(data), SUCCESS);
+    }
+
+    #[test]
+    fn threshold_exceeded() {
+        // 3 approvals with threshold=2 should still pass
+        let data = b"threshold=2;approval_count=3";
+        assert_eq!(check_approval_threshold(data), SUCCESS);
+    }
+
+    #[test]
+    fn threshold_not_met() {
+        // 1 approval with threshold=2 should fail
+        let data = b"threshold=2;approval_count=1";
+        assert_eq!(check_approval_threshold(data), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn threshold_zero_approvals() {
+        // No approvals at all
+        let data = b"threshold=2";
+        assert_eq!(check_approval_threshold(data), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn threshold_of_one() {
+        // Single approval needed and met
+        let data = b"threshold=1;approval_count=1";
+        assert_eq!(check_approval_threshold(data), SUCCESS);
+    }
+
+    #[test]
+    fn threshold_missing_config() {
+        // No threshold in data = bad config
+        let data = b"approval_count=5";
+        assert_eq!(check_approval_threshold(data), ERR_BAD_CONFIG);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // AMOUNT-TIERED THRESHOLD TESTS (tier_N)
+    // ═════════════════════════════════════════════════════════════
+
+    /// 1 approval under 10k XRP, 2 under 100k XRP, 3 at or above 100k XRP.
+    const TIERS: &[u8] = b";tier_0=0:1;tier_1=10000000000:2;tier_2=100000000000:3";
+
+    fn tiered_data(approvals: u8) -> Vec<u8> {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(TIERS);
+        data.extend_from_slice(b";approval_count=");
+        data.push(b'0' + approvals);
+        data
+    }
+
+    #[test]
+    fn tier_parses_entry() {
+        assert_eq!(parse_tier(b"10000000000:2"), Some((10_000_000_000, 2)));
+        assert_eq!(parse_tier(b"0:1"), Some((0, 1)));
+        assert_eq!(parse_tier(b"100"), None);      // no colon
+        assert_eq!(parse_tier(b"100:12"), None);   // threshold must be a digit
+        assert_eq!(parse_tier(b":1"), None);       // missing amount
+    }
+
+    #[test]
+    fn tier_selects_threshold_by_amount() {
+        let data = tiered_data(0);
+        assert_eq!(required_threshold(&data, Some(0), 0), Ok(1));
+        assert_eq!(required_threshold(&data, Some(9_999_999_999), 0), Ok(1));
+        assert_eq!(required_threshold(&data, Some(10_000_000_000), 0), Ok(2));
+        assert_eq!(required_threshold(&data, Some(99_999_999_999), 0), Ok(2));
+        assert_eq!(required_threshold(&data, Some(100_000_000_000), 0), Ok(3));
+        assert_eq!(required_threshold(&data, Some(u64::MAX), 0), Ok(3));
+    }
+
+    #[test]
+    fn tier_threshold_applied_at_finish() {
+        // 50k XRP needs 2 approvals
+        let amount = Some(50_000_000_000);
+        assert_eq!(check_tiered_threshold(&tiered_data(1), amount, 0), ERR_NOT_APPROVED);
+        assert_eq!(check_tiered_threshold(&tiered_data(2), amount, 0), SUCCESS);
+        // 500 XRP needs only 1
+        assert_eq!(check_tiered_threshold(&tiered_data(1), Some(500_000_000), 0), SUCCESS);
+    }
+
+    #[test]
+    fn tier_absent_falls_back_to_threshold_key() {
+        let data = b"notary_count=3;threshold=2;approval_count=2";
+        assert_eq!(required_threshold(data, Some(1), 0), Ok(2));
+        assert_eq!(required_threshold(data, None, 0), Ok(2));
+        assert_eq!(check_tiered_threshold(data, None, 0), SUCCESS);
+    }
+
+    #[test]
+    fn tier_rejects_token_amounts() {
+        // Tiers are in drops; a non-XRP escrow amount can't be tiered
+        assert_eq!(required_threshold(&tiered_data(3), None, 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_counts_tiers() {
+        assert_eq!(validate_tiers(&tiered_data(0)), Ok(3));
+        assert_eq!(validate_tiers(b"notary_count=1;threshold=1"), Ok(0));
+    }
+
+    #[test]
+    fn tier_validation_requires_zero_start() {
+        let data = b"notary_count=3;tier_0=100:1;tier_1=200:2";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_rejects_non_increasing_amounts() {
+        let data = b"notary_count=3;tier_0=0:1;tier_1=500:2;tier_2=500:3";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_rejects_decreasing_thresholds() {
+        let data = b"notary_count=3;tier_0=0:2;tier_1=500:1";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_rejects_zero_threshold() {
+        let data = b"notary_count=3;tier_0=0:0";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_rejects_unsatisfiable_top_tier() {
+        // Highest tier needs 4 approvals but only 3 notaries exist
+        let data = b"notary_count=3;tier_0=0:1;tier_1=500:4";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_tiered_threshold(data, Some(1), 0), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn tier_validation_rejects_malformed_and_excess_tiers() {
+        assert_eq!(validate_tiers(b"notary_count=3;tier_0=zero:1"), Err(ERR_BAD_CONFIG));
+        let data = b"notary_count=5;tier_0=0:1;tier_1=1:1;tier_2=2:1;tier_3=3:1;tier_4=4:1;tier_5=5:1";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tier_validation_rejects_numbering_gap() {
+        // tier_2 without tier_1 would never be read
+        let data = b"notary_count=3;tier_0=0:1;tier_2=2:2";
+        assert_eq!(validate_tiers(data), Err(ERR_BAD_CONFIG));
+        assert_eq!(required_threshold(data, Some(5), 0), Err(ERR_BAD_CONFIG));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // TIME-LOCK TESTS (Security Fix #4)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn time_lock_with_finish_after() {
+        // FinishAfter is set — protocol enforced it, so we pass
+        assert_eq!(check_time_lock(Some(781364800)), SUCCESS);
+    }
+
+    #[test]
+    fn time_lock_without_finish_after() {
+        // No FinishAfter — no time-lock, still passes
+        assert_eq!(check_time_lock(None), SUCCESS);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // APPROVAL RECORDING TESTS (Security Fix #5, #7)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn record_first_approval() {
+        // First notary approves — approval_count goes from 0 to 1
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (new_data, new_len) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let new_slice = &new_data[..new_len];
+
+        // Verify approval_0=1 is present
+        assert_eq!(find_value(new_slice, b"approval_0"), Some(b"1" as &[u8]));
+        // Verify count incremented
+        assert_eq!(find_value(new_slice, b"approval_count"), Some(b"1" as &[u8]));
+        // Verify notary config is preserved
+        assert_eq!(find_value(new_slice, b"notary_count"), Some(b"3" as &[u8]));
+        assert_eq!(find_value(new_slice, b"threshold"), Some(b"2" as &[u8]));
+    }
+
+    #[test]
+    fn record_second_approval_different_notary() {
+        // Second notary approves after first — count goes to 2
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        // First approval
+        let (data1, len1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        // Second approval (different notary)
+        let (data2, len2) = record_approval(&data1[..len1], len1, 1, &accounts[1], 101, 1000, 2).unwrap();
+        let result = &data2[..len2];
+
+        assert_eq!(find_value(result, b"approval_0"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(result, b"approval_1"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(result, b"approval_count"), Some(b"2" as &[u8]));
+    }
+
+    #[test]
+    fn record_duplicate_approval_rejected() {
+        // Same notary trying to approve twice should fail
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (data1, len1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let result = record_approval(&data1[..len1], len1, 0, &accounts[0], 101, 1000, 2);
+
+        assert_eq!(result, Err(ERR_ALREADY_APPROVED));
+    }
+
+    #[test]
+    fn record_all_three_approvals() {
+        // All three notaries approve — threshold easily met
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 1000, 2).unwrap();
+        let (d3, l3) = record_approval(&d2[..l2], l2, 2, &accounts[2], 102, 1000, 2).unwrap();
+
+        let result = &d3[..l3];
+        assert_eq!(find_value(result, b"approval_count"), Some(b"3" as &[u8]));
+        assert_eq!(check_approval_threshold(result), SUCCESS);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // REVOCATION TESTS
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn revoke_existing_approval() {
+        // Approve then revoke — count should go back to 0
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"approval_count"), Some(b"1" as &[u8]));
+
+        let (d2, l2) = record_revocation(&d1[..l1], l1, 0, 1000, 2).unwrap();
+        assert_eq!(find_value(&d2[..l2], b"approval_0"), Some(b"0" as &[u8]));
+        assert_eq!(find_value(&d2[..l2], b"approval_count"), Some(b"0" as &[u8]));
+    }
+
+    #[test]
+    fn revoke_then_reapprove() {
+        // Approve → revoke → approve again should work
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let (d2, l2) = record_revocation(&d1[..l1], l1, 0, 1000, 2).unwrap();
+        // Should be able to approve again after revoking
+        let (d3, l3) = record_approval(&d2[..l2], l2, 0, &accounts[0], 102, 1000, 2).unwrap();
+        assert_eq!(find_value(&d3[..l3], b"approval_0"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(&d3[..l3], b"approval_count"), Some(b"1" as &[u8]));
+    }
+
+    #[test]
+    fn revoke_unapproved_notary() {
+        // Revoking when you haven't approved yet — count stays at 0
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_revocation(&data, data.len(), 0, 1000, 2).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"approval_0"), Some(b"0" as &[u8]));
+        assert_eq!(find_value(&d1[..l1], b"approval_count"), Some(b"0" as &[u8]));
+    }
+
+    #[test]
+    fn partial_revoke_preserves_others() {
+        // Two notaries approve, one revokes — other approval preserved
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 1000, 2).unwrap();
+        assert_eq!(find_value(&d2[..l2], b"approval_count"), Some(b"2" as &[u8]));
+
+        // Notary 0 revokes
+        let (d3, l3) = record_revocation(&d2[..l2], l2, 0, 1000, 2).unwrap();
+        assert_eq!(find_value(&d3[..l3], b"approval_0"), Some(b"0" as &[u8]));
+        assert_eq!(find_value(&d3[..l3], b"approval_1"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(&d3[..l3], b"approval_count"), Some(b"1" as &[u8]));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // TIME-DECAYING THRESHOLD TESTS (decay_N)
+    // ═════════════════════════════════════════════════════════════
+
+    /// 3-of-3 until ledger 1000, 2-of-3 until 2000, 1-of-3 after.
+    fn decay_data(approvals: u8) -> Vec<u8> {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";decay_0=0:3;decay_1=1000:2;decay_2=2000:1;approval_count=");
+        data.push(b'0' + approvals);
+        data
+    }
+
+    #[test]
+    fn decay_step_boundaries() {
+        let data = decay_data(0);
+        assert_eq!(required_threshold(&data, None, 0), Ok(3));
+        assert_eq!(required_threshold(&data, None, 999), Ok(3));
+        assert_eq!(required_threshold(&data, None, 1000), Ok(2));
+        assert_eq!(required_threshold(&data, None, 1999), Ok(2));
+        assert_eq!(required_threshold(&data, None, 2000), Ok(1));
+        assert_eq!(required_threshold(&data, None, u32::MAX), Ok(1));
+    }
+
+    #[test]
+    fn decay_overrides_plain_threshold() {
+        // threshold=2 from multi_notary_data is ignored while a schedule exists
+        let data = decay_data(2);
+        assert_eq!(check_tiered_threshold(&data, Some(1), 999), ERR_NOT_APPROVED);
+        assert_eq!(check_tiered_threshold(&data, Some(1), 1000), SUCCESS);
+    }
+
+    #[test]
+    fn decay_releases_on_existing_approvals() {
+        let data = decay_data(1);
+        assert_eq!(check_tiered_threshold(&data, None, 1999), ERR_NOT_APPROVED);
+        assert_eq!(check_tiered_threshold(&data, None, 2000), SUCCESS);
+    }
+
+    #[test]
+    fn decay_single_step_is_constant() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";decay_0=0:3");
+        assert_eq!(required_threshold(&data, None, 0), Ok(3));
+        assert_eq!(required_threshold(&data, None, u32::MAX), Ok(3));
+    }
+
+    #[test]
+    fn decay_schedule_validation() {
+        let cases: [&[u8]; 9] = [
+            b";decay_0=10:3",                               // must start at ledger 0
+            b";decay_0=0:3;decay_1=1000:3;decay_2=1000:1",  // ledgers not strictly increasing
+            b";decay_0=0:3;decay_1=2000:2;decay_2=1000:1",  // ledgers out of order
+            b";decay_0=0:2;decay_1=1000:3",                 // threshold may not tighten
+            b";decay_0=0:3;decay_1=1000:0",                 // threshold of zero
+            b";decay_0=0:4",                                // more than notary_count
+            b";decay_0=0:x",                                // unparsable threshold
+            b";decay_0=0;decay_1=1000:2",                   // missing threshold
+            b";decay_0=0:3;tier_0=0:1",                     // tiers and decay are exclusive
+        ];
+        for case in cases {
+            let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+            data.extend_from_slice(case);
+            assert_eq!(
+                required_threshold(&data, Some(1), 5000),
+                Err(ERR_BAD_CONFIG),
+                "{}",
+                core::str::from_utf8(case).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn decay_schedule_too_long() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(
+            b";decay_0=0:3;decay_1=1:3;decay_2=2:2;decay_3=3:2;decay_4=4:1;decay_5=5:1",
+        );
+        assert_eq!(validate_decay_schedule(&data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn decay_gap_is_rejected() {
+        // decay_2 without decay_1 would never be reached
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";decay_0=0:3;decay_2=1000:1");
+        assert_eq!(validate_decay_schedule(&data), Err(ERR_BAD_CONFIG));
+        assert_eq!(required_threshold(&data, None, 5000), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn backup_threshold_beats_decay() {
+        let mut data = decay_data(0);
+        data.extend_from_slice(b";notary_set=backup;backup_threshold=2");
+        assert_eq!(required_threshold(&data, None, 0), Ok(2));
+        assert_eq!(required_threshold(&data, None, 5000), Ok(2));
+    }
+
+    #[test]
+    fn decayed_quorum_starts_challenge_at_step() {
+        // Quorum reached by decay, not by an approval: no quorum_ledger recorded
+        let mut data = decay_data(2);
+        data.extend_from_slice(b";challenge_ledgers=100");
+        assert_eq!(check_challenge_period(&data, 1000), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(&data, 1099), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(&data, 1100), SUCCESS);
+    }
+
+    #[test]
+    fn recorded_quorum_ledger_beats_decay_step() {
+        let mut data = decay_data(2);
+        data.extend_from_slice(b";challenge_ledgers=100;quorum_ledger=1500");
+        assert_eq!(check_challenge_period(&data, 1100), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(&data, 1600), SUCCESS);
+    }
+
+    #[test]
+    fn first_decay_step_does_not_prove_quorum_start() {
+        // Preset approvals meeting decay_0 still need a recorded quorum_ledger
+        let mut data = decay_data(3);
+        data.extend_from_slice(b";challenge_ledgers=100");
+        assert_eq!(check_challenge_period(&data, 500), ERR_TOO_EARLY);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // CHALLENGE PERIOD TESTS (quorum_ledger / challenge_ledgers)
+    // ═════════════════════════════════════════════════════════════
+
+    /// 2-of-3 escrow with a 100-ledger challenge period.
+    fn challenge_data() -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3]) {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";challenge_ledgers=100");
+        (data, accounts)
+    }
+
+    #[test]
+    fn quorum_ledger_recorded_when_threshold_reached() {
+        let (data, accounts) = challenge_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 5000, 2).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"quorum_ledger"), None); // 1 of 2
+
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 5010, 2).unwrap();
+        assert_eq!(find_value(&d2[..l2], b"quorum_ledger"), Some(b"5010" as &[u8]));
+    }
+
+    #[test]
+    fn quorum_ledger_not_moved_by_extra_approvals() {
+        let (data, accounts) = challenge_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 5000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 5010, 2).unwrap();
+        let (d3, l3) = record_approval(&d2[..l2], l2, 2, &accounts[2], 102, 5090, 2).unwrap();
+        assert_eq!(find_value(&d3[..l3], b"quorum_ledger"), Some(b"5010" as &[u8]));
+    }
+
+    #[test]
+    fn quorum_ledger_cleared_when_quorum_lost() {
+        let (data, accounts) = challenge_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 5000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 5010, 2).unwrap();
+        let (d3, l3) = record_revocation(&d2[..l2], l2, 0, 1000, 2).unwrap();
+        assert_eq!(find_value(&d3[..l3], b"quorum_ledger"), None);
+
+        // Re-reaching quorum restarts the window from the new ledger
+        let (d4, l4) = record_approval(&d3[..l3], l3, 2, &accounts[2], 102, 5200, 2).unwrap();
+        assert_eq!(find_value(&d4[..l4], b"quorum_ledger"), Some(b"5200" as &[u8]));
+    }
+
+    #[test]
+    fn quorum_ledger_kept_when_quorum_survives_revocation() {
+        let (data, accounts) = challenge_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 5000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 5010, 2).unwrap();
+        let (d3, l3) = record_approval(&d2[..l2], l2, 2, &accounts[2], 102, 5020, 2).unwrap();
+        let (d4, l4) = record_revocation(&d3[..l3], l3, 0, 1000, 2).unwrap();
+        assert_eq!(find_value(&d4[..l4], b"quorum_ledger"), Some(b"5010" as &[u8]));
+    }
+
+    #[test]
+    fn challenge_period_blocks_until_elapsed() {
+        let data = b"threshold=2;approval_count=2;challenge_ledgers=100;quorum_ledger=5010";
+        assert_eq!(check_challenge_period(data, 5010), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(data, 5109), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(data, 5110), SUCCESS);
+        assert_eq!(check_challenge_period(data, 9000), SUCCESS);
+    }
+
+    #[test]
+    fn challenge_period_absent_is_no_wait() {
+        let data = b"threshold=2;approval_count=2";
+        assert_eq!(check_challenge_period(data, 0), SUCCESS);
+    }
+
+    #[test]
+    fn challenge_period_without_quorum_record_denies() {
+        // Approvals preset at creation never recorded when quorum started
+        let data = b"threshold=1;approval_count=1;challenge_ledgers=10";
+        assert_eq!(check_challenge_period(data, 1_000_000), ERR_TOO_EARLY);
+    }
+
+    #[test]
+    fn challenge_period_invalid_config() {
+        let data = b"challenge_ledgers=soon;quorum_ledger=1";
+        assert_eq!(check_challenge_period(data, 100), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn challenge_period_window_saturates() {
+        // A huge window can't overflow into an early release
+        let data = b"challenge_ledgers=4294967295;quorum_ledger=10";
+        assert_eq!(check_challenge_period(data, u32::MAX - 1), ERR_TOO_EARLY);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // AUDIT TRAIL TESTS (Security Fix #5)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn audit_records_denial() {
+        let data = b"threshold=2;approval_count=0";
+        let (audit, len) = record_audit(data, data.len(), ERR_NOT_APPROVED, 42);
+        let result = &audit[..len];
+
+        assert_eq!(find_value(result, b"last_result"), Some(b"not_approved" as &[u8]));
+        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"42" as &[u8]));
+        // Original data preserved
+        assert_eq!(find_value(result, b"threshold"), Some(b"2" as &[u8]));
+    }
+
+    #[test]
+    fn audit_records_success() {
+        let data = b"threshold=1;approval_count=1";
+        let (audit, len) = record_audit(data, data.len(), SUCCESS, 999);
+        let result = &audit[..len];
+
+        assert_eq!(find_value(result, b"last_result"), Some(b"approved" as &[u8]));
+        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"999" as &[u8]));
+    }
+
+    #[test]
+    fn audit_records_wrong_account() {
+        let data = b"threshold=2";
+        let (audit, len) = record_audit(data, data.len(), ERR_WRONG_ACCOUNT, 1);
+        let result = &audit[..len];
+        assert_eq!(find_value(result, b"last_result"), Some(b"wrong_account" as &[u8]));
+    }
+
+    #[test]
+    fn audit_overwrites_previous_audit() {
+        // First attempt denied
+        let data = b"threshold=2;approval_count=0";
+        let (d1, l1) = record_audit(data, data.len(), ERR_NOT_APPROVED, 10);
+
+        // Second attempt also denied — should overwrite first audit
+        let (d2, l2) = record_audit(&d1[..l1], l1, ERR_WRONG_ACCOUNT, 20);
+        let result = &d2[..l2];
+
+        assert_eq!(find_value(result, b"last_result"), Some(b"wrong_account" as &[u8]));
+        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"20" as &[u8]));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // LIFECYCLE STATE MACHINE TESTS (state)
+    // ═════════════════════════════════════════════════════════════
+
+    const NONE: Tally = Tally { approvals: 0, quorum: false };
+    const SOME: Tally = Tally { approvals: 1, quorum: false };
+    const QUORUM: Tally = Tally { approvals: 2, quorum: true };
+
+    const STATES: [EscrowState; 6] = [
+        EscrowState::Configured,
+        EscrowState::Collecting,
+        EscrowState::Quorum,
+        EscrowState::Released,
+        EscrowState::Frozen,
+        EscrowState::Aborted,
+    ];
+
+    const EVENTS: [LifecycleEvent; 16] = [
+        LifecycleEvent::Approve(SOME),
+        LifecycleEvent::Approve(QUORUM),
+        LifecycleEvent::Revoke(NONE),
+        LifecycleEvent::Revoke(SOME),
+        LifecycleEvent::Revoke(QUORUM),
+        LifecycleEvent::NotarySetSwitched,
+        LifecycleEvent::RoundReset,
+        LifecycleEvent::Release,
+        LifecycleEvent::Freeze,
+        LifecycleEvent::Unfreeze(NONE),
+        LifecycleEvent::Unfreeze(SOME),
+        LifecycleEvent::Unfreeze(QUORUM),
+        LifecycleEvent::Abort,
+        LifecycleEvent::Reconfigured(NONE),
+        LifecycleEvent::Reconfigured(QUORUM),
+        LifecycleEvent::Annotate,
+    ];
+
+    #[test]
+    fn transition_table_is_exhaustive() {
+        use EscrowState::*;
+        const X: Option<EscrowState> = None; // illegal
+
+        // Rows follow STATES, columns follow EVENTS
+        let expected: [[Option<EscrowState>; 16]; 6] = [
+            // Approve(some), Approve(quorum), Revoke(none), Revoke(some), Revoke(quorum),
+            // Switched, RoundReset, Release, Freeze, Unfreeze(none), Unfreeze(some), Unfreeze(quorum), Abort,
+            // Reconfigured(none), Reconfigured(quorum), Annotate
+            /* Configured */ [Some(Collecting), Some(Quorum), Some(Configured), Some(Collecting), Some(Quorum),
+                              Some(Configured), Some(Configured), Some(Released), Some(Frozen), X, X, X, Some(Aborted),
+                              Some(Configured), Some(Quorum), Some(Configured)],
+            /* Collecting */ [Some(Collecting), Some(Quorum), Some(Configured), Some(Collecting), Some(Quorum),
+                              Some(Configured), Some(Configured), Some(Released), Some(Frozen), X, X, X, Some(Aborted),
+                              Some(Configured), Some(Quorum), Some(Collecting)],
+            /* Quorum     */ [Some(Collecting), Some(Quorum), Some(Configured), Some(Collecting), Some(Quorum),
+                              Some(Configured), Some(Configured), Some(Released), Some(Frozen), X, X, X, Some(Aborted),
+                              Some(Configured), Some(Quorum), Some(Quorum)],
+            /* Released   */ [X, X, X, X, X, X, X, X, X, X, X, X, X, X, X, X],
+            /* Frozen     */ [X, X, X, X, X, Some(Frozen), Some(Frozen), X, X,
+                              Some(Configured), Some(Collecting), Some(Quorum), Some(Aborted),
+                              Some(Frozen), Some(Frozen), Some(Frozen)],
+            /* Aborted    */ [X, X, X, X, X, X, X, X, X, X, X, X, X, X, X, X],
+        ];
+
+        for (row, state) in STATES.iter().enumerate() {
+            for (col, event) in EVENTS.iter().enumerate() {
+                let want = match expected[row][col] {
+                    Some(next) => Ok(next),
+                    None if *state == Aborted => Err(ERR_ABORTED),
+                    None => Err(ERR_ILLEGAL_TRANSITION),
+                };
+                assert_eq!(transition(*state, *event), want, "{:?} + {:?}", state, event);
+            }
+        }
+    }
+
+    #[test]
+    fn state_names_round_trip() {
+        for state in STATES {
+            assert_eq!(EscrowState::parse(state.as_bytes()), Some(state));
+        }
+        assert_eq!(EscrowState::parse(b"Quorum"), None);
+    }
+
+    #[test]
+    fn missing_state_is_configured() {
+        assert_eq!(recorded_state(b"threshold=1"), Ok(EscrowState::Configured));
+        assert_eq!(recorded_state(b"state=paused"), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn tally_against_threshold() {
+        assert_eq!(Tally::of(b"approval_count=0", 2).state(), EscrowState::Configured);
+        assert_eq!(Tally::of(b"threshold=2", 2).state(), EscrowState::Configured);
+        assert_eq!(Tally::of(b"approval_count=1", 2).state(), EscrowState::Collecting);
+        assert_eq!(Tally::of(b"approval_count=2", 2).state(), EscrowState::Quorum);
+        assert_eq!(Tally::of(b"approval_count=3", 2).state(), EscrowState::Quorum);
+    }
+
+    #[test]
+    fn record_transition_persists_state() {
+        let data = b"threshold=1;approval_count=1;state=quorum";
+        let (d1, l1) = record_transition(data, data.len(), LifecycleEvent::Release).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"state"), Some(b"released" as &[u8]));
+        assert_eq!(find_value(&d1[..l1], b"threshold"), Some(b"1" as &[u8]));
+
+        // Released is terminal
+        assert_eq!(
+            record_transition(&d1[..l1], l1, LifecycleEvent::Approve(QUORUM)).err(),
+            Some(ERR_ILLEGAL_TRANSITION)
+        );
+    }
+
+    #[test]
+    fn approval_flow_walks_states() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 10, 2).unwrap();
+        let tally = Tally::of(&d1[..l1], 2);
+        let (d1, l1) = record_transition(&d1[..l1], l1, LifecycleEvent::Approve(tally)).unwrap();
+        assert_eq!(recorded_state(&d1[..l1]), Ok(EscrowState::Collecting));
+
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 11, 2).unwrap();
+        let tally = Tally::of(&d2[..l2], 2);
+        let (d2, l2) = record_transition(&d2[..l2], l2, LifecycleEvent::Approve(tally)).unwrap();
+        assert_eq!(recorded_state(&d2[..l2]), Ok(EscrowState::Quorum));
+
+        let (d3, l3) = record_revocation(&d2[..l2], l2, 1, 12, 2).unwrap();
+        let tally = Tally::of(&d3[..l3], 2);
+        let (d3, l3) = record_transition(&d3[..l3], l3, LifecycleEvent::Revoke(tally)).unwrap();
+        assert_eq!(recorded_state(&d3[..l3]), Ok(EscrowState::Collecting));
+    }
+
+    #[test]
+    fn frozen_escrow_rejects_approval_and_release() {
+        let data = b"threshold=1;approval_count=0;state=frozen";
+        let tally = Tally::of(b"approval_count=1", 1);
+        assert_eq!(
+            record_transition(data, data.len(), LifecycleEvent::Approve(tally)).err(),
+            Some(ERR_ILLEGAL_TRANSITION)
+        );
+        assert_eq!(
+            record_transition(data, data.len(), LifecycleEvent::Release).err(),
+            Some(ERR_ILLEGAL_TRANSITION)
+        );
+    }
+
+    #[test]
+    fn dead_man_switch_resets_state() {
+        let (data, _, _) = dead_man_data();
+        let mut quorum = data.clone();
+        quorum.extend_from_slice(b";state=quorum");
+        let (d1, l1) = apply_inactivity_switch(&quorum, quorum.len(), 6000).unwrap().unwrap();
+        assert_eq!(recorded_state(&d1[..l1]), Ok(EscrowState::Configured));
+
+        let mut frozen = data.clone();
+        frozen.extend_from_slice(b";state=frozen");
+        let (d2, l2) = apply_inactivity_switch(&frozen, frozen.len(), 6000).unwrap().unwrap();
+        assert_eq!(recorded_state(&d2[..l2]), Ok(EscrowState::Frozen));
+
+        let mut aborted = data;
+        aborted.extend_from_slice(b";state=aborted");
+        assert_eq!(
+            apply_inactivity_switch(&aborted, aborted.len(), 6000),
+            Err(ERR_ABORTED)
+        );
+    }
+
+    #[test]
+    fn settled_escrow_refuses_side_records() {
+        let dest = mock_account(0x60);
+        let mut base = governed_data();
+        base.extend_from_slice(b";require_consent=owner;require_destination_ack=1");
+        let (open, open_len) = record_proposal(&base, base.len(), &proposal(1, b"threshold", b"1"), 0, 1000).unwrap();
+        let (sealed, _, phases) = sealed_data();
+
+        for (state, want) in [
+            (&b"frozen"[..], None),
+            (b"released", Some(ERR_ILLEGAL_TRANSITION)),
+            (b"aborted", Some(ERR_ABORTED)),
+        ] {
+            let data = [base.clone(), b";state=".to_vec(), state.to_vec()].concat();
+            let len = data.len();
+            assert_eq!(record_deputy(&data, len, 1, Some((mock_account(0x50), 2000)), 1000).err(), want);
+            assert_eq!(record_consent(&data, len, FINISH_BY_OWNER, true, 1000).err(), want);
+            assert_eq!(record_destination_ack(&data, len, &dest, &dest, 1, 1000).err(), want);
+            assert_eq!(record_proposal(&data, len, &proposal(1, b"threshold", b"1"), 0, 1000).err(), want);
+
+            let data = [open[..open_len].to_vec(), b";state=".to_vec(), state.to_vec()].concat();
+            assert_eq!(record_proposal_vote(&data, data.len(), 1, 1, 1001).err(), want);
+            assert_eq!(record_proposal_withdrawal(&data, data.len(), 1, 0, 1001).err(), want);
+
+            let data = [sealed.clone(), b";state=".to_vec(), state.to_vec()].concat();
+            assert_eq!(record_commit(&data, data.len(), &phases, 0, &commitment(1, &SALT), 900).err(), want);
+        }
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // REVOCATION POLICY TESTS (revocation_policy)
+    // ═════════════════════════════════════════════════════════════
+
+    /// Notary 0 approves at ledger 1000 under `policy`.
+    fn approved_under(policy: &[u8]) -> ([u8; 4096], usize) {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let data = [data, policy.to_vec()].concat();
+        record_approval(&data, data.len(), 0, &accounts[0], 7, 1000, 2).unwrap()
+    }
+
+    #[test]
+    fn revocation_always_by_default() {
+        let (d, l) = approved_under(b"");
+        let (d, l) = record_revocation(&d[..l], l, 0, 900_000, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"approval_0"), Some(&b"0"[..]));
+        assert_eq!(find_value(&d[..l], b"last_revocation_policy"), Some(&b"always"[..]));
+        assert_eq!(find_value(&d[..l], b"last_revocation_ledger"), Some(&b"900000"[..]));
+        assert_eq!(find_value(&d[..l], b"approve_ledger_0"), None);
+    }
+
+    #[test]
+    fn revocation_never_locks_approvals() {
+        let (d, l) = approved_under(b";revocation_policy=never");
+        assert_eq!(record_revocation(&d[..l], l, 0, 1001, 2).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+        // Nothing to withdraw for a notary that never approved
+        assert!(record_revocation(&d[..l], l, 1, 1001, 2).is_ok());
+
+        let (audit, audit_len) = record_audit(&d[..l], l, ERR_REVOCATION_LOCKED, 5);
+        assert_eq!(find_value(&audit[..audit_len], b"last_result"), Some(&b"revocation_locked"[..]));
+    }
+
+    #[test]
+    fn revocation_before_quorum_locks_at_quorum() {
+        let (_, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (d, l) = approved_under(b";revocation_policy=before_quorum");
+        let (d1, l1) = record_revocation(&d[..l], l, 0, 1001, 2).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"last_revocation_policy"), Some(&b"before_quorum"[..]));
+
+        let (d, l) = record_approval(&d[..l], l, 1, &accounts[1], 8, 1002, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), Some(&b"1002"[..]));
+        assert_eq!(record_revocation(&d[..l], l, 0, 1003, 2).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+        assert_eq!(record_revocation(&d[..l], l, 1, 1003, 2).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+    }
+
+    #[test]
+    fn revocation_within_window_of_approval() {
+        let (d, l) = approved_under(b";revocation_policy=within_n_ledgers_of_approval;revocation_window_ledgers=10");
+        assert_eq!(find_value(&d[..l], b"approve_ledger_0"), Some(&b"1000"[..]));
+
+        let (d1, l1) = record_revocation(&d[..l], l, 0, 1010, 2).unwrap();
+        assert_eq!(
+            find_value(&d1[..l1], b"last_revocation_policy"),
+            Some(&b"within_n_ledgers_of_approval"[..])
+        );
+        assert_eq!(record_revocation(&d[..l], l, 0, 1011, 2).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+    }
+
+    #[test]
+    fn approval_keeps_other_notaries_approval_ledgers() {
+        let (_, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (d, l) = approved_under(b"");
+        let (d, l) = record_approval(&d[..l], l, 1, &accounts[1], 8, 1005, 3).unwrap();
+        // The audit trail names the latest approver only...
+        assert_eq!(find_value(&d[..l], b"approver_0"), None);
+        assert_eq!(find_value(&d[..l], b"approve_seq_0"), None);
+        assert_eq!(find_value(&d[..l], b"approve_seq_1"), Some(&b"8"[..]));
+        // ...but every approval keeps the ledger its window runs from
+        assert_eq!(find_value(&d[..l], b"approve_ledger_0"), Some(&b"1000"[..]));
+        assert_eq!(find_value(&d[..l], b"approve_ledger_1"), Some(&b"1005"[..]));
+    }
+
+    #[test]
+    fn revocation_policy_must_be_known() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";revocation_policy=sometimes");
+        assert_eq!(validate_config(&data), Err(ERR_BAD_CONFIG));
+
+        // The window policy needs its window
+        let mut data = governed_data();
+        data.extend_from_slice(b";revocation_policy=within_n_ledgers_of_approval");
+        assert_eq!(validate_config(&data), Err(ERR_BAD_CONFIG));
+        data.extend_from_slice(b";revocation_window_ledgers=50");
+        assert_eq!(validate_config(&data), Ok(()));
+    }
+
+    #[test]
+    fn revocation_policy_is_not_votable() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";revocation_policy=within_n_ledgers_of_approval;revocation_window_ledgers=50");
+        for p in [
+            proposal(1, b"revocation_policy", b"always"),
+            proposal(1, b"revocation_window_ledgers", b"5000"),
+        ] {
+            assert_eq!(record_proposal(&data, data.len(), &p, 0, 1).err(), Some(ERR_IMMUTABLE_KEY));
+        }
+    }
+
+    #[test]
+    fn signer_list_revocation_follows_policy() {
+        let list = signer_list(&[(0x01, 1), (0x02, 1)], 2);
+        let member = mock_account(0x01);
+        for (policy, expected) in [
+            (&b";revocation_policy=never"[..], Err(ERR_REVOCATION_LOCKED)),
+            (&b";revocation_policy=within_n_ledgers_of_approval;revocation_window_ledgers=5"[..], Err(ERR_BAD_CONFIG)),
+            (&b""[..], Ok(())),
+        ] {
+            let data = [signer_list_data(), policy.to_vec()].concat();
+            let (d, l) = record_signer_list_vote(&data, data.len(), &list, &member, true, 100).unwrap();
+            let result = record_signer_list_vote(&d[..l], l, &list, &member, false, 101);
+            assert_eq!(result.map(|_| ()), expected);
+        }
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // APPROVAL ROUND TESTS (approval_round / new_round)
+    // ═════════════════════════════════════════════════════════════
+
+    /// Notaries 0 and 1 of three have approved (threshold 2), reaching quorum.
+    fn approved_round_data() -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3]) {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (d, l) = record_approval(&data, data.len(), 0, &accounts[0], 1, 100, 2).unwrap();
+        let (d, l) = record_approval(&d[..l], l, 1, &accounts[1], 2, 101, 2).unwrap();
+        let tally = Tally::of(&d[..l], 2);
+        let (d, l) = record_transition(&d[..l], l, LifecycleEvent::Approve(tally)).unwrap();
+        (d[..l].to_vec(), accounts)
+    }
+
+    #[test]
+    fn new_round_voids_earlier_approvals() {
+        let (data, accounts) = approved_round_data();
+        assert_eq!(check_approval_threshold(&data), SUCCESS);
+
+        let (d, l) = start_round(&data, data.len(), 200).unwrap();
+        let round1 = &d[..l];
+        assert_eq!(approval_round(round1), Ok(1));
+        assert_eq!(find_value(round1, b"round_ledger"), Some(&b"200"[..]));
+        assert_eq!(check_approval_threshold(round1), ERR_NOT_APPROVED);
+        assert_eq!(find_value(round1, b"quorum_ledger"), None);
+        assert_eq!(recorded_state(round1), Ok(EscrowState::Configured));
+        // The old approvals are left in place, they just don't count
+        assert_eq!(find_value(round1, b"approval_0"), Some(&b"1"[..]));
+        assert_eq!(count_approvals_with_signers(round1, &[]), Ok(0));
+        assert!(!has_current_approval(round1, 0, 1));
+
+        // Notaries approve again in the new round
+        let (d, l) = record_approval(round1, l, 0, &accounts[0], 3, 201, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"approve_round_0"), Some(&b"1"[..]));
+        assert_eq!(check_approval_threshold(&d[..l]), ERR_NOT_APPROVED);
+        let (d, l) = record_approval(&d[..l], l, 1, &accounts[1], 4, 202, 2).unwrap();
+        assert_eq!(check_approval_threshold(&d[..l]), SUCCESS);
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), Some(&b"202"[..]));
+    }
+
+    #[test]
+    fn new_round_clears_consent_but_keeps_destination_ack() {
+        let (data, _) = approved_round_data();
+        let data = [data, b";consent_owner=150;consent_destination=151;dest_ack=00;dest_ack_ledger=152".to_vec()].concat();
+        let (d, l) = start_round(&data, data.len(), 200).unwrap();
+        assert_eq!(find_value(&d[..l], b"consent_owner"), None);
+        assert_eq!(find_value(&d[..l], b"consent_destination"), None);
+        assert_eq!(find_value(&d[..l], b"dest_ack"), Some(&b"00"[..]));
+        assert_eq!(find_value(&d[..l], b"dest_ack_ledger"), Some(&b"152"[..]));
+    }
+
+    /// approved_round_data() under `policy`: approvals at 100 and 101.
+    fn locked_round_data(policy: &[u8]) -> Vec<u8> {
+        let (data, _) = approved_round_data();
+        [data, b";".to_vec(), policy.to_vec()].concat()
+    }
+
+    #[test]
+    fn new_round_under_always_policy() {
+        let data = locked_round_data(b"revocation_policy=always");
+        assert!(start_round(&data, data.len(), 5000).is_ok());
+    }
+
+    #[test]
+    fn new_round_refused_under_never_policy() {
+        let data = locked_round_data(b"revocation_policy=never");
+        assert_eq!(start_round(&data, data.len(), 200).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+        // Nor can the notaries vote their way around it
+        let (d, l) = record_round_vote(&data, data.len(), 2, 2, 200).unwrap();
+        assert_eq!(record_round_vote(&d[..l], l, 0, 2, 201).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+        // With nothing approved in the round there is nothing to withdraw
+        let (fresh, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let fresh = [fresh, b";revocation_policy=never".to_vec()].concat();
+        assert!(start_round(&fresh, fresh.len(), 200).is_ok());
+    }
+
+    #[test]
+    fn new_round_refused_under_before_quorum_policy_after_quorum() {
+        let data = locked_round_data(b"revocation_policy=before_quorum");
+        assert!(find_value(&data, b"quorum_ledger").is_some());
+        assert_eq!(start_round(&data, data.len(), 200).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+
+        // Short of quorum the approvals may still be withdrawn
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let data = [data, b";revocation_policy=before_quorum".to_vec()].concat();
+        let (d, l) = record_approval(&data, data.len(), 0, &accounts[0], 1, 100, 2).unwrap();
+        assert!(start_round(&d[..l], l, 200).is_ok());
+    }
+
+    #[test]
+    fn new_round_within_revocation_window() {
+        let data = locked_round_data(b"revocation_policy=within_n_ledgers_of_approval;revocation_window_ledgers=50");
+        // Both approvals (100 and 101) are still inside the window at 150
+        assert!(start_round(&data, data.len(), 150).is_ok());
+        // The first one has left it at 151
+        assert_eq!(start_round(&data, data.len(), 151).map(|_| ()), Err(ERR_REVOCATION_LOCKED));
+    }
+
+    #[test]
+    fn stale_approval_revocation_leaves_count_alone() {
+        let (data, accounts) = approved_round_data();
+        let (d, l) = start_round(&data, data.len(), 200).unwrap();
+        let (d, l) = record_approval(&d[..l], l, 2, &accounts[2], 3, 201, 2).unwrap();
+        let (d, l) = record_revocation(&d[..l], l, 0, 202, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"approval_count"), Some(&b"1"[..]));
+        assert_eq!(find_value(&d[..l], b"last_revocation_policy"), None);
+    }
+
+    #[test]
+    fn notary_quorum_starts_new_round() {
+        let (data, _) = approved_round_data();
+        let (d, l) = record_round_vote(&data, data.len(), 2, 2, 300).unwrap();
+        assert_eq!(approval_round(&d[..l]), Ok(0));
+        assert_eq!(find_value(&d[..l], b"round_vote_2"), Some(&b"1"[..]));
+        assert_eq!(
+            record_round_vote(&d[..l], l, 2, 2, 301).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+
+        let (d, l) = record_round_vote(&d[..l], l, 0, 2, 302).unwrap();
+        assert_eq!(approval_round(&d[..l]), Ok(1));
+        assert_eq!(check_approval_threshold(&d[..l]), ERR_NOT_APPROVED);
+        assert_eq!(find_value(&d[..l], b"round_vote_2"), None);
+        assert_eq!(find_value(&d[..l], b"last_activity_ledger"), Some(&b"302"[..]));
+
+        // Votes are for the following round now
+        let (d, l) = record_round_vote(&d[..l], l, 2, 2, 303).unwrap();
+        assert_eq!(find_value(&d[..l], b"round_vote_2"), Some(&b"2"[..]));
+    }
+
+    #[test]
+    fn new_round_needs_an_open_data_notary_escrow() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let released = [data.clone(), b";state=released".to_vec()].concat();
+        assert_eq!(start_round(&released, released.len(), 200).map(|_| ()), Err(ERR_ILLEGAL_TRANSITION));
+        assert_eq!(
+            record_round_vote(&released, released.len(), 0, 2, 200).map(|_| ()),
+            Err(ERR_ILLEGAL_TRANSITION)
+        );
+        let frozen = [data, b";state=frozen".to_vec()].concat();
+        let (d, l) = start_round(&frozen, frozen.len(), 200).unwrap();
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Frozen));
+
+        let list = signer_list_data();
+        assert_eq!(start_round(&list, list.len(), 200).map(|_| ()), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_key_mutable(&list, b"approval_round"), Err(ERR_IMMUTABLE_KEY));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // COMMIT-REVEAL TESTS (commit_deadline / reveal_deadline)
+    // ═════════════════════════════════════════════════════════════
+
+    const SALT: [u8; 16] = [0x5a; 16];
+
+    /// Three notaries, threshold 2; commits until 1000, reveals until 1100.
+    fn sealed_data() -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3], CommitReveal) {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let data = [data, b";commit_deadline=1000;reveal_deadline=1100".to_vec()].concat();
+        let phases = parse_commit_reveal(&data).unwrap().unwrap();
+        (data, accounts, phases)
+    }
+
+    /// Reveal `vote` as set_approval does: a revealed 1 becomes an approval.
+    fn reveal(
+        data: &[u8],
+        phases: &CommitReveal,
+        index: u8,
+        caller: &[u8; ACCOUNT_ID_SIZE],
+        vote: u8,
+        ledger: u32,
+    ) -> Result<([u8; 4096], usize), i32> {
+        let (d, l) = record_reveal(data, data.len(), phases, index, (vote, &SALT), ledger)?;
+        if vote == 0 {
+            return Ok((d, l));
+        }
+        record_approval(&d[..l], l, index, caller, 1, ledger, 2)
+    }
+
+    fn commitment(vote: u8, salt: &[u8]) -> [u8; 64] {
+        let mut hex = [0u8; 64];
+        encode_hex(&sha256::sha256(&[&[vote][..], salt].concat()), &mut hex).unwrap();
+        hex
+    }
+
+    #[test]
+    fn commit_reveal_config() {
+        let (data, _, phases) = sealed_data();
+        assert_eq!(phases, CommitReveal { commit_deadline: 1000, reveal_deadline: 1100 });
+        assert_eq!(validate_config(&data), Ok(()));
+        assert_eq!(parse_commit_reveal(b"threshold=1"), Ok(None));
+        assert_eq!(parse_commit_reveal(b"commit_deadline=1000"), Err(ERR_BAD_CONFIG));
+        assert_eq!(parse_commit_reveal(b"reveal_deadline=1100"), Err(ERR_BAD_CONFIG));
+        assert_eq!(parse_commit_reveal(b"commit_deadline=1000;reveal_deadline=1000"), Err(ERR_BAD_CONFIG));
+        let (backed, _, _) = dead_man_data();
+        let backed = [backed, b";commit_deadline=1000;reveal_deadline=1100".to_vec()].concat();
+        assert_eq!(parse_commit_reveal(&backed), Err(ERR_BAD_CONFIG));
+        assert_eq!(start_round(&data, data.len(), 500).map(|_| ()), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn valid_reveals_reach_threshold() {
+        let (data, accounts, phases) = sealed_data();
+        let (d, l) = record_commit(&data, data.len(), &phases, 0, &commitment(1, &SALT), 900).unwrap();
+        let (d, l) = record_commit(&d[..l], l, &phases, 1, &commitment(1, &SALT), 901).unwrap();
+        let (d, l) = record_commit(&d[..l], l, &phases, 2, &commitment(0, &SALT), 902).unwrap();
+        assert_eq!(check_approval_threshold(&d[..l]), ERR_NOT_APPROVED);
+
+        let (d, l) = reveal(&d[..l], &phases, 0, &accounts[0], 1, 1001).unwrap();
+        assert_eq!(find_value(&d[..l], b"vote_reveal_0"), Some(&b"1"[..]));
+        let (d, l) = reveal(&d[..l], &phases, 2, &accounts[2], 0, 1002).unwrap();
+        assert_eq!(find_value(&d[..l], b"vote_reveal_2"), Some(&b"0"[..]));
+        assert_eq!(check_approval_threshold(&d[..l]), ERR_NOT_APPROVED);
+
+        let (d, l) = reveal(&d[..l], &phases, 1, &accounts[1], 1, 1003).unwrap();
+        assert_eq!(check_approval_threshold(&d[..l]), SUCCESS);
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), Some(&b"1003"[..]));
+        assert_eq!(
+            reveal(&d[..l], &phases, 1, &accounts[1], 1, 1004).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+    }
+
+    #[test]
+    fn invalid_reveals_are_refused() {
+        let (data, accounts, phases) = sealed_data();
+        let (d, l) = record_commit(&data, data.len(), &phases, 0, &commitment(0, &SALT), 900).unwrap();
+        let sealed = &d[..l];
+        // Revealing a different vote, or the right vote with another salt
+        assert_eq!(check_reveal(sealed, &phases, 0, 1, &SALT, 1001), Err(ERR_REVEAL));
+        assert_eq!(check_reveal(sealed, &phases, 0, 0, &[0x5b; 16], 1001), Err(ERR_REVEAL));
+        // Nothing committed
+        assert_eq!(check_reveal(sealed, &phases, 1, 1, &SALT, 1001), Err(ERR_REVEAL));
+        assert_eq!(check_reveal(sealed, &phases, 0, 0, &SALT, 1001), Ok(()));
+        assert_eq!(
+            reveal(sealed, &phases, 0, &accounts[0], 1, 1001).map(|_| ()),
+            Err(ERR_REVEAL)
+        );
+
+        let (audit, audit_len) = record_audit(sealed, l, ERR_REVEAL, 5);
+        assert_eq!(find_value(&audit[..audit_len], b"last_result"), Some(&b"reveal_invalid"[..]));
+    }
+
+    #[test]
+    fn reveals_only_between_deadlines() {
+        let (data, _, phases) = sealed_data();
+        let (d, l) = record_commit(&data, data.len(), &phases, 0, &commitment(1, &SALT), 1000).unwrap();
+        // Commit phase closes at the deadline
+        assert_eq!(
+            record_commit(&d[..l], l, &phases, 1, &commitment(1, &SALT), 1001).map(|_| ()),
+            Err(ERR_PHASE_CLOSED)
+        );
+        // Early and late reveals
+        assert_eq!(check_reveal(&d[..l], &phases, 0, 1, &SALT, 1000), Err(ERR_TOO_EARLY));
+        assert_eq!(check_reveal(&d[..l], &phases, 0, 1, &SALT, 1100), Ok(()));
+        assert_eq!(check_reveal(&d[..l], &phases, 0, 1, &SALT, 1101), Err(ERR_PHASE_CLOSED));
+
+        let (audit, audit_len) = record_audit(&d[..l], l, ERR_PHASE_CLOSED, 5);
+        assert_eq!(find_value(&audit[..audit_len], b"last_result"), Some(&b"phase_closed"[..]));
+    }
+
+    #[test]
+    fn recommit_replaces_commitment() {
+        let (data, _, phases) = sealed_data();
+        let (d, l) = record_commit(&data, data.len(), &phases, 0, &commitment(0, &SALT), 900).unwrap();
+        let (d, l) = record_commit(&d[..l], l, &phases, 0, &commitment(1, &SALT), 950).unwrap();
+        assert_eq!(check_reveal(&d[..l], &phases, 0, 0, &SALT, 1001), Err(ERR_REVEAL));
+        assert_eq!(check_reveal(&d[..l], &phases, 0, 1, &SALT, 1001), Ok(()));
+        assert_eq!(
+            record_commit(&d[..l], l, &phases, 0, b"abcd", 960).map(|_| ()),
+            Err(ERR_REVEAL)
+        );
+        assert_eq!(check_key_mutable(&data, b"vote_commit_0"), Err(ERR_IMMUTABLE_KEY));
+    }
+
+    #[test]
+    fn deadlines_are_not_votable() {
+        let (data, _, _) = sealed_data();
+        let data = [data, b";gov_threshold=2".to_vec()].concat();
+        for p in [proposal(1, b"commit_deadline", b"5000"), proposal(1, b"reveal_deadline", b"6000")] {
+            assert_eq!(record_proposal(&data, data.len(), &p, 0, 1).err(), Some(ERR_IMMUTABLE_KEY));
+        }
+    }
+
+    #[test]
+    fn reveal_memo_parsing() {
+        let memo = [b"vote=1;salt=".to_vec(), [b'a'; 32].to_vec()].concat();
+        let (vote, salt, len) = parse_reveal_memo(&memo).unwrap();
+        assert_eq!((vote, &salt[..len]), (1, &[0xaa; 16][..]));
+        // Salt too short to hide a one-bit vote, or not hex
+        let short = [b"vote=1;salt=".to_vec(), [b'a'; 30].to_vec()].concat();
+        assert_eq!(parse_reveal_memo(&short).map(|_| ()), Err(ERR_REVEAL));
+        assert_eq!(parse_reveal_memo(b"vote=yes;salt=aa").map(|_| ()), Err(ERR_REVEAL));
+        let long = [b"vote=0;salt=".to_vec(), [b'a'; 66].to_vec()].concat();
+        assert_eq!(parse_reveal_memo(&long).map(|_| ()), Err(ERR_REVEAL));
+    }
+
+    #[test]
+    fn signers_do_not_bypass_commit_reveal() {
+        let (data, accounts, _) = sealed_data();
+        assert_eq!(count_approvals_with_signers(&data, &accounts), Ok(0));
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &accounts), ERR_NOT_APPROVED);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // DEPUTY DELEGATION TESTS (deputy_N / set_deputy)
+    // ═════════════════════════════════════════════════════════════
+
+    /// Three notaries; notary 1 has deputy 0x50 until ledger 2000.
+    fn deputy_data() -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3]) {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (d, l) = record_deputy(&data, data.len(), 1, Some((mock_account(0x50), 2000)), 1000).unwrap();
+        (d[..l].to_vec(), accounts)
+    }
+
+    #[test]
+    fn deputy_resolves_to_principal_until_expiry() {
+        let (data, accounts) = deputy_data();
+        let deputy = mock_account(0x50);
+        assert_eq!(check_caller_is_notary(&data, &deputy, 1500), Ok(1));
+        assert_eq!(check_caller_is_notary(&data, &deputy, 2000), Ok(1));
+        assert_eq!(check_caller_is_notary(&data, &deputy, 2001), Err(ERR_WRONG_ACCOUNT));
+        // The principal keeps acting for itself
+        assert_eq!(check_caller_is_notary(&data, &accounts[1], 1500), Ok(1));
+        // Deputies aren't listed notaries for Signers or roles
+        assert_eq!(check_listed_notary(&data, &deputy), Err(ERR_WRONG_ACCOUNT));
+    }
+
+    #[test]
+    fn deputy_approval_counts_once_and_names_both() {
+        let (data, accounts) = deputy_data();
+        let deputy = mock_account(0x50);
+        let index = check_caller_is_notary(&data, &deputy, 1500).unwrap();
+        let (d, l) = record_approval(&data, data.len(), index, &deputy, 9, 1500, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"approval_count"), Some(&b"1"[..]));
+
+        let mut principal_hex = [0u8; 40];
+        encode_hex(&accounts[1], &mut principal_hex);
+        let mut deputy_hex = [0u8; 40];
+        encode_hex(&deputy, &mut deputy_hex);
+        assert_eq!(find_value(&d[..l], b"approver_1"), Some(&principal_hex[..]));
+        assert_eq!(find_value(&d[..l], b"approve_deputy_1"), Some(&deputy_hex[..]));
+
+        // The principal can't add a second approval for the same index
+        assert_eq!(
+            record_approval(&d[..l], l, 1, &accounts[1], 10, 1501, 2).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+        assert_eq!(count_approvals_with_signers(&d[..l], &[accounts[1]]), Ok(1));
+
+        // A direct re-approval after revoking drops the deputy record
+        let (d, l) = record_revocation(&d[..l], l, 1, 1502, 2).unwrap();
+        let (d, l) = record_approval(&d[..l], l, 1, &accounts[1], 11, 1503, 2).unwrap();
+        assert_eq!(find_value(&d[..l], b"approve_deputy_1"), None);
+    }
+
+    #[test]
+    fn deputy_registration_is_checked() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let deputy = Some((mock_account(0x50), 2000));
+        // Expired at registration, or another listed notary
+        assert!(record_deputy(&data, data.len(), 0, Some((mock_account(0x50), 999)), 1000).is_err());
+        assert!(record_deputy(&data, data.len(), 0, Some((accounts[2], 2000)), 1000).is_err());
+
+        // Already standing in for a different notary
+        let (d, l) = record_deputy(&data, data.len(), 1, deputy, 1000).unwrap();
+        assert_eq!(record_deputy(&d[..l], l, 0, deputy, 1000).map(|_| ()), Err(ERR_BAD_CONFIG));
+        // ...but the same notary may renew, or withdraw
+        let (d, l) = record_deputy(&d[..l], l, 1, Some((mock_account(0x50), 3000)), 1000).unwrap();
+        assert_eq!(find_value(&d[..l], b"deputy_until_1"), Some(&b"3000"[..]));
+        let (d, l) = record_deputy(&d[..l], l, 1, None, 1000).unwrap();
+        assert_eq!(find_value(&d[..l], b"deputy_1"), None);
+        assert_eq!(check_caller_is_notary(&d[..l], &mock_account(0x50), 1000), Err(ERR_WRONG_ACCOUNT));
+
+        // An excluded party can't come back in as a deputy
+        let owner = mock_account(0xA0);
+        let mut owner_hex = [0u8; 40];
+        encode_hex(&owner, &mut owner_hex);
+        let bound = [data.clone(), b";notary_exclude=owner;owner=".to_vec(), owner_hex.to_vec()].concat();
+        assert_eq!(
+            record_deputy(&bound, bound.len(), 0, Some((owner, 2000)), 1000).map(|_| ()),
+            Err(ERR_BAD_CONFIG)
+        );
+        assert_eq!(check_key_mutable(&data, b"deputy_0"), Err(ERR_IMMUTABLE_KEY));
+    }
+
+    #[test]
+    fn parse_deputy_memo_forms() {
+        let hex = [b"deputy=".to_vec(), mock_account_hex(0x50).to_vec(), b";until=2000".to_vec()].concat();
+        assert_eq!(parse_deputy_memo(&hex), Ok(Some((mock_account(0x50), 2000))));
+        assert_eq!(parse_deputy_memo(b"deputy=none"), Ok(None));
+        assert_eq!(parse_deputy_memo(b"deputy=abcd;until=5"), Err(ERR_BAD_CONFIG));
+        assert_eq!(parse_deputy_memo(&hex[..47]), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn dead_man_switch_drops_deputies() {
+        let (data, _, backup) = dead_man_data();
+        let (d, l) = record_deputy(&data, data.len(), 0, Some((mock_account(0x50), 9000)), 100).unwrap();
+        let (d, l) = apply_inactivity_switch(&d[..l], l, 6000).unwrap().unwrap();
+        assert_eq!(find_value(&d[..l], b"deputy_0"), None);
+        assert_eq!(check_caller_is_notary(&d[..l], &mock_account(0x50), 6000), Err(ERR_WRONG_ACCOUNT));
+        assert_eq!(check_caller_is_notary(&d[..l], &backup[0], 6000), Ok(0));
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // ABORT VOTE TESTS (abort_threshold / vote_abort)
+    // ═════════════════════════════════════════════════════════════
+
+    fn abortable_data() -> Vec<u8> {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        [data, b";abort_threshold=2".to_vec()].concat()
+    }
+
+    #[test]
+    fn abort_needs_threshold_votes() {
+        let data = abortable_data();
+        let (d, l) = record_abort_vote(&data, data.len(), 0, 300).unwrap();
+        assert_eq!(find_value(&d[..l], b"abort_votes"), Some(&b"1"[..]));
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Configured));
+        assert_eq!(
+            record_abort_vote(&d[..l], l, 0, 301).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+
+        let (d, l) = record_abort_vote(&d[..l], l, 2, 302).unwrap();
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Aborted));
+        assert_eq!(find_value(&d[..l], b"abort_ledger"), Some(&b"302"[..]));
+        assert_eq!(find_value(&d[..l], b"last_activity_ledger"), Some(&b"302"[..]));
+    }
+
+    #[test]
+    fn aborted_escrow_refuses_everything() {
+        let data = abortable_data();
+        let (d, l) = record_abort_vote(&data, data.len(), 0, 300).unwrap();
+        let (d, l) = record_abort_vote(&d[..l], l, 1, 301).unwrap();
+        let aborted = &d[..l];
+
+        assert_eq!(record_transition(aborted, l, LifecycleEvent::Release).map(|_| ()), Err(ERR_ABORTED));
+        let tally = Tally { approvals: 2, quorum: true };
+        assert_eq!(record_transition(aborted, l, LifecycleEvent::Approve(tally)).map(|_| ()), Err(ERR_ABORTED));
+        assert_eq!(record_transition(aborted, l, LifecycleEvent::Unfreeze(tally)).map(|_| ()), Err(ERR_ABORTED));
+        assert_eq!(record_abort_vote(aborted, l, 2, 302).map(|_| ()), Err(ERR_ABORTED));
+
+        let (audit, audit_len) = record_audit(aborted, l, ERR_ABORTED, 5);
+        assert_eq!(find_value(&audit[..audit_len], b"last_result"), Some(&b"aborted"[..]));
+    }
+
+    #[test]
+    fn abort_vote_works_on_frozen_escrow() {
+        let data = [multi_notary_data([0x01, 0x02, 0x03]).0, b";abort_threshold=1;state=frozen".to_vec()].concat();
+        let (d, l) = record_abort_vote(&data, data.len(), 1, 300).unwrap();
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Aborted));
+    }
+
+    #[test]
+    fn abort_requires_configuration() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(record_abort_vote(&data, data.len(), 0, 300).map(|_| ()), Err(ERR_BAD_CONFIG));
+        // Governance can't set an unsatisfiable abort threshold
+        let too_high = [governed_data(), b";abort_threshold=4".to_vec()].concat();
+        assert_eq!(validate_config(&too_high), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_key_mutable(&too_high, b"abort_votes"), Err(ERR_IMMUTABLE_KEY));
+    }
+
+    #[test]
+    fn dead_man_switch_drops_abort_votes() {
+        let (data, _, _) = dead_man_data();
+        let data = [data, b";abort_threshold=2;abort_vote_0=1;abort_votes=1".to_vec()].concat();
+        let (d, l) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        assert_eq!(find_value(&d[..l], b"abort_vote_0"), None);
+        assert_eq!(find_value(&d[..l], b"abort_votes"), None);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // DEAD-MAN SWITCH TESTS (inactivity_ledgers / backup_notary_N)
+    // ═════════════════════════════════════════════════════════════
+
+    /// 2-of-3 primary set, 1-of-2 backup set, switch after 1000 idle ledgers.
+    fn dead_man_data() -> (Vec<u8>, [[u8; ACCOUNT_ID_SIZE]; 3], [[u8; ACCOUNT_ID_SIZE]; 2]) {
+        let (mut data, primary) = multi_notary_data([0x01, 0x02, 0x03]);
+        let backup = [mock_account(0x11), mock_account(0x12)];
+        data.extend_from_slice(b";inactivity_ledgers=1000;created_ledger=5000");
+        data.extend_from_slice(b";backup_notary_count=2;backup_threshold=1;backup_notary_0=");
+        data.extend_from_slice(&mock_account_hex(0x11));
+        data.extend_from_slice(b";backup_notary_1=");
+        data.extend_from_slice(&mock_account_hex(0x12));
+        (data, primary, backup)
+    }
 
-        (data, accounts)
+    #[test]
+    fn notary_set_primary_while_active() {
+        let (data, _, _) = dead_man_data();
+        assert_eq!(active_notary_set(&data, 5000), Ok(NotarySet::Primary));
+        assert_eq!(active_notary_set(&data, 5999), Ok(NotarySet::Primary));
+        assert_eq!(apply_inactivity_switch(&data, data.len(), 5999), Ok(None));
+    }
+
+    #[test]
+    fn notary_set_backup_after_inactivity() {
+        let (data, _, _) = dead_man_data();
+        assert_eq!(active_notary_set(&data, 6000), Ok(NotarySet::Backup));
+    }
+
+    #[test]
+    fn approval_activity_resets_inactivity() {
+        let (data, primary, _) = dead_man_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &primary[0], 100, 5900, 2).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"last_activity_ledger"), Some(b"5900" as &[u8]));
+        assert_eq!(active_notary_set(&d1[..l1], 6000), Ok(NotarySet::Primary));
+        assert_eq!(active_notary_set(&d1[..l1], 6900), Ok(NotarySet::Backup));
+    }
+
+    #[test]
+    fn revocation_activity_resets_inactivity() {
+        let (data, primary, _) = dead_man_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &primary[0], 100, 5100, 2).unwrap();
+        let (d2, l2) = record_revocation(&d1[..l1], l1, 0, 5950, 2).unwrap();
+        assert_eq!(find_value(&d2[..l2], b"last_activity_ledger"), Some(b"5950" as &[u8]));
+        assert_eq!(active_notary_set(&d2[..l2], 6900), Ok(NotarySet::Primary));
+    }
+
+    #[test]
+    fn switch_is_persisted_and_resets_approvals() {
+        let (data, primary, _) = dead_man_data();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &primary[0], 100, 5100, 2).unwrap();
+
+        let (d2, l2) = apply_inactivity_switch(&d1[..l1], l1, 7000).unwrap().unwrap();
+        let switched = &d2[..l2];
+        assert_eq!(find_value(switched, b"notary_set"), Some(b"backup" as &[u8]));
+        // Recorded at the ledger the switch fired, not when it was noticed
+        assert_eq!(find_value(switched, b"notary_set_ledger"), Some(b"6100" as &[u8]));
+        assert_eq!(find_value(switched, b"approval_count"), Some(b"0" as &[u8]));
+        assert_eq!(find_value(switched, b"approval_0"), None);
+        // Configuration survives
+        assert_eq!(find_value(switched, b"backup_threshold"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(switched, b"notary_count"), Some(b"3" as &[u8]));
+
+        // Already recorded — nothing more to do
+        assert_eq!(apply_inactivity_switch(switched, l2, 9000), Ok(None));
+    }
+
+    #[test]
+    fn switch_keeps_the_approval_round() {
+        let (data, _, _) = dead_man_data();
+        let (d1, l1) = start_round(&data, data.len(), 5100).unwrap();
+        let (d2, l2) = start_round(&d1[..l1], l1, 5200).unwrap();
+        assert_eq!(approval_round(&d2[..l2]), Ok(2));
+
+        let (d3, l3) = apply_inactivity_switch(&d2[..l2], l2, 7000).unwrap().unwrap();
+        let switched = &d3[..l3];
+        assert_eq!(approval_round(switched), Ok(2));
+        assert_eq!(find_value(switched, b"round_ledger"), Some(b"5200" as &[u8]));
+    }
+
+    #[test]
+    fn switch_is_one_way() {
+        let (data, _, backup) = dead_man_data();
+        let (d1, l1) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        // Backup activity doesn't hand control back to the primary set
+        let (d2, l2) = record_approval(&d1[..l1], l1, 0, &backup[0], 200, 6001, 1).unwrap();
+        assert_eq!(active_notary_set(&d2[..l2], 6002), Ok(NotarySet::Backup));
+    }
+
+    #[test]
+    fn switch_drops_primary_governance_votes() {
+        let (mut data, _, _) = dead_man_data();
+        data.extend_from_slice(b";prop_id=1;prop_key=threshold;prop_value=1;prop_vote_0=1;prop_votes=2");
+        data.extend_from_slice(b";prop_quorum_ledger=5100;upgrade_votes=1;upgrade_vote_2=1");
+        let (d1, l1) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        let switched = &d1[..l1];
+        // The proposal stays open for the backup set to vote on from scratch
+        assert_eq!(find_value(switched, b"prop_id"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(switched, b"prop_vote_0"), None);
+        assert_eq!(find_value(switched, b"prop_votes"), None);
+        assert_eq!(find_value(switched, b"prop_quorum_ledger"), None);
+        assert_eq!(find_value(switched, b"upgrade_votes"), None);
+        assert_eq!(find_value(switched, b"upgrade_vote_2"), None);
+    }
+
+    #[test]
+    fn caller_check_follows_active_set() {
+        let (data, primary, backup) = dead_man_data();
+        assert_eq!(check_caller_is_notary(&data, &primary[1], 0), Ok(1));
+        assert_eq!(check_caller_is_notary(&data, &backup[0], 0), Err(ERR_WRONG_ACCOUNT));
+
+        let (d1, l1) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        let switched = &d1[..l1];
+        assert_eq!(check_caller_is_notary(switched, &primary[1], 0), Err(ERR_WRONG_ACCOUNT));
+        assert_eq!(check_caller_is_notary(switched, &backup[0], 0), Ok(0));
+        assert_eq!(check_caller_is_notary(switched, &backup[1], 0), Ok(1));
+    }
+
+    #[test]
+    fn backup_threshold_applies_after_switch() {
+        let (data, _, backup) = dead_man_data();
+        let (d1, l1) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        assert_eq!(required_threshold(&d1[..l1], None, 0), Ok(1));
+        assert_eq!(check_tiered_threshold(&d1[..l1], None, 0), ERR_NOT_APPROVED);
+
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &backup[1], 201, 6005, 1).unwrap();
+        assert_eq!(check_tiered_threshold(&d2[..l2], None, 0), SUCCESS);
+    }
+
+    #[test]
+    fn dead_man_without_baseline_is_bad_config() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";inactivity_ledgers=1000");
+        assert_eq!(active_notary_set(&data, 9000), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn dead_man_invalid_backup_set() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";inactivity_ledgers=10;created_ledger=0;backup_notary_count=1;backup_threshold=2");
+        assert_eq!(apply_inactivity_switch(&data, data.len(), 100), Err(ERR_BAD_CONFIG));
+
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";inactivity_ledgers=10;created_ledger=0");
+        assert_eq!(apply_inactivity_switch(&data, data.len(), 100), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn dead_man_not_configured_stays_primary() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(active_notary_set(&data, u32::MAX), Ok(NotarySet::Primary));
+        assert_eq!(apply_inactivity_switch(&data, data.len(), u32::MAX), Ok(None));
+    }
+
+    #[test]
+    fn unknown_notary_set_is_bad_config() {
+        let (mut data, account) = single_notary_data(0x01);
+        data.extend_from_slice(b";notary_set=tertiary");
+        assert_eq!(check_caller_is_notary(&data, &account, 0), Err(ERR_BAD_CONFIG));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // find_value TESTS
+    // GOVERNANCE PROPOSAL TESTS (gov_threshold / prop_*)
     // ═════════════════════════════════════════════════════════════
 
+    /// 2-of-3 escrow where 2 notary votes change config after 100 ledgers.
+    fn governed_data() -> Vec<u8> {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";gov_threshold=2;gov_delay_ledgers=100;immutable=finish_by,notary_0");
+        data
+    }
+
+    fn proposal(id: u32, key: &'static [u8], value: &'static [u8]) -> Proposal<'static> {
+        Proposal { id, key, value }
+    }
+
     #[test]
-    fn find_value_single_entry() {
-        // A data string with just one key=value pair
-        assert_eq!(find_value(b"key=val", b"key"), Some(b"val" as &[u8]));
+    fn parse_proposal_memo() {
+        assert_eq!(
+            parse_proposal(b"id=7;key=threshold;value=3"),
+            Ok(proposal(7, b"threshold", b"3"))
+        );
+        assert_eq!(parse_proposal(b"key=threshold;value=3"), Err(ERR_PROPOSAL));
+        assert_eq!(parse_proposal(b"id=x;key=threshold;value=3"), Err(ERR_PROPOSAL));
+        assert_eq!(parse_proposal(b"id=1;key=Threshold;value=3"), Err(ERR_PROPOSAL));
+        assert_eq!(parse_proposal(b"id=1;key=threshold;value="), Err(ERR_PROPOSAL));
+        assert_eq!(parse_proposal(b"id=1;key=threshold;value=a b"), Err(ERR_PROPOSAL));
+        assert_eq!(parse_proposal(b"id=1;key=;value=3"), Err(ERR_PROPOSAL));
     }
 
     #[test]
-    fn find_value_multiple_entries() {
-        // Standard semicolon-delimited format
-        let data = b"a=1;b=2;c=3";
-        assert_eq!(find_value(data, b"a"), Some(b"1" as &[u8]));
-        assert_eq!(find_value(data, b"b"), Some(b"2" as &[u8]));
-        assert_eq!(find_value(data, b"c"), Some(b"3" as &[u8]));
+    fn reserved_and_declared_keys_are_immutable() {
+        let data = governed_data();
+        for key in [
+            b"state" as &[u8], b"approval_count", b"approval_1", b"approver_0", b"quorum_ledger",
+            b"prop_votes", b"gov_last_id", b"immutable", b"notary_set", b"gov_delay_ledgers",
+            b"gov_expiry_ledgers", b"created_ledger", b"notaries_from",
+        ] {
+            assert_eq!(check_key_mutable(&data, key), Err(ERR_IMMUTABLE_KEY), "{:?}", key);
+        }
+        // Declared by the creator
+        assert_eq!(check_key_mutable(&data, b"finish_by"), Err(ERR_IMMUTABLE_KEY));
+        assert_eq!(check_key_mutable(&data, b"notary_0"), Err(ERR_IMMUTABLE_KEY));
+        // Everything else is open to proposals
+        assert_eq!(check_key_mutable(&data, b"notary_1"), Ok(()));
+        assert_eq!(check_key_mutable(&data, b"threshold"), Ok(()));
     }
 
     #[test]
-    fn find_value_missing_key() {
-        // Key that doesn't exist returns None
-        assert_eq!(find_value(b"a=1;b=2", b"c"), None);
+    fn proposal_passes_after_delay() {
+        let data = governed_data();
+        let p = proposal(1, b"threshold", b"3");
+
+        let (d1, l1) = record_proposal(&data, data.len(), &p, 0, 5000).unwrap();
+        let open = &d1[..l1];
+        assert_eq!(find_value(open, b"prop_key"), Some(b"threshold" as &[u8]));
+        assert_eq!(find_value(open, b"prop_votes"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(open, b"prop_quorum_ledger"), None);
+        assert_eq!(find_value(open, b"gov_last"), Some(b"proposed:1" as &[u8]));
+        assert_eq!(find_value(open, b"threshold"), Some(b"2" as &[u8])); // not yet
+
+        let (d2, l2) = record_proposal_vote(open, l1, 1, 2, 5010).unwrap();
+        let passed = &d2[..l2];
+        assert_eq!(find_value(passed, b"prop_votes"), Some(b"2" as &[u8]));
+        assert_eq!(find_value(passed, b"prop_quorum_ledger"), Some(b"5010" as &[u8]));
+        assert_eq!(find_value(passed, b"gov_last"), Some(b"voted:1" as &[u8]));
+
+        // Delay counts from the deciding vote
+        assert_eq!(apply_due_proposal(passed, l2, None, 5109), Ok(None));
+        let (d3, l3) = apply_due_proposal(passed, l2, None, 5110).unwrap().unwrap();
+        let applied = &d3[..l3];
+        assert_eq!(find_value(applied, b"threshold"), Some(b"3" as &[u8]));
+        assert_eq!(find_value(applied, b"gov_last"), Some(b"applied:1" as &[u8]));
+        assert_eq!(find_value(applied, b"gov_last_ledger"), Some(b"5110" as &[u8]));
+        assert_eq!(find_value(applied, b"gov_last_id"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(applied, b"prop_id"), None);
+        assert_eq!(find_value(applied, b"prop_vote_0"), None);
+
+        // Nothing open any more
+        assert_eq!(apply_due_proposal(applied, l3, None, 9000), Ok(None));
     }
 
     #[test]
-    fn find_value_empty_data() {
-        // Empty data always returns None
-        assert_eq!(find_value(b"", b"key"), None);
+    fn proposal_below_threshold_never_applies() {
+        let data = governed_data();
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(1, b"threshold", b"1"), 0, 5000).unwrap();
+        assert_eq!(apply_due_proposal(&d1[..l1], l1, None, u32::MAX), Ok(None));
     }
 
     #[test]
-    fn find_value_empty_value() {
-        // Key exists but value is empty
-        assert_eq!(find_value(b"key=", b"key"), Some(b"" as &[u8]));
+    fn proposal_votes_are_counted_once() {
+        let data = governed_data();
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(1, b"threshold", b"1"), 0, 5000).unwrap();
+        assert_eq!(record_proposal_vote(&d1[..l1], l1, 1, 0, 5001).err(), Some(ERR_ALREADY_APPROVED));
+        // Voting on the wrong id
+        assert_eq!(record_proposal_vote(&d1[..l1], l1, 2, 1, 5001).err(), Some(ERR_PROPOSAL));
+        // Voting with nothing open
+        assert_eq!(record_proposal_vote(&data, data.len(), 1, 1, 5001).err(), Some(ERR_PROPOSAL));
     }
 
     #[test]
-    fn find_value_partial_key_match() {
-        // "notary" should not match "notary_count"
-        assert_eq!(find_value(b"notary_count=3;notary=bad", b"notary"), Some(b"bad" as &[u8]));
-        assert_eq!(find_value(b"notary_count=3", b"notary"), None);
+    fn one_open_proposal_and_increasing_ids() {
+        let data = governed_data();
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(5, b"threshold", b"1"), 0, 5000).unwrap();
+        assert_eq!(
+            record_proposal(&d1[..l1], l1, &proposal(6, b"threshold", b"3"), 1, 5001).err(),
+            Some(ERR_PROPOSAL)
+        );
+
+        let (d2, l2) = record_proposal_vote(&d1[..l1], l1, 5, 1, 5001).unwrap();
+        let (d3, l3) = apply_due_proposal(&d2[..l2], l2, None, 5101).unwrap().unwrap();
+        // An applied id can't be reused, and ids can't go backwards
+        assert_eq!(
+            record_proposal(&d3[..l3], l3, &proposal(5, b"threshold", b"3"), 1, 5200).err(),
+            Some(ERR_PROPOSAL)
+        );
+        assert_eq!(
+            record_proposal(&d3[..l3], l3, &proposal(4, b"threshold", b"3"), 1, 5200).err(),
+            Some(ERR_PROPOSAL)
+        );
+        assert!(record_proposal(&d3[..l3], l3, &proposal(6, b"threshold", b"3"), 1, 5200).is_ok());
     }
 
     #[test]
-    fn find_value_duplicate_keys_returns_first() {
-        // If duplicate keys exist, first one wins
-        assert_eq!(find_value(b"x=first;x=second", b"x"), Some(b"first" as &[u8]));
+    fn expired_proposal_frees_the_slot() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";gov_expiry_ledgers=50");
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(5, b"threshold", b"1"), 0, 5000).unwrap();
+        let open = &d1[..l1];
+        assert_eq!(find_value(open, b"prop_expiry_ledger"), Some(b"5050" as &[u8]));
+
+        // Still open on its last ledger
+        assert_eq!(
+            record_proposal(open, l1, &proposal(6, b"threshold", b"3"), 1, 5050).err(),
+            Some(ERR_PROPOSAL)
+        );
+        // Expired: no more votes, and a new proposal replaces it
+        assert_eq!(record_proposal_vote(open, l1, 5, 1, 5051).err(), Some(ERR_PROPOSAL));
+        assert_eq!(
+            record_proposal(open, l1, &proposal(5, b"threshold", b"3"), 1, 5051).err(),
+            Some(ERR_PROPOSAL)
+        );
+        let (d2, l2) = record_proposal(open, l1, &proposal(6, b"threshold", b"3"), 1, 5051).unwrap();
+        let replaced = &d2[..l2];
+        assert_eq!(find_value(replaced, b"prop_id"), Some(b"6" as &[u8]));
+        assert_eq!(find_value(replaced, b"prop_vote_0"), None);
+        assert_eq!(find_value(replaced, b"prop_votes"), Some(b"1" as &[u8]));
     }
 
     #[test]
-    fn find_value_value_with_special_chars() {
-        // Values can contain any bytes except semicolons
-        assert_eq!(find_value(b"k=abc123!@#", b"k"), Some(b"abc123!@#" as &[u8]));
+    fn passed_proposal_does_not_expire() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";gov_expiry_ledgers=50");
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(1, b"threshold", b"3"), 0, 5000).unwrap();
+        let (d2, l2) = record_proposal_vote(&d1[..l1], l1, 1, 1, 5040).unwrap();
+        assert_eq!(
+            record_proposal(&d2[..l2], l2, &proposal(2, b"threshold", b"1"), 1, 5100).err(),
+            Some(ERR_PROPOSAL)
+        );
+        let (d3, l3) = apply_due_proposal(&d2[..l2], l2, None, 5140).unwrap().unwrap();
+        assert_eq!(find_value(&d3[..l3], b"threshold"), Some(b"3" as &[u8]));
+    }
+
+    #[test]
+    fn proposer_can_withdraw() {
+        let data = governed_data();
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(3, b"threshold", b"1"), 0, 5000).unwrap();
+        let open = &d1[..l1];
+        assert_eq!(record_proposal_withdrawal(open, l1, 3, 1, 5001).err(), Some(ERR_WRONG_ACCOUNT));
+        assert_eq!(record_proposal_withdrawal(open, l1, 4, 0, 5001).err(), Some(ERR_PROPOSAL));
+
+        let (d2, l2) = record_proposal_withdrawal(open, l1, 3, 0, 5001).unwrap();
+        let withdrawn = &d2[..l2];
+        assert_eq!(find_value(withdrawn, b"prop_id"), None);
+        assert_eq!(find_value(withdrawn, b"gov_last"), Some(b"withdrawn:3" as &[u8]));
+        assert_eq!(find_value(withdrawn, b"gov_last_id"), Some(b"3" as &[u8]));
+        // The slot is free, the id is used up
+        assert_eq!(
+            record_proposal(withdrawn, l2, &proposal(3, b"threshold", b"3"), 1, 5002).err(),
+            Some(ERR_PROPOSAL)
+        );
+        assert!(record_proposal(withdrawn, l2, &proposal(4, b"threshold", b"3"), 1, 5002).is_ok());
+    }
+
+    #[test]
+    fn proposal_refuses_immutable_and_invalid_changes() {
+        let data = governed_data();
+        let len = data.len();
+        assert_eq!(
+            record_proposal(&data, len, &proposal(1, b"finish_by", b"any"), 0, 1).err(),
+            Some(ERR_IMMUTABLE_KEY)
+        );
+        assert_eq!(
+            record_proposal(&data, len, &proposal(1, b"approval_count", b"3"), 0, 1).err(),
+            Some(ERR_IMMUTABLE_KEY)
+        );
+        // Would make the escrow unreleasable / unparsable
+        assert_eq!(
+            record_proposal(&data, len, &proposal(1, b"threshold", b"4"), 0, 1).err(),
+            Some(ERR_BAD_CONFIG)
+        );
+        assert_eq!(
+            record_proposal(&data, len, &proposal(1, b"notary_count", b"0"), 0, 1).err(),
+            Some(ERR_BAD_CONFIG)
+        );
+        assert_eq!(
+            record_proposal(&data, len, &proposal(1, b"tier_0", b"5:1"), 0, 1).err(),
+            Some(ERR_BAD_CONFIG)
+        );
+    }
+
+    #[test]
+    fn proposal_refuses_reserved_rule_keys() {
+        let data = governed_data();
+        for key in [
+            b"gov_threshold" as &[u8], b"abort_threshold", b"destination_tag", b"source_tag", b"max_amount",
+        ] {
+            let p = Proposal { id: 1, key, value: b"1" };
+            assert_eq!(record_proposal(&data, data.len(), &p, 0, 1).err(), Some(ERR_IMMUTABLE_KEY), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn governance_disabled_without_threshold() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(
+            record_proposal(&data, data.len(), &proposal(1, b"threshold", b"1"), 0, 1).err(),
+            Some(ERR_BAD_CONFIG)
+        );
+    }
+
+    #[test]
+    fn replacing_a_notary_by_vote() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";gov_threshold=1;gov_delay_ledgers=0");
+        let new_hex = mock_account_hex(0x09);
+        let p = Proposal { id: 1, key: b"notary_2", value: &new_hex };
+
+        let (d1, l1) = record_proposal(&data, data.len(), &p, 0, 10).unwrap();
+        // Threshold of one, no delay: due at once
+        let (d2, l2) = apply_due_proposal(&d1[..l1], l1, None, 10).unwrap().unwrap();
+        assert_eq!(check_caller_is_notary(&d2[..l2], &accounts[2], 0), Err(ERR_WRONG_ACCOUNT));
+        assert_eq!(check_caller_is_notary(&d2[..l2], &mock_account(0x09), 0), Ok(2));
+    }
+
+    #[test]
+    fn lowered_threshold_starts_the_challenge_period() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";gov_threshold=1;gov_delay_ledgers=0;challenge_ledgers=50");
+        let (d, l) = record_approval(&data, data.len(), 1, &accounts[1], 7, 10, 2).unwrap();
+        let (d, l) = record_transition(&d[..l], l, LifecycleEvent::Approve(Tally::of(&d[..l], 2))).unwrap();
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Collecting));
+
+        let (d, l) = record_proposal(&d[..l], l, &proposal(1, b"threshold", b"1"), 0, 20).unwrap();
+        let (d, l) = apply_due_proposal(&d[..l], l, None, 20).unwrap().unwrap();
+        let lowered = &d[..l];
+        assert_eq!(find_value(lowered, b"quorum_ledger"), Some(b"20" as &[u8]));
+        assert_eq!(recorded_state(lowered), Ok(EscrowState::Quorum));
+        assert_eq!(check_challenge_period(lowered, 69), ERR_TOO_EARLY);
+        assert_eq!(check_challenge_period(lowered, 70), SUCCESS);
+
+        // Raising it again loses the quorum
+        let (d, l) = record_proposal(lowered, l, &proposal(2, b"threshold", b"3"), 0, 30).unwrap();
+        let (d, l) = apply_due_proposal(&d[..l], l, None, 30).unwrap().unwrap();
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), None);
+        assert_eq!(recorded_state(&d[..l]), Ok(EscrowState::Collecting));
+    }
+
+    #[test]
+    fn settled_escrow_config_does_not_change() {
+        let data = governed_data();
+        let (d1, l1) = record_proposal(&data, data.len(), &proposal(1, b"threshold", b"3"), 0, 10).unwrap();
+        let (d2, l2) = record_proposal_vote(&d1[..l1], l1, 1, 1, 11).unwrap();
+        assert!(apply_due_proposal(&d2[..l2], l2, None, 111).unwrap().is_some());
+        let (d3, l3) = record_transition(&d2[..l2], l2, LifecycleEvent::Abort).unwrap();
+        assert_eq!(apply_due_proposal(&d3[..l3], l3, None, 111), Ok(None));
+    }
+
+    #[test]
+    fn replaced_notary_loses_approval_and_votes() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";gov_threshold=1;gov_delay_ledgers=0;abort_threshold=3");
+        let (d, l) = record_approval(&data, data.len(), 1, &accounts[1], 7, 10, 3).unwrap();
+        let (d, l) = record_approval(&d[..l], l, 2, &accounts[2], 8, 11, 3).unwrap();
+        let (d, l) = record_abort_vote(&d[..l], l, 2, 12).unwrap();
+        assert_eq!(find_value(&d[..l], b"approval_count"), Some(b"2" as &[u8]));
+
+        let new_hex = mock_account_hex(0x09);
+        let p = Proposal { id: 1, key: b"notary_2", value: &new_hex };
+        let (d, l) = record_proposal(&d[..l], l, &p, 0, 20).unwrap();
+        let (d, l) = apply_due_proposal(&d[..l], l, None, 20).unwrap().unwrap();
+        let applied = &d[..l];
+        assert_eq!(find_value(applied, b"approval_2"), None);
+        assert_eq!(find_value(applied, b"approver_2"), None);
+        assert_eq!(find_value(applied, b"approve_seq_2"), None);
+        assert_eq!(find_value(applied, b"abort_vote_2"), None);
+        assert_eq!(find_value(applied, b"abort_votes"), Some(b"0" as &[u8]));
+        assert_eq!(find_value(applied, b"approval_count"), Some(b"1" as &[u8]));
+        // Notary 1 keeps its approval
+        assert_eq!(find_value(applied, b"approval_1"), Some(b"1" as &[u8]));
+    }
+
+    #[test]
+    fn lowering_notary_count_clears_dropped_slots() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";gov_threshold=1;gov_delay_ledgers=0");
+        let (d, l) = record_approval(&data, data.len(), 0, &accounts[0], 7, 10, 3).unwrap();
+        let (d, l) = record_approval(&d[..l], l, 2, &accounts[2], 8, 11, 3).unwrap();
+
+        let (d, l) = record_proposal(&d[..l], l, &proposal(1, b"notary_count", b"2"), 0, 20).unwrap();
+        let (d, l) = apply_due_proposal(&d[..l], l, None, 20).unwrap().unwrap();
+        assert_eq!(find_value(&d[..l], b"approval_2"), None);
+        assert_eq!(find_value(&d[..l], b"approval_0"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(&d[..l], b"approval_count"), Some(b"1" as &[u8]));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // parse_u8_digit TESTS
+    // MULTISIGNED FINISH TESTS (Signers as approvals)
     // ═════════════════════════════════════════════════════════════
 
     #[test]
-    fn parse_digit_valid() {
-        for i in 0..=9u8 {
-            assert_eq!(parse_u8_digit(&[b'0' + i]), Some(i));
+    fn no_signers_falls_back_to_stored_approvals() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[]), ERR_NOT_APPROVED);
+        data.extend_from_slice(b";approval_0=1;approval_1=1;approval_count=2");
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[]), SUCCESS);
+    }
+
+    #[test]
+    fn signers_alone_reach_quorum() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[accounts[0]]), ERR_NOT_APPROVED);
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[accounts[0], accounts[2]]), SUCCESS);
+    }
+
+    #[test]
+    fn signers_combine_with_stored_approvals() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";approval_1=1;approval_count=1");
+        assert_eq!(count_approvals_with_signers(&data, &[accounts[2]]), Ok(2));
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[accounts[2]]), SUCCESS);
+    }
+
+    #[test]
+    fn signer_who_already_approved_counts_once() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";approval_0=1;approval_count=1");
+        assert_eq!(count_approvals_with_signers(&data, &[accounts[0], accounts[0]]), Ok(1));
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &[accounts[0]]), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn non_notary_signers_are_ignored() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let outsiders = [mock_account(0x40), mock_account(0x41), accounts[1]];
+        assert_eq!(count_approvals_with_signers(&data, &outsiders), Ok(1));
+        assert_eq!(check_threshold_with_signers(&data, None, None, 0, &outsiders), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn revoked_approval_is_not_counted() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";approval_0=0;approval_count=0");
+        assert_eq!(count_approvals_with_signers(&data, &[accounts[1]]), Ok(1));
+    }
+
+    #[test]
+    fn signers_use_the_active_notary_set() {
+        let (data, primary, backup) = dead_man_data();
+        let (d1, l1) = apply_inactivity_switch(&data, data.len(), 6000).unwrap().unwrap();
+        let switched = &d1[..l1];
+        assert_eq!(check_threshold_with_signers(switched, None, None, 6000, &[primary[0], primary[1]]), ERR_NOT_APPROVED);
+        assert_eq!(check_threshold_with_signers(switched, None, None, 6000, &[backup[1]]), SUCCESS);
+    }
+
+    #[test]
+    fn signer_can_authorize_submission() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let shared = mock_account(0x50);
+        let owner = mock_account(0x60);
+        let dest = mock_account(0x61);
+
+        // A shared account multisigned by a notary may finish
+        assert_eq!(check_finish_submitter_or_signers(&data, None, &shared, &[accounts[1]], &owner, &dest), Ok(()));
+        assert_eq!(
+            check_finish_submitter_or_signers(&data, None, &shared, &[mock_account(0x51)], &owner, &dest),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+        assert_eq!(
+            check_finish_submitter_or_signers(&data, None, &shared, &[], &owner, &dest),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+    }
+
+    #[test]
+    fn signer_submission_keeps_config_errors() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";finish_by=auditor");
+        let other = mock_account(0x60);
+        assert_eq!(
+            check_finish_submitter_or_signers(&data, None, &other, &[accounts[0]], &other, &other),
+            Err(ERR_BAD_CONFIG)
+        );
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // SIGNERLIST NOTARY TESTS (notaries_from)
+    // ═════════════════════════════════════════════════════════════
+
+    /// A SignerList of (seed, weight) entries with the given quorum.
+    fn signer_list(entries: &[(u8, u16)], quorum: u32) -> SignerList {
+        let mut list = SignerList {
+            accounts: [[0u8; ACCOUNT_ID_SIZE]; MAX_SIGNERS],
+            weights: [0u16; MAX_SIGNERS],
+            len: entries.len(),
+            quorum,
+        };
+        for (i, &(seed, weight)) in entries.iter().enumerate() {
+            list.accounts[i] = mock_account(seed);
+            list.weights[i] = weight;
+        }
+        list
+    }
+
+    fn signer_list_data() -> Vec<u8> {
+        let mut data = b"notaries_from=signerlist:".to_vec();
+        data.extend_from_slice(&mock_account_hex(0x70));
+        data
+    }
+
+    #[test]
+    fn notaries_from_parses_signer_list_owner() {
+        assert_eq!(parse_notaries_from(b"threshold=1"), Ok(None));
+        assert_eq!(parse_notaries_from(&signer_list_data()), Ok(Some(mock_account(0x70))));
+        assert_eq!(parse_notaries_from(b"notaries_from=signerlist:abcd"), Err(ERR_BAD_CONFIG));
+        assert_eq!(parse_notaries_from(b"notaries_from=multisig"), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn notaries_from_rejects_index_keyed_config() {
+        for key in SIGNERLIST_CONFLICTING_KEYS {
+            let mut data = signer_list_data();
+            data.push(b';');
+            data.extend_from_slice(key);
+            data.extend_from_slice(b"=1");
+            assert_eq!(parse_notaries_from(&data), Err(ERR_BAD_CONFIG));
         }
     }
 
     #[test]
-    fn parse_digit_invalid() {
-        assert_eq!(parse_u8_digit(b""), None);        // empty
-        assert_eq!(parse_u8_digit(b"10"), None);       // two digits
-        assert_eq!(parse_u8_digit(b"a"), None);        // not a digit
-        assert_eq!(parse_u8_digit(b" "), None);        // space
+    fn signer_list_membership_replaces_notary_list() {
+        let data = signer_list_data();
+        let list = signer_list(&[(0x01, 1), (0x02, 1)], 2);
+        let owner = mock_account(0xA0);
+        assert_eq!(check_notary_membership(&data, Some(&list), &mock_account(0x02)), Ok(()));
+        assert_eq!(
+            check_notary_membership(&data, Some(&list), &mock_account(0x03)),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+        assert_eq!(
+            check_finish_submitter_or_signers(&data, Some(&list), &owner, &[mock_account(0x01)], &owner, &owner),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn signer_list_quorum_is_weighted() {
+        let list = signer_list(&[(0x01, 3), (0x02, 1), (0x03, 1)], 3);
+        let (data, len) = {
+            let d = signer_list_data();
+            let l = d.len();
+            record_signer_list_vote(&d, l, &list, &mock_account(0x02), true, 100).unwrap()
+        };
+        let data = &data[..len];
+        assert_eq!(check_threshold_with_signers(data, Some(&list), None, 100, &[]), ERR_NOT_APPROVED);
+        // The heavy member alone reaches the quorum, as a signer or stored
+        assert_eq!(check_threshold_with_signers(data, Some(&list), None, 100, &[mock_account(0x01)]), SUCCESS);
+        let (data, len) = record_signer_list_vote(data, len, &list, &mock_account(0x03), true, 101).unwrap();
+        assert_eq!(check_signer_list_quorum(&data[..len], &list, &[]), ERR_NOT_APPROVED);
+        assert_eq!(signer_list_tally(&data[..len], &list), Tally { approvals: 2, quorum: false });
+    }
+
+    #[test]
+    fn signer_list_vote_records_quorum_ledger() {
+        let list = signer_list(&[(0x01, 1), (0x02, 1)], 2);
+        let d = signer_list_data();
+        let (d, l) = record_signer_list_vote(&d, d.len(), &list, &mock_account(0x01), true, 100).unwrap();
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), None);
+        let (d, l) = record_signer_list_vote(&d[..l], l, &list, &mock_account(0x02), true, 105).unwrap();
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), Some(&b"105"[..]));
+        assert_eq!(find_value(&d[..l], b"last_activity_ledger"), Some(&b"105"[..]));
+
+        // Duplicate approvals and outsiders are refused
+        assert_eq!(
+            record_signer_list_vote(&d[..l], l, &list, &mock_account(0x01), true, 106).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+        assert_eq!(
+            record_signer_list_vote(&d[..l], l, &list, &mock_account(0x09), true, 106).map(|_| ()),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+
+        // Revoking loses quorum and clears quorum_ledger
+        let (d, l) = record_signer_list_vote(&d[..l], l, &list, &mock_account(0x01), false, 107).unwrap();
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), None);
+        assert_eq!(signer_list_tally(&d[..l], &list), Tally { approvals: 1, quorum: false });
+    }
+
+    #[test]
+    fn signer_list_change_drops_removed_members() {
+        let before = signer_list(&[(0x01, 1), (0x02, 1), (0x03, 1)], 2);
+        let d = signer_list_data();
+        let (d, l) = record_signer_list_vote(&d, d.len(), &before, &mock_account(0x01), true, 100).unwrap();
+        let (d, l) = record_signer_list_vote(&d[..l], l, &before, &mock_account(0x02), true, 101).unwrap();
+        assert_eq!(check_signer_list_quorum(&d[..l], &before, &[]), SUCCESS);
+
+        // 0x01 is removed from the SignerList: its approval stops counting at once
+        let after = signer_list(&[(0x02, 1), (0x03, 1)], 2);
+        assert_eq!(check_signer_list_quorum(&d[..l], &after, &[]), ERR_NOT_APPROVED);
+
+        // ...and is pruned from the data on the next vote
+        let (d, l) = record_signer_list_vote(&d[..l], l, &after, &mock_account(0x03), true, 102).unwrap();
+        let mut key = [0u8; 56];
+        let key_len = signer_approval_key(&mock_account(0x01), &mut key);
+        assert_eq!(find_value(&d[..l], &key[..key_len]), None);
+        assert_eq!(check_signer_list_quorum(&d[..l], &after, &[]), SUCCESS);
+        // Quorum held throughout the change, so the original quorum ledger stands
+        assert_eq!(find_value(&d[..l], b"quorum_ledger"), Some(&b"101"[..]));
+    }
+
+    #[test]
+    fn signer_approvals_are_reserved_keys() {
+        let data = governed_data();
+        assert_eq!(check_key_mutable(&data, b"signer_approval_00"), Err(ERR_IMMUTABLE_KEY));
+        // Governance can't switch an escrow to SignerList notaries
+        let mut switched = data.clone();
+        switched.extend_from_slice(b";notaries_from=signerlist:");
+        switched.extend_from_slice(&mock_account_hex(0x70));
+        assert_eq!(validate_config(&switched), Err(ERR_BAD_CONFIG));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // parse_u32 TESTS
+    // RELEASE POLICY TESTS (policy / guardian)
     // ═════════════════════════════════════════════════════════════
 
     #[test]
-    fn parse_u32_valid() {
-        assert_eq!(parse_u32(b"0"), Some(0));
-        assert_eq!(parse_u32(b"1"), Some(1));
-        assert_eq!(parse_u32(b"42"), Some(42));
-        assert_eq!(parse_u32(b"1000"), Some(1000));
-        assert_eq!(parse_u32(b"4294967295"), Some(u32::MAX));
+    fn release_policy_is_optional() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert!(matches!(parse_release_policy(&data), Ok(None)));
     }
 
     #[test]
-    fn parse_u32_invalid() {
-        assert_eq!(parse_u32(b""), None);
-        assert_eq!(parse_u32(b"abc"), None);
-        assert_eq!(parse_u32(b"12x"), None);
-        assert_eq!(parse_u32(b"-1"), None);
+    fn release_policy_parses_from_data() {
+        let (mut data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        data.extend_from_slice(b";policy=AND(threshold, ledger>=9000000)");
+        assert!(matches!(parse_release_policy(&data), Ok(Some(_))));
+
+        // hashlock_mode would be silently ignored under a policy
+        data.extend_from_slice(b";hashlock_mode=or");
+        assert!(matches!(parse_release_policy(&data), Err(ERR_BAD_CONFIG)));
     }
 
     #[test]
-    fn parse_u32_overflow() {
-        // One more than u32::MAX should overflow
-        assert_eq!(parse_u32(b"4294967296"), None);
+    fn governance_cannot_install_a_malformed_policy() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";policy=AND(threshold");
+        assert_eq!(validate_config(&data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn governance_cannot_propose_a_template() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";template=custody_2of3");
+        assert_eq!(validate_config(&data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn guardian_submits_or_signs() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let guardian = mock_account(0x40);
+        let other = mock_account(0x41);
+        assert_eq!(check_guardian(&data, &guardian, &[]), ERR_BAD_CONFIG);
+
+        data.extend_from_slice(b";guardian=");
+        data.extend_from_slice(&mock_account_hex(0x40));
+        assert_eq!(check_guardian(&data, &guardian, &[]), SUCCESS);
+        assert_eq!(check_guardian(&data, &other, &[accounts[0], guardian]), SUCCESS);
+        assert_eq!(check_guardian(&data, &other, &[accounts[0]]), ERR_WRONG_ACCOUNT);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // CONFIG BINDING TESTS (owner / destination / tags / max_amount)
+    // ═════════════════════════════════════════════════════════════
+
+    fn escrow_fields() -> EscrowFields {
+        EscrowFields {
+            owner: mock_account(0xA0),
+            destination: mock_account(0xB0),
+            destination_tag: Some(42),
+            source_tag: None,
+            amount_drops: Some(5_000_000),
+        }
+    }
+
+    #[test]
+    fn unbound_config_matches_any_escrow() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(check_config_binding(&data, &escrow_fields()), SUCCESS);
+    }
+
+    #[test]
+    fn binding_checks_owner_and_destination() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let bound = [
+            data.clone(),
+            b";owner=".to_vec(),
+            mock_account_hex(0xA0).to_vec(),
+            b";destination=".to_vec(),
+            mock_account_hex(0xB0).to_vec(),
+        ]
+        .concat();
+        assert_eq!(check_config_binding(&bound, &escrow_fields()), SUCCESS);
+
+        // The same data pasted into another owner's escrow
+        let mut other = escrow_fields();
+        other.owner = mock_account(0xA1);
+        assert_eq!(check_config_binding(&bound, &other), ERR_BAD_CONFIG);
+
+        let swapped = [data, b";destination=".to_vec(), mock_account_hex(0xA0).to_vec()].concat();
+        assert_eq!(check_config_binding(&swapped, &escrow_fields()), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn binding_checks_tags() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let tagged = [data.clone(), b";destination_tag=42".to_vec()].concat();
+        assert_eq!(check_config_binding(&tagged, &escrow_fields()), SUCCESS);
+        let wrong = [data.clone(), b";destination_tag=43".to_vec()].concat();
+        assert_eq!(check_config_binding(&wrong, &escrow_fields()), ERR_BAD_CONFIG);
+        // The escrow has no SourceTag, so any bound source_tag mismatches
+        let source = [data.clone(), b";source_tag=0".to_vec()].concat();
+        assert_eq!(check_config_binding(&source, &escrow_fields()), ERR_BAD_CONFIG);
+        let malformed = [data, b";destination_tag=forty-two".to_vec()].concat();
+        assert_eq!(check_config_binding(&malformed, &escrow_fields()), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn binding_caps_amount() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let capped = [data.clone(), b";max_amount=5000000".to_vec()].concat();
+        assert_eq!(check_config_binding(&capped, &escrow_fields()), SUCCESS);
+
+        let mut larger = escrow_fields();
+        larger.amount_drops = Some(5_000_001);
+        assert_eq!(check_config_binding(&capped, &larger), ERR_BAD_CONFIG);
+
+        // A drops cap can't be checked against a token escrow
+        let mut token = escrow_fields();
+        token.amount_drops = None;
+        assert_eq!(check_config_binding(&capped, &token), ERR_BAD_CONFIG);
+    }
+
+    // ═════════════════════════════════════════════════════════════
+    // CONFLICT OF INTEREST TESTS (notary_exclude / require_consent)
+    // ═════════════════════════════════════════════════════════════
+
+    #[test]
+    fn duplicate_notaries_are_bad_config() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x01]);
+        assert_eq!(check_caller_is_notary(&data, &accounts[1], 0), Err(ERR_BAD_CONFIG));
+        assert_eq!(validate_config(&data), Err(ERR_BAD_CONFIG));
+        // Hex case doesn't hide a duplicate
+        let (mut data, accounts) = multi_notary_data([0xAB, 0x02, 0x03]);
+        let upper = mock_account_hex(0xAB).to_ascii_uppercase();
+        let pos = data.windows(40).position(|w| w == mock_account_hex(0x03)).unwrap();
+        data[pos..pos + 40].copy_from_slice(&upper);
+        assert_eq!(check_caller_is_notary(&data, &accounts[0], 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn excluded_parties_cannot_be_notaries() {
+        let (data, accounts) = multi_notary_data([0x01, 0x02, 0xA0]);
+        let with_owner = [data.clone(), b";owner=".to_vec(), mock_account_hex(0xA0).to_vec()].concat();
+        // Without the rule the owner may notarize
+        assert_eq!(check_caller_is_notary(&with_owner, &accounts[2], 0), Ok(2));
+
+        let excluded = [with_owner, b";notary_exclude=owner".to_vec()].concat();
+        assert_eq!(check_caller_is_notary(&excluded, &accounts[0], 0), Err(ERR_BAD_CONFIG));
+
+        // Excluding the destination doesn't affect an owner-notary...
+        let destination_only = [
+            data.clone(),
+            b";destination=".to_vec(),
+            mock_account_hex(0xB0).to_vec(),
+            b";notary_exclude=destination".to_vec(),
+        ]
+        .concat();
+        assert_eq!(check_caller_is_notary(&destination_only, &accounts[2], 0), Ok(2));
+
+        // ...and an exclusion needs the binding key it refers to
+        let unbound = [data, b";notary_exclude=owner".to_vec()].concat();
+        assert_eq!(check_caller_is_notary(&unbound, &accounts[0], 0), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn exclusion_applies_to_backup_set() {
+        let (mut data, _, _) = dead_man_data();
+        data.extend_from_slice(b";notary_exclude=destination;destination=");
+        data.extend_from_slice(&mock_account_hex(0x12));
+        assert_eq!(validate_backup_set(&data), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn consent_role_only_for_required_parties() {
+        let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let owner = mock_account(0xA0);
+        let dest = mock_account(0xB0);
+        assert_eq!(consent_role(&data, &owner, &owner, &dest), Ok(None));
+
+        data.extend_from_slice(b";require_consent=destination");
+        assert_eq!(consent_role(&data, &dest, &owner, &dest), Ok(Some(FINISH_BY_DESTINATION)));
+        assert_eq!(consent_role(&data, &owner, &owner, &dest), Ok(None));
+        assert_eq!(consent_role(&data, &accounts[0], &owner, &dest), Ok(None));
+
+        let bad = b"require_consent=notary";
+        assert_eq!(consent_role(bad, &owner, &owner, &dest), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_consent(bad), ERR_BAD_CONFIG);
+    }
+
+    #[test]
+    fn consent_is_required_but_does_not_count() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let data = [data, b";approval_0=1;approval_1=1;approval_count=2;require_consent=owner,destination".to_vec()].concat();
+        assert_eq!(check_consent(&data), ERR_NOT_APPROVED);
+
+        let (d, l) = record_consent(&data, data.len(), FINISH_BY_OWNER, true, 700).unwrap();
+        assert_eq!(find_value(&d[..l], b"consent_owner"), Some(&b"700"[..]));
+        assert_eq!(check_consent(&d[..l]), ERR_NOT_APPROVED);
+        assert_eq!(
+            record_consent(&d[..l], l, FINISH_BY_OWNER, true, 701).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+
+        let (d, l) = record_consent(&d[..l], l, FINISH_BY_DESTINATION, true, 702).unwrap();
+        assert_eq!(check_consent(&d[..l]), SUCCESS);
+        // Consent is tracked apart from the notary approvals
+        assert_eq!(find_value(&d[..l], b"approval_count"), Some(&b"2"[..]));
+
+        let (d, l) = record_consent(&d[..l], l, FINISH_BY_OWNER, false, 703).unwrap();
+        assert_eq!(find_value(&d[..l], b"consent_owner"), None);
+        assert_eq!(check_consent(&d[..l]), ERR_NOT_APPROVED);
+    }
+
+    #[test]
+    fn consent_keys_are_reserved() {
+        let data = governed_data();
+        assert_eq!(check_key_mutable(&data, b"consent_owner"), Err(ERR_IMMUTABLE_KEY));
+        // Notaries can't vote the parties' protections away
+        let owner_hex = mock_account_hex(0xA0);
+        for (key, value) in [
+            (b"require_consent" as &[u8], b"owner" as &[u8]),
+            (b"notary_exclude", b"destination"),
+            (b"owner", &owner_hex),
+            (b"destination", &owner_hex),
+        ] {
+            let p = Proposal { id: 1, key, value };
+            assert_eq!(record_proposal(&data, data.len(), &p, 0, 1).err(), Some(ERR_IMMUTABLE_KEY), "{:?}", key);
+        }
     }
 
     // ═════════════════════════════════════════════════════════════
-    // HEX ENCODING/DECODING TESTS
+    // DESTINATION ACKNOWLEDGEMENT TESTS
     // ═════════════════════════════════════════════════════════════
 
     #[test]
-    fn hex_roundtrip() {
-        let original = [0xDE, 0xAD, 0xBE, 0xEF];
-        let mut hex = [0u8; 8];
-        let hex_len = encode_hex(&original, &mut hex).unwrap();
-        assert_eq!(&hex[..hex_len], b"deadbeef");
-
-        let mut decoded = [0u8; 4];
-        let dec_len = decode_hex(&hex[..hex_len], &mut decoded).unwrap();
-        assert_eq!(&decoded[..dec_len], &original);
+    fn destination_ack_not_required_by_default() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        assert_eq!(check_destination_ack(&data), SUCCESS);
+        let dest = mock_account(0xB0);
+        assert_eq!(
+            record_destination_ack(&data, data.len(), &dest, &dest, 1, 100).map(|_| ()),
+            Err(ERR_BAD_CONFIG)
+        );
     }
 
     #[test]
-    fn hex_decode_uppercase() {
-        let mut out = [0u8; 2];
-        assert_eq!(decode_hex(b"FF", &mut out), Some(1));  // "FF" = 1 byte
-        assert_eq!(out[0], 0xFF);
+    fn destination_ack_gates_release() {
+        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+        let data = [data, b";approval_0=1;approval_1=1;approval_count=2;require_destination_ack=1".to_vec()].concat();
+        let dest = mock_account(0xB0);
+        assert_eq!(check_tiered_threshold(&data, None, 0), SUCCESS);
+        assert_eq!(check_destination_ack(&data), ERR_DESTINATION_ACK);
+
+        let (d, l) = record_destination_ack(&data, data.len(), &dest, &dest, 77, 500).unwrap();
+        let acked = &d[..l];
+        assert_eq!(check_destination_ack(acked), SUCCESS);
+        assert_eq!(find_value(acked, b"dest_ack"), Some(&mock_account_hex(0xB0)[..]));
+        assert_eq!(find_value(acked, b"dest_ack_seq"), Some(&b"77"[..]));
+        assert_eq!(find_value(acked, b"dest_ack_ledger"), Some(&b"500"[..]));
+        // Tracked apart from notary approvals
+        assert_eq!(find_value(acked, b"approval_count"), Some(&b"2"[..]));
     }
 
     #[test]
-    fn hex_decode_invalid() {
-        let mut out = [0u8; 4];
-        assert_eq!(decode_hex(b"xyz", &mut out), None);    // odd length
-        assert_eq!(decode_hex(b"gg", &mut out), None);     // invalid chars
+    fn only_destination_acknowledges_once() {
+        let data = b"require_destination_ack=1".to_vec();
+        let dest = mock_account(0xB0);
+        assert_eq!(
+            record_destination_ack(&data, data.len(), &mock_account(0xA0), &dest, 1, 100).map(|_| ()),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+        let (d, l) = record_destination_ack(&data, data.len(), &dest, &dest, 1, 100).unwrap();
+        assert_eq!(
+            record_destination_ack(&d[..l], l, &dest, &dest, 2, 101).map(|_| ()),
+            Err(ERR_ALREADY_APPROVED)
+        );
+        assert_eq!(check_destination_ack(b"require_destination_ack=yes"), ERR_BAD_CONFIG);
     }
 
     #[test]
-    fn hex_encode_empty() {
-        let mut out = [0u8; 0];
-        assert_eq!(encode_hex(&[], &mut out), Some(0));
+    fn destination_ack_has_its_own_audit_result() {
+        let data = b"require_destination_ack=1";
+        let (d, l) = record_audit(data, data.len(), ERR_DESTINATION_ACK, 9);
+        assert_eq!(find_value(&d[..l], b"last_result"), Some(&b"destination_unacked"[..]));
+        assert_eq!(check_key_mutable(data, b"dest_ack"), Err(ERR_IMMUTABLE_KEY));
     }
 
     #[test]
-    fn hex_encode_buffer_too_small() {
-        let mut out = [0u8; 2]; // need 4 for 2 bytes
-        assert_eq!(encode_hex(&[0xAB, 0xCD], &mut out), None);
+    fn destination_ack_requirement_is_not_votable() {
+        let mut data = governed_data();
+        data.extend_from_slice(b";require_destination_ack=1");
+        let p = proposal(1, b"require_destination_ack", b"0");
+        assert_eq!(record_proposal(&data, data.len(), &p, 0, 1).err(), Some(ERR_IMMUTABLE_KEY));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // format_u32 TESTS
+    // PRICE ORACLE CONDITION TESTS (XLS-47)
     // ═════════════════════════════════════════════════════════════
 
-    #[test]
-    fn format_u32_values() {
-        let mut buf = [0u8; 10];
+    /// Oracle block: release when XRP/USD >= 0.75, at most 300 seconds old.
+    fn oracle_data(cmp: &[u8]) -> Vec<u8> {
+        let (mut data, _) = single_notary_data(0x01);
+        data.extend_from_slice(b";oracle_owner=");
+        data.extend_from_slice(&mock_account_hex(0x0C));
+        data.extend_from_slice(b";oracle_document=7;oracle_base=XRP;oracle_quote=USD;oracle_cmp=");
+        data.extend_from_slice(cmp);
+        data.extend_from_slice(b";oracle_price=0.75;oracle_max_age=300");
+        data
+    }
 
-        let len = format_u32(0, &mut buf);
-        assert_eq!(&buf[..len], b"0");
+    fn oracle_condition(cmp: &[u8]) -> OracleCondition {
+        parse_oracle_condition(&oracle_data(cmp)).unwrap().unwrap()
+    }
 
-        let len = format_u32(1, &mut buf);
-        assert_eq!(&buf[..len], b"1");
+    const NOW: u32 = 1_760_000_000;
 
-        let len = format_u32(42, &mut buf);
-        assert_eq!(&buf[..len], b"42");
+    fn reading(asset_price: u64, scale: u8) -> Option<OraclePrice> {
+        Some(OraclePrice { asset_price, scale, last_update_time: NOW - 60 })
+    }
 
-        let len = format_u32(1000, &mut buf);
-        assert_eq!(&buf[..len], b"1000");
+    #[test]
+    fn currency_codes_parse() {
+        assert_eq!(parse_currency(b"XRP"), Some([0u8; CURRENCY_SIZE]));
 
-        let len = format_u32(4294967295, &mut buf);
-        assert_eq!(&buf[..len], b"4294967295");
-    }
+        let usd = parse_currency(b"USD").unwrap();
+        assert_eq!(&usd[12..15], b"USD");
+        assert!(usd[..12].iter().chain(usd[15..].iter()).all(|&b| b == 0));
 
-    // ═════════════════════════════════════════════════════════════
-    // build_indexed_key TESTS
-    // ═════════════════════════════════════════════════════════════
+        let hex = b"0158415500000000c1f76ff6ecb0bac600000000";
+        let custom = parse_currency(hex).unwrap();
+        assert_eq!(custom[0], 0x01);
+        assert_eq!(custom[15], 0xc6);
+
+        assert_eq!(parse_currency(b"US"), None);
+        assert_eq!(parse_currency(b"0158"), None);
+    }
 
     #[test]
-    fn indexed_key_builds_correctly() {
-        let mut buf = [0u8; 16];
-        let len = build_indexed_key(b"notary_", 0, &mut buf);
-        assert_eq!(&buf[..len], b"notary_0");
+    fn decimal_parses() {
+        assert_eq!(parse_decimal(b"0.75"), Some((75, 2)));
+        assert_eq!(parse_decimal(b"12.345"), Some((12345, 3)));
+        assert_eq!(parse_decimal(b"42"), Some((42, 0)));
+        assert_eq!(parse_decimal(b"1.000000000000000001"), Some((1_000_000_000_000_000_001, 18)));
+        assert_eq!(parse_decimal(b"1.0000000000000000001"), None); // scale > 18
+        assert_eq!(parse_decimal(b".5"), None);
+        assert_eq!(parse_decimal(b"5."), None);
+        assert_eq!(parse_decimal(b"1.2.3"), None);
+        assert_eq!(parse_decimal(b"-1"), None);
+        assert_eq!(parse_decimal(b""), None);
+    }
 
-        let len = build_indexed_key(b"approval_", 3, &mut buf);
-        assert_eq!(&buf[..len], b"approval_3");
+    #[test]
+    fn decimal_comparison_across_scales() {
+        use core::cmp::Ordering;
+        assert_eq!(compare_decimal((75, 2), (750, 3)), Ordering::Equal);
+        assert_eq!(compare_decimal((7501, 4), (75, 2)), Ordering::Greater);
+        assert_eq!(compare_decimal((7, 1), (75, 2)), Ordering::Less);
+        assert_eq!(compare_decimal((u64::MAX, 0), (u64::MAX, 18)), Ordering::Greater);
     }
 
-    // ═════════════════════════════════════════════════════════════
-    // CALLER AUTHORIZATION TESTS (Security Fix #1, #2, #3)
-    // ═════════════════════════════════════════════════════════════
+    #[test]
+    fn oracle_block_absent_is_none() {
+        let (data, _) = single_notary_data(0x01);
+        assert_eq!(parse_oracle_condition(&data), Ok(None));
+    }
 
     #[test]
-    fn single_notary_authorized() {
-        // Authorized notary should be recognized
-        let (data, account) = single_notary_data(0x01);
-        assert_eq!(check_caller_is_notary(&data, &account), Ok(0));
+    fn oracle_block_parses() {
+        let condition = oracle_condition(b"ge");
+        assert_eq!(condition.owner, mock_account(0x0C));
+        assert_eq!(condition.document_id, 7);
+        assert_eq!(condition.base, [0u8; CURRENCY_SIZE]);
+        assert_eq!(&condition.quote[12..15], b"USD");
+        assert_eq!(condition.comparison, PriceComparison::GreaterOrEqual);
+        assert_eq!((condition.price_mantissa, condition.price_scale), (75, 2));
+        assert_eq!(condition.max_age, 300);
     }
 
     #[test]
-    fn single_notary_unauthorized() {
-        // Random account should be rejected
-        let (data, _) = single_notary_data(0x01);
-        let impostor = mock_account(0xFF);
-        assert_eq!(check_caller_is_notary(&data, &impostor), Err(ERR_WRONG_ACCOUNT));
+    fn oracle_block_incomplete_is_bad_config() {
+        assert_eq!(parse_oracle_condition(&oracle_data(b"eq")), Err(ERR_BAD_CONFIG));
+
+        let data = b"oracle_owner=0c000000000000000000000000000000000000c0;oracle_document=7";
+        assert_eq!(parse_oracle_condition(data), Err(ERR_BAD_CONFIG));
+
+        let data = b"oracle_owner=abcd";
+        assert_eq!(parse_oracle_condition(data), Err(ERR_BAD_CONFIG));
     }
 
     #[test]
-    fn multi_notary_all_recognized() {
-        // All three notaries should be recognized with correct indices
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
-        assert_eq!(check_caller_is_notary(&data, &accounts[0]), Ok(0));
-        assert_eq!(check_caller_is_notary(&data, &accounts[1]), Ok(1));
-        assert_eq!(check_caller_is_notary(&data, &accounts[2]), Ok(2));
+    fn oracle_greater_or_equal() {
+        let condition = oracle_condition(b"ge");
+        assert_eq!(check_oracle_condition(&condition, reading(75, 2), NOW), SUCCESS);
+        assert_eq!(check_oracle_condition(&condition, reading(80, 2), NOW), SUCCESS);
+        assert_eq!(check_oracle_condition(&condition, reading(7499, 4), NOW), ERR_PRICE_CONDITION);
     }
 
     #[test]
-    fn multi_notary_impostor_rejected() {
-        // Account not in the notary list should be rejected
-        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
-        let impostor = mock_account(0x99);
-        assert_eq!(check_caller_is_notary(&data, &impostor), Err(ERR_WRONG_ACCOUNT));
+    fn oracle_strict_comparisons() {
+        let gt = oracle_condition(b"gt");
+        assert_eq!(check_oracle_condition(&gt, reading(75, 2), NOW), ERR_PRICE_CONDITION);
+        assert_eq!(check_oracle_condition(&gt, reading(751, 3), NOW), SUCCESS);
+
+        let lt = oracle_condition(b"lt");
+        assert_eq!(check_oracle_condition(&lt, reading(75, 2), NOW), ERR_PRICE_CONDITION);
+        assert_eq!(check_oracle_condition(&lt, reading(749, 3), NOW), SUCCESS);
+
+        let le = oracle_condition(b"le");
+        assert_eq!(check_oracle_condition(&le, reading(75, 2), NOW), SUCCESS);
+        assert_eq!(check_oracle_condition(&le, reading(1, 0), NOW), ERR_PRICE_CONDITION);
     }
 
     #[test]
-    fn notary_check_no_config() {
-        // Missing notary_count in data should return BAD_CONFIG
-        let data = b"threshold=1";
-        let account = mock_account(0x01);
-        assert_eq!(check_caller_is_notary(data, &account), Err(ERR_BAD_CONFIG));
+    fn oracle_stale_price_denied() {
+        let condition = oracle_condition(b"ge");
+        let fresh = OraclePrice { asset_price: 1, scale: 0, last_update_time: NOW - 300 };
+        let stale = OraclePrice { asset_price: 1, scale: 0, last_update_time: NOW - 301 };
+        assert_eq!(check_oracle_condition(&condition, Some(fresh), NOW), SUCCESS);
+        assert_eq!(check_oracle_condition(&condition, Some(stale), NOW), ERR_ORACLE_STALE);
     }
 
     #[test]
-    fn notary_check_zero_count() {
-        // Zero notaries is invalid config
-        let data = b"notary_count=0;threshold=1";
-        let account = mock_account(0x01);
-        assert_eq!(check_caller_is_notary(data, &account), Err(ERR_BAD_CONFIG));
+    fn oracle_future_timestamp_not_stale() {
+        let condition = oracle_condition(b"ge");
+        let ahead = OraclePrice { asset_price: 1, scale: 0, last_update_time: NOW + 5 };
+        assert_eq!(check_oracle_condition(&condition, Some(ahead), NOW), SUCCESS);
     }
 
     #[test]
-    fn notary_check_count_exceeds_max() {
-        // More than MAX_NOTARIES is invalid
-        let data = b"notary_count=9;threshold=1";
-        let account = mock_account(0x01);
-        assert_eq!(check_caller_is_notary(data, &account), Err(ERR_BAD_CONFIG));
+    fn oracle_missing_price_denied() {
+        let condition = oracle_condition(b"ge");
+        assert_eq!(check_oracle_condition(&condition, None, NOW), ERR_PRICE_CONDITION);
+        // Out-of-range scale from a malformed object is treated as no price
+        assert_eq!(check_oracle_condition(&condition, reading(1, 19), NOW), ERR_PRICE_CONDITION);
     }
 
     #[test]
-    fn notary_check_similar_accounts() {
-        // Two accounts that differ by one byte should not cross-match
-        let (data, account) = single_notary_data(0x01);
-        let mut similar = account;
-        similar[10] = 0xFF; // change one byte in the middle
-        assert_eq!(check_caller_is_notary(&data, &similar), Err(ERR_WRONG_ACCOUNT));
+    fn audit_records_oracle_denials() {
+        let data = b"threshold=1";
+        let (audit, len) = record_audit(data, data.len(), ERR_PRICE_CONDITION, 5);
+        assert_eq!(find_value(&audit[..len], b"last_result"), Some(b"price_condition" as &[u8]));
+        let (audit, len) = record_audit(data, data.len(), ERR_ORACLE_STALE, 6);
+        assert_eq!(find_value(&audit[..len], b"last_result"), Some(b"oracle_stale" as &[u8]));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // APPROVAL THRESHOLD TESTS (Security Fix #2)
+    // CREDENTIAL CONDITION TESTS (XLS-70)
     // ═════════════════════════════════════════════════════════════
 
-    #[test]
-    fn threshold_met_exactly() {
-        // 2 approvals with threshold=2 should pass
-        let data = b"threshold=2;approval_count=2";
-        assert_eq!(check_approval_threshold(data), SUCCESS);
+    /// Credential block: issuer 0x1C, type "KYC" (hex 4b5943).
+    fn credential_data(extra: &[u8]) -> Vec<u8> {
+        let (mut data, _) = single_notary_data(0x01);
+        data.extend_from_slice(b";credential_issuer=");
+        data.extend_from_slice(&mock_account_hex(0x1C));
+        data.extend_from_slice(b";credential_type=4b5943");
+        data.extend_from_slice(extra);
+        data
     }
 
     #[test]
-    fn threshold_exceeded() {
-        // 3 approvals with threshold=2 should still pass
-        let data = b"threshold=2;approval_count=3";
-        assert_eq!(check_approval_threshold(data), SUCCESS);
+    fn credential_block_absent_is_none() {
+        let (data, _) = single_notary_data(0x01);
+        assert_eq!(parse_credential_condition(&data), Ok(None));
     }
 
     #[test]
-    fn threshold_not_met() {
-        // 1 approval with threshold=2 should fail
-        let data = b"threshold=2;approval_count=1";
-        assert_eq!(check_approval_threshold(data), ERR_NOT_APPROVED);
+    fn credential_block_parses() {
+        let condition = parse_credential_condition(&credential_data(b"")).unwrap().unwrap();
+        assert_eq!(condition.issuer, mock_account(0x1C));
+        assert_eq!(condition.credential_type(), b"KYC");
+        assert!(!condition.require_notary);
+
+        let condition = parse_credential_condition(&credential_data(b";credential_notary=1"))
+            .unwrap()
+            .unwrap();
+        assert!(condition.require_notary);
     }
 
     #[test]
-    fn threshold_zero_approvals() {
-        // No approvals at all
-        let data = b"threshold=2";
-        assert_eq!(check_approval_threshold(data), ERR_NOT_APPROVED);
+    fn credential_block_invalid_is_bad_config() {
+        // Missing type
+        let mut data = b"credential_issuer=".to_vec();
+        data.extend_from_slice(&mock_account_hex(0x1C));
+        assert_eq!(parse_credential_condition(&data), Err(ERR_BAD_CONFIG));
+
+        // Empty, odd-length and over-long types
+        let mut empty = data.clone();
+        empty.extend_from_slice(b";credential_type=");
+        assert_eq!(parse_credential_condition(&empty), Err(ERR_BAD_CONFIG));
+        let mut odd = data.clone();
+        odd.extend_from_slice(b";credential_type=4b5");
+        assert_eq!(parse_credential_condition(&odd), Err(ERR_BAD_CONFIG));
+        let mut long = data.clone();
+        long.extend_from_slice(b";credential_type=");
+        long.extend_from_slice(&[b'a'; MAX_CREDENTIAL_TYPE_SIZE * 2 + 2]);
+        assert_eq!(parse_credential_condition(&long), Err(ERR_BAD_CONFIG));
+
+        // Bad flag value and short issuer
+        assert_eq!(
+            parse_credential_condition(&credential_data(b";credential_notary=yes")),
+            Err(ERR_BAD_CONFIG)
+        );
+        assert_eq!(
+            parse_credential_condition(b"credential_issuer=1c;credential_type=4b5943"),
+            Err(ERR_BAD_CONFIG)
+        );
     }
 
     #[test]
-    fn threshold_of_one() {
-        // Single approval needed and met
-        let data = b"threshold=1;approval_count=1";
-        assert_eq!(check_approval_threshold(data), SUCCESS);
+    fn credential_accepted_without_expiry() {
+        let state = CredentialState { flags: LSF_ACCEPTED, expiration: None };
+        assert_eq!(check_credential(Some(state), 800_000_000), SUCCESS);
     }
 
     #[test]
-    fn threshold_missing_config() {
-        // No threshold in data = bad config
-        let data = b"approval_count=5";
-        assert_eq!(check_approval_threshold(data), ERR_BAD_CONFIG);
+    fn credential_missing_denied() {
+        assert_eq!(check_credential(None, 800_000_000), ERR_NO_CREDENTIAL);
     }
 
-    // ═════════════════════════════════════════════════════════════
-    // TIME-LOCK TESTS (Security Fix #4)
-    // ═════════════════════════════════════════════════════════════
+    #[test]
+    fn credential_not_accepted_denied() {
+        let state = CredentialState { flags: 0, expiration: None };
+        assert_eq!(check_credential(Some(state), 800_000_000), ERR_NO_CREDENTIAL);
+    }
 
     #[test]
-    fn time_lock_with_finish_after() {
-        // FinishAfter is set — protocol enforced it, so we pass
-        assert_eq!(check_time_lock(Some(781364800)), SUCCESS);
+    fn credential_expiry_boundary() {
+        let state = CredentialState { flags: LSF_ACCEPTED, expiration: Some(800_000_000) };
+        assert_eq!(check_credential(Some(state), 799_999_999), SUCCESS);
+        assert_eq!(check_credential(Some(state), 800_000_000), SUCCESS);
+        assert_eq!(check_credential(Some(state), 800_000_001), ERR_NO_CREDENTIAL);
     }
 
     #[test]
-    fn time_lock_without_finish_after() {
-        // No FinishAfter — no time-lock, still passes
-        assert_eq!(check_time_lock(None), SUCCESS);
+    fn audit_records_missing_credential() {
+        let data = b"threshold=1";
+        let (audit, len) = record_audit(data, data.len(), ERR_NO_CREDENTIAL, 8);
+        assert_eq!(find_value(&audit[..len], b"last_result"), Some(b"no_credential" as &[u8]));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // APPROVAL RECORDING TESTS (Security Fix #5, #7)
+    // HASH-LOCK TESTS (atomic swaps)
     // ═════════════════════════════════════════════════════════════
 
-    #[test]
-    fn record_first_approval() {
-        // First notary approves — approval_count goes from 0 to 1
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
-        let (new_data, new_len) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let new_slice = &new_data[..new_len];
+    const PREIMAGE: &[u8] = b"secret-preimage-for-atomic-swap!";
+    const PREIMAGE_DIGEST: &[u8] = b"4ac93cda213c93082e3be61f4bc3740ccc1a6325ebfa1ef5c0da718a9366bbc2";
 
-        // Verify approval_0=1 is present
-        assert_eq!(find_value(new_slice, b"approval_0"), Some(b"1" as &[u8]));
-        // Verify count incremented
-        assert_eq!(find_value(new_slice, b"approval_count"), Some(b"1" as &[u8]));
-        // Verify notary config is preserved
-        assert_eq!(find_value(new_slice, b"notary_count"), Some(b"3" as &[u8]));
-        assert_eq!(find_value(new_slice, b"threshold"), Some(b"2" as &[u8]));
+    fn hashlock(mode: &[u8]) -> Hashlock {
+        let mut data = b"hashlock=".to_vec();
+        data.extend_from_slice(PREIMAGE_DIGEST);
+        data.extend_from_slice(b";hashlock_mode=");
+        data.extend_from_slice(mode);
+        parse_hashlock(&data).unwrap().unwrap()
     }
 
     #[test]
-    fn record_second_approval_different_notary() {
-        // Second notary approves after first — count goes to 2
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+    fn hashlock_absent_is_none() {
+        assert_eq!(parse_hashlock(b"threshold=1"), Ok(None));
+    }
 
-        // First approval
-        let (data1, len1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        // Second approval (different notary)
-        let (data2, len2) = record_approval(&data1[..len1], len1, 1, &accounts[1], 101).unwrap();
-        let result = &data2[..len2];
+    #[test]
+    fn hashlock_mode_defaults_to_and() {
+        let mut data = b"hashlock=".to_vec();
+        data.extend_from_slice(PREIMAGE_DIGEST);
+        assert_eq!(parse_hashlock(&data).unwrap().unwrap().mode, HashlockMode::And);
+        assert_eq!(hashlock(b"or").mode, HashlockMode::Or);
+    }
 
-        assert_eq!(find_value(result, b"approval_0"), Some(b"1" as &[u8]));
-        assert_eq!(find_value(result, b"approval_1"), Some(b"1" as &[u8]));
-        assert_eq!(find_value(result, b"approval_count"), Some(b"2" as &[u8]));
+    #[test]
+    fn hashlock_invalid_config() {
+        assert_eq!(parse_hashlock(b"hashlock=abcd"), Err(ERR_BAD_CONFIG));
+        let mut data = b"hashlock=".to_vec();
+        data.extend_from_slice(&[b'z'; 64]);
+        assert_eq!(parse_hashlock(&data), Err(ERR_BAD_CONFIG));
+
+        let mut data = b"hashlock=".to_vec();
+        data.extend_from_slice(PREIMAGE_DIGEST);
+        data.extend_from_slice(b";hashlock_mode=xor");
+        assert_eq!(parse_hashlock(&data), Err(ERR_BAD_CONFIG));
     }
 
     #[test]
-    fn record_duplicate_approval_rejected() {
-        // Same notary trying to approve twice should fail
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+    fn preimage_matches_digest() {
+        let lock = hashlock(b"and");
+        assert_eq!(check_preimage(&lock, Some(PREIMAGE)), SUCCESS);
+        assert_eq!(check_preimage(&lock, Some(b"wrong-preimage")), ERR_BAD_PREIMAGE);
+        assert_eq!(check_preimage(&lock, None), ERR_BAD_PREIMAGE);
+    }
 
-        let (data1, len1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let result = record_approval(&data1[..len1], len1, 0, &accounts[0], 101);
+    #[test]
+    fn preimage_size_limits() {
+        // The published empty-string digest must not unlock with an empty memo
+        let mut data = b"hashlock=".to_vec();
+        data.extend_from_slice(b"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        let empty_lock = parse_hashlock(&data).unwrap().unwrap();
+        assert_eq!(check_preimage(&empty_lock, Some(b"")), ERR_BAD_PREIMAGE);
+
+        // Oversized preimage is rejected even if it would hash correctly
+        let oversized = [b'a'; MAX_PREIMAGE_SIZE + 1];
+        let mut data = b"hashlock=".to_vec();
+        let mut hex = [0u8; 64];
+        encode_hex(&sha256::sha256(&oversized), &mut hex).unwrap();
+        data.extend_from_slice(&hex);
+        let big_lock = parse_hashlock(&data).unwrap().unwrap();
+        assert_eq!(check_preimage(&big_lock, Some(&oversized)), ERR_BAD_PREIMAGE);
 
-        assert_eq!(result, Err(ERR_ALREADY_APPROVED));
+        // Exactly MAX_PREIMAGE_SIZE is fine
+        let max = [b'a'; MAX_PREIMAGE_SIZE];
+        let mut data = b"hashlock=".to_vec();
+        encode_hex(&sha256::sha256(&max), &mut hex).unwrap();
+        data.extend_from_slice(&hex);
+        let max_lock = parse_hashlock(&data).unwrap().unwrap();
+        assert_eq!(check_preimage(&max_lock, Some(&max)), SUCCESS);
     }
 
     #[test]
-    fn record_all_three_approvals() {
-        // All three notaries approve — threshold easily met
+    fn hashlock_and_requires_both() {
+        let lock = hashlock(b"and");
+        assert_eq!(check_hashlock(&lock, Some(PREIMAGE), SUCCESS), SUCCESS);
+        assert_eq!(check_hashlock(&lock, Some(PREIMAGE), ERR_NOT_APPROVED), ERR_NOT_APPROVED);
+        assert_eq!(check_hashlock(&lock, None, SUCCESS), ERR_BAD_PREIMAGE);
+        assert_eq!(check_hashlock(&lock, Some(b"nope"), SUCCESS), ERR_BAD_PREIMAGE);
+    }
+
+    #[test]
+    fn hashlock_or_accepts_either() {
+        let lock = hashlock(b"or");
+        // Preimage alone releases (counterparty revealed it on the other chain)
+        assert_eq!(check_hashlock(&lock, Some(PREIMAGE), ERR_NOT_APPROVED), SUCCESS);
+        // Threshold alone releases
+        assert_eq!(check_hashlock(&lock, None, SUCCESS), SUCCESS);
+        // Neither: report why
+        assert_eq!(check_hashlock(&lock, None, ERR_NOT_APPROVED), ERR_NOT_APPROVED);
+        assert_eq!(check_hashlock(&lock, Some(b"nope"), ERR_NOT_APPROVED), ERR_BAD_PREIMAGE);
+    }
+
+    #[test]
+    fn hashlock_or_with_2_of_3() {
+        // preimage OR 2-of-3, driven through the real threshold check
+        let lock = hashlock(b"or");
         let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        assert_eq!(check_hashlock(&lock, None, check_approval_threshold(&d1[..l1])), ERR_NOT_APPROVED);
+        assert_eq!(check_hashlock(&lock, Some(PREIMAGE), check_approval_threshold(&d1[..l1])), SUCCESS);
 
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101).unwrap();
-        let (d3, l3) = record_approval(&d2[..l2], l2, 2, &accounts[2], 102).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 1000, 2).unwrap();
+        assert_eq!(check_hashlock(&lock, None, check_approval_threshold(&d2[..l2])), SUCCESS);
+    }
 
-        let result = &d3[..l3];
-        assert_eq!(find_value(result, b"approval_count"), Some(b"3" as &[u8]));
-        assert_eq!(check_approval_threshold(result), SUCCESS);
+    #[test]
+    fn audit_records_bad_preimage() {
+        let data = b"threshold=1";
+        let (audit, len) = record_audit(data, data.len(), ERR_BAD_PREIMAGE, 9);
+        assert_eq!(find_value(&audit[..len], b"last_result"), Some(b"bad_preimage" as &[u8]));
     }
 
     // ═════════════════════════════════════════════════════════════
-    // REVOCATION TESTS
+    // CROSS-ESCROW DEPENDENCY TESTS (depends_on)
     // ═════════════════════════════════════════════════════════════
 
     #[test]
-    fn revoke_existing_approval() {
-        // Approve then revoke — count should go back to 0
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
-
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        assert_eq!(find_value(&d1[..l1], b"approval_count"), Some(b"1" as &[u8]));
-
-        let (d2, l2) = record_revocation(&d1[..l1], l1, 0).unwrap();
-        assert_eq!(find_value(&d2[..l2], b"approval_0"), Some(b"0" as &[u8]));
-        assert_eq!(find_value(&d2[..l2], b"approval_count"), Some(b"0" as &[u8]));
+    fn dependency_absent_is_none() {
+        assert_eq!(parse_dependency(b"threshold=1"), Ok(None));
     }
 
     #[test]
-    fn revoke_then_reapprove() {
-        // Approve → revoke → approve again should work
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
-
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let (d2, l2) = record_revocation(&d1[..l1], l1, 0).unwrap();
-        // Should be able to approve again after revoking
-        let (d3, l3) = record_approval(&d2[..l2], l2, 0, &accounts[0], 102).unwrap();
-        assert_eq!(find_value(&d3[..l3], b"approval_0"), Some(b"1" as &[u8]));
-        assert_eq!(find_value(&d3[..l3], b"approval_count"), Some(b"1" as &[u8]));
+    fn dependency_parses_owner_and_sequence() {
+        let mut data = b"depends_on=".to_vec();
+        data.extend_from_slice(&mock_account_hex(0x42));
+        data.extend_from_slice(b":12345");
+        assert_eq!(
+            parse_dependency(&data),
+            Ok(Some(Dependency { owner: mock_account(0x42), sequence: 12345, missing_denied: false }))
+        );
     }
 
     #[test]
-    fn revoke_unapproved_notary() {
-        // Revoking when you haven't approved yet — count stays at 0
-        let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
+    fn dependency_invalid_is_bad_config() {
+        let mut no_seq = b"depends_on=".to_vec();
+        no_seq.extend_from_slice(&mock_account_hex(0x42));
+        assert_eq!(parse_dependency(&no_seq), Err(ERR_BAD_CONFIG));
 
-        let (d1, l1) = record_revocation(&data, data.len(), 0).unwrap();
-        assert_eq!(find_value(&d1[..l1], b"approval_0"), Some(b"0" as &[u8]));
-        assert_eq!(find_value(&d1[..l1], b"approval_count"), Some(b"0" as &[u8]));
+        let mut bad_seq = no_seq.clone();
+        bad_seq.extend_from_slice(b":abc");
+        assert_eq!(parse_dependency(&bad_seq), Err(ERR_BAD_CONFIG));
+
+        assert_eq!(parse_dependency(b"depends_on=abcd:1"), Err(ERR_BAD_CONFIG));
     }
 
     #[test]
-    fn partial_revoke_preserves_others() {
-        // Two notaries approve, one revokes — other approval preserved
-        let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
-
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101).unwrap();
-        assert_eq!(find_value(&d2[..l2], b"approval_count"), Some(b"2" as &[u8]));
-
-        // Notary 0 revokes
-        let (d3, l3) = record_revocation(&d2[..l2], l2, 0).unwrap();
-        assert_eq!(find_value(&d3[..l3], b"approval_0"), Some(b"0" as &[u8]));
-        assert_eq!(find_value(&d3[..l3], b"approval_1"), Some(b"1" as &[u8]));
-        assert_eq!(find_value(&d3[..l3], b"approval_count"), Some(b"1" as &[u8]));
+    fn dependency_keylet_matches_escrow_index() {
+        // rDx69ebzbowuqztksVDmZXjizTd12BVr4x, EscrowCreate sequence 84
+        let mut data = b"depends_on=8e29e6ef856e317e9ad1f7a7b1d8b44bd0884c9a:84".to_vec();
+        let dep = parse_dependency(&data).unwrap().unwrap();
+        let mut expected = [0u8; 32];
+        decode_hex(b"61E8E8ED53FA2CEBE192B23897071E9A75217BF5A410E9CB5B45AAB7AECA567A", &mut expected).unwrap();
+        assert_eq!(dep.keylet(), expected);
+
+        // Uppercase hex names the same escrow
+        data = b"depends_on=8E29E6EF856E317E9AD1F7A7B1D8B44BD0884C9A:84".to_vec();
+        assert_eq!(parse_dependency(&data).unwrap().unwrap().keylet(), expected);
     }
 
-    // ═════════════════════════════════════════════════════════════
-    // AUDIT TRAIL TESTS (Security Fix #5)
-    // ═════════════════════════════════════════════════════════════
+    const DEPENDENCY: Dependency = Dependency { owner: [0xb5; ACCOUNT_ID_SIZE], sequence: 1, missing_denied: false };
 
     #[test]
-    fn audit_records_denial() {
-        let data = b"threshold=2;approval_count=0";
-        let (audit, len) = record_audit(data, data.len(), ERR_NOT_APPROVED, 42);
-        let result = &audit[..len];
-
-        assert_eq!(find_value(result, b"last_result"), Some(b"not_approved" as &[u8]));
-        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"42" as &[u8]));
-        // Original data preserved
-        assert_eq!(find_value(result, b"threshold"), Some(b"2" as &[u8]));
+    fn dependency_satisfied_when_object_gone() {
+        // A finished escrow is deleted
+        assert_eq!(check_dependency(&DEPENDENCY, None), SUCCESS);
     }
 
     #[test]
-    fn audit_records_success() {
-        let data = b"threshold=1;approval_count=1";
-        let (audit, len) = record_audit(data, data.len(), SUCCESS, 999);
-        let result = &audit[..len];
-
-        assert_eq!(find_value(result, b"last_result"), Some(b"approved" as &[u8]));
-        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"999" as &[u8]));
+    fn dependency_strict_denies_missing_object() {
+        // Finished, cancelled or never existed look the same
+        let strict = Dependency { missing_denied: true, ..DEPENDENCY };
+        assert_eq!(check_dependency(&strict, None), ERR_DEPENDENCY);
+        assert_eq!(check_dependency(&strict, Some(b"last_result=approved")), SUCCESS);
     }
 
     #[test]
-    fn audit_records_wrong_account() {
-        let data = b"threshold=2";
-        let (audit, len) = record_audit(data, data.len(), ERR_WRONG_ACCOUNT, 1);
-        let result = &audit[..len];
-        assert_eq!(find_value(result, b"last_result"), Some(b"wrong_account" as &[u8]));
+    fn dependency_strict_is_opt_in() {
+        let mut data = b"depends_on=b5f762798a53d543a014caf8b297cff8f2f937e8:1".to_vec();
+        assert!(!parse_dependency(&data).unwrap().unwrap().missing_denied);
+        data.extend_from_slice(b";depends_on_missing=deny");
+        assert!(parse_dependency(&data).unwrap().unwrap().missing_denied);
+        data.extend_from_slice(b"x");
+        assert_eq!(parse_dependency(&data), Err(ERR_BAD_CONFIG));
     }
 
     #[test]
-    fn audit_overwrites_previous_audit() {
-        // First attempt denied
-        let data = b"threshold=2;approval_count=0";
-        let (d1, l1) = record_audit(data, data.len(), ERR_NOT_APPROVED, 10);
+    fn dependency_satisfied_by_approved_last_result() {
+        assert_eq!(check_dependency(&DEPENDENCY, Some(b"threshold=1;last_result=approved")), SUCCESS);
+    }
 
-        // Second attempt also denied — should overwrite first audit
-        let (d2, l2) = record_audit(&d1[..l1], l1, ERR_WRONG_ACCOUNT, 20);
-        let result = &d2[..l2];
+    #[test]
+    fn dependency_pending_while_escrow_open() {
+        assert_eq!(check_dependency(&DEPENDENCY, Some(b"threshold=1")), ERR_DEPENDENCY);
+        assert_eq!(check_dependency(&DEPENDENCY, Some(b"threshold=1;last_result=not_approved")), ERR_DEPENDENCY);
+        assert_eq!(check_dependency(&DEPENDENCY, Some(b"")), ERR_DEPENDENCY);
+    }
 
-        assert_eq!(find_value(result, b"last_result"), Some(b"wrong_account" as &[u8]));
-        assert_eq!(find_value(result, b"last_attempt_seq"), Some(b"20" as &[u8]));
+    #[test]
+    fn audit_records_pending_dependency() {
+        let data = b"threshold=1";
+        let (audit, len) = record_audit(data, data.len(), ERR_DEPENDENCY, 3);
+        assert_eq!(find_value(&audit[..len], b"last_result"), Some(b"dependency_pending" as &[u8]));
     }
 
     // ═════════════════════════════════════════════════════════════
@@ -1333,11 +8055,11 @@ mod tests {
         assert_eq!(check_approval_threshold(&data), ERR_NOT_APPROVED);
 
         // Step 2: Notary 0 approves
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
         assert_eq!(check_approval_threshold(&d1[..l1]), ERR_NOT_APPROVED); // still only 1
 
         // Step 3: Notary 2 approves (skipping notary 1)
-        let (d2, l2) = record_approval(&d1[..l1], l1, 2, &accounts[2], 101).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 2, &accounts[2], 101, 1000, 2).unwrap();
         assert_eq!(check_approval_threshold(&d2[..l2]), SUCCESS); // 2-of-3 met!
 
         // Step 4: Verify all notary config is intact
@@ -1350,13 +8072,13 @@ mod tests {
         let (data, account) = single_notary_data(0xAB);
 
         // Notary is authorized
-        assert_eq!(check_caller_is_notary(&data, &account), Ok(0));
+        assert_eq!(check_caller_is_notary(&data, &account, 0), Ok(0));
 
         // No approvals yet
         assert_eq!(check_approval_threshold(&data), ERR_NOT_APPROVED);
 
         // Approve
-        let (d1, l1) = record_approval(&data, data.len(), 0, &account, 50).unwrap();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &account, 50, 1000, 1).unwrap();
         assert_eq!(check_approval_threshold(&d1[..l1]), SUCCESS);
     }
 
@@ -1365,16 +8087,16 @@ mod tests {
         let (data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
 
         // Notary 0 and 1 approve
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
-        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101).unwrap();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
+        let (d2, l2) = record_approval(&d1[..l1], l1, 1, &accounts[1], 101, 1000, 2).unwrap();
         assert_eq!(check_approval_threshold(&d2[..l2]), SUCCESS);
 
         // Notary 0 revokes — no longer at threshold
-        let (d3, l3) = record_revocation(&d2[..l2], l2, 0).unwrap();
+        let (d3, l3) = record_revocation(&d2[..l2], l2, 0, 1000, 2).unwrap();
         assert_eq!(check_approval_threshold(&d3[..l3]), ERR_NOT_APPROVED);
 
         // Notary 2 approves — back to threshold
-        let (d4, l4) = record_approval(&d3[..l3], l3, 2, &accounts[2], 103).unwrap();
+        let (d4, l4) = record_approval(&d3[..l3], l3, 2, &accounts[2], 103, 1000, 2).unwrap();
         assert_eq!(check_approval_threshold(&d4[..l4]), SUCCESS);
     }
 
@@ -1384,7 +8106,7 @@ mod tests {
         // they can't pass the check_caller_is_notary check
         let (data, _) = multi_notary_data([0x01, 0x02, 0x03]);
         let impostor = mock_account(0xFF);
-        assert_eq!(check_caller_is_notary(&data, &impostor), Err(ERR_WRONG_ACCOUNT));
+        assert_eq!(check_caller_is_notary(&data, &impostor, 0), Err(ERR_WRONG_ACCOUNT));
     }
 
     // ═════════════════════════════════════════════════════════════
@@ -1396,7 +8118,7 @@ mod tests {
         // Garbage data should fail with BAD_CONFIG, not panic
         let garbage = b"asdfghjkl;12345;no_equals_here";
         let account = mock_account(0x01);
-        assert_eq!(check_caller_is_notary(garbage, &account), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_caller_is_notary(garbage, &account, 0), Err(ERR_BAD_CONFIG));
         assert_eq!(check_approval_threshold(garbage), ERR_BAD_CONFIG);
     }
 
@@ -1404,7 +8126,7 @@ mod tests {
     fn data_with_only_semicolons() {
         let data = b";;;";
         let account = mock_account(0x01);
-        assert_eq!(check_caller_is_notary(data, &account), Err(ERR_BAD_CONFIG));
+        assert_eq!(check_caller_is_notary(data, &account, 0), Err(ERR_BAD_CONFIG));
     }
 
     #[test]
@@ -1415,7 +8137,7 @@ mod tests {
         data.extend_from_slice(&[b'a'; 1000]); // invalid but shouldn't panic
         let account = mock_account(0x01);
         // Should fail cleanly (hex won't match), not panic
-        assert_eq!(check_caller_is_notary(&data, &account), Err(ERR_WRONG_ACCOUNT));
+        assert_eq!(check_caller_is_notary(&data, &account, 0), Err(ERR_WRONG_ACCOUNT));
     }
 
     #[test]
@@ -1430,7 +8152,7 @@ mod tests {
     fn approval_count_cannot_go_negative() {
         // Revoking from 0 should stay at 0
         let data = b"notary_count=1;threshold=1;approval_count=0";
-        let (d, l) = record_revocation(data, data.len(), 0).unwrap();
+        let (d, l) = record_revocation(data, data.len(), 0, 1000, 1).unwrap();
         assert_eq!(find_value(&d[..l], b"approval_count"), Some(b"0" as &[u8]));
     }
 
@@ -1440,7 +8162,7 @@ mod tests {
         let (mut data, accounts) = multi_notary_data([0x01, 0x02, 0x03]);
         data.extend_from_slice(b";custom_field=hello;another=world");
 
-        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100).unwrap();
+        let (d1, l1) = record_approval(&data, data.len(), 0, &accounts[0], 100, 1000, 2).unwrap();
         let result = &d1[..l1];
 
         // Custom fields should still be there
@@ -0,0 +1,225 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/sha256.rs — SHA-256 (FIPS 180-4) for no_std WASM
+// ═══════════════════════════════════════════════════════════════════════
+//
+// The host exposes SHA-512Half for keylets, but hash-locks published on
+// other chains use plain SHA-256, so the contract carries its own.
+// Fixed-size state, no heap allocation.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Size of a SHA-256 digest in bytes
+pub(crate) const DIGEST_SIZE: usize = 32;
+
+/// Size of a SHA-256 message block in bytes
+const BLOCK_SIZE: usize = 64;
+
+/// Initial hash value H(0): first 32 bits of the fractional parts of the
+/// square roots of the first 8 primes.
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Round constants: first 32 bits of the fractional parts of the cube
+/// roots of the first 64 primes.
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Incremental SHA-256 hasher.
+pub(crate) struct Sha256 {
+    state: [u32; 8],
+    block: [u8; BLOCK_SIZE],
+    block_len: usize,
+    total_len: u64,
+}
+
+impl Sha256 {
+    pub(crate) fn new() -> Self {
+        Sha256 {
+            state: H0,
+            block: [0u8; BLOCK_SIZE],
+            block_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Feed more message bytes into the hash.
+    pub(crate) fn update(&mut self, mut input: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(input.len() as u64);
+
+        while !input.is_empty() {
+            let take = (BLOCK_SIZE - self.block_len).min(input.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+
+            if self.block_len == BLOCK_SIZE {
+                let block = self.block;
+                compress(&mut self.state, &block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    /// Apply padding and return the digest.
+    pub(crate) fn finalize(mut self) -> [u8; DIGEST_SIZE] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        // Append the 0x80 terminator, then zero-pad to 56 mod 64
+        self.block[self.block_len] = 0x80;
+        self.block_len += 1;
+        if self.block_len > BLOCK_SIZE - 8 {
+            self.block[self.block_len..].fill(0);
+            let block = self.block;
+            compress(&mut self.state, &block);
+            self.block_len = 0;
+        }
+        self.block[self.block_len..BLOCK_SIZE - 8].fill(0);
+
+        // Message length in bits, big-endian
+        self.block[BLOCK_SIZE - 8..].copy_from_slice(&bit_len.to_be_bytes());
+        let block = self.block;
+        compress(&mut self.state, &block);
+
+        let mut digest = [0u8; DIGEST_SIZE];
+        for (i, word) in self.state.iter().enumerate() {
+            digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+/// One-shot SHA-256 of `input`.
+pub(crate) fn sha256(input: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut hasher = Sha256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Process one 64-byte block.
+fn compress(state: &mut [u32; 8], block: &[u8; BLOCK_SIZE]) {
+    // Message schedule
+    let mut w = [0u32; 64];
+    for (i, chunk) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(add);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — FIPS 180-4 / NIST CAVP published test vectors
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+
+    fn digest_from_hex(hex: &[u8]) -> [u8; DIGEST_SIZE] {
+        let mut out = [0u8; DIGEST_SIZE];
+        assert_eq!(decode_hex(hex, &mut out), Some(DIGEST_SIZE));
+        out
+    }
+
+    #[test]
+    fn sha256_empty_message() {
+        assert_eq!(
+            sha256(b""),
+            digest_from_hex(b"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+    }
+
+    #[test]
+    fn sha256_abc() {
+        // FIPS 180-4 example: one-block message
+        assert_eq!(
+            sha256(b"abc"),
+            digest_from_hex(b"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+
+    #[test]
+    fn sha256_two_block_message() {
+        // FIPS 180-4 example: 448-bit message, padding spills into a second block
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            digest_from_hex(b"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        );
+    }
+
+    #[test]
+    fn sha256_896_bit_message() {
+        assert_eq!(
+            sha256(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"),
+            digest_from_hex(b"cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1")
+        );
+    }
+
+    #[test]
+    fn sha256_million_a() {
+        // FIPS 180-4 example: 1,000,000 repetitions of 'a', fed incrementally
+        let mut hasher = Sha256::new();
+        let chunk = [b'a'; 1000];
+        for _ in 0..1000 {
+            hasher.update(&chunk);
+        }
+        assert_eq!(
+            hasher.finalize(),
+            digest_from_hex(b"cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        );
+    }
+
+    #[test]
+    fn sha256_incremental_matches_one_shot() {
+        // Split points on both sides of the 55/56/64-byte padding boundaries
+        let message = [0x5au8; 130];
+        for split in [0, 1, 55, 56, 63, 64, 65, 129, 130] {
+            let mut hasher = Sha256::new();
+            hasher.update(&message[..split]);
+            hasher.update(&message[split..]);
+            assert_eq!(hasher.finalize(), sha256(&message), "split at {}", split);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/sha512.rs — SHA-512 / SHA-512Half (FIPS 180-4) for no_std WASM
+// ═══════════════════════════════════════════════════════════════════════
+//
+// The XRPL derives ledger object indexes (keylets) with SHA-512Half: the
+// first 32 bytes of a SHA-512 digest. Computing them in-contract lets
+// finish() address other ledger objects without a host round-trip, and
+// lets the derivation be tested natively against known ledger indexes.
+// ═══════════════════════════════════════════════════════════════════════
+
+/// Size of a SHA-512 digest in bytes
+pub(crate) const DIGEST_SIZE: usize = 64;
+
+/// Size of a SHA-512Half digest in bytes
+pub(crate) const HALF_DIGEST_SIZE: usize = 32;
+
+/// Size of a SHA-512 message block in bytes
+const BLOCK_SIZE: usize = 128;
+
+/// Initial hash value H(0): first 64 bits of the fractional parts of the
+/// square roots of the first 8 primes.
+const H0: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+/// Round constants: first 64 bits of the fractional parts of the cube
+/// roots of the first 80 primes.
+const K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// Incremental SHA-512 hasher.
+pub(crate) struct Sha512 {
+    state: [u64; 8],
+    block: [u8; BLOCK_SIZE],
+    block_len: usize,
+    total_len: u128,
+}
+
+impl Sha512 {
+    pub(crate) fn new() -> Self {
+        Sha512 {
+            state: H0,
+            block: [0u8; BLOCK_SIZE],
+            block_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Feed more message bytes into the hash.
+    pub(crate) fn update(&mut self, mut input: &[u8]) {
+        self.total_len = self.total_len.wrapping_add(input.len() as u128);
+
+        while !input.is_empty() {
+            let take = (BLOCK_SIZE - self.block_len).min(input.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+
+            if self.block_len == BLOCK_SIZE {
+                let block = self.block;
+                compress(&mut self.state, &block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    /// Apply padding and return the full 64-byte digest.
+    pub(crate) fn finalize(mut self) -> [u8; DIGEST_SIZE] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        // Append the 0x80 terminator, then zero-pad to 112 mod 128
+        self.block[self.block_len] = 0x80;
+        self.block_len += 1;
+        if self.block_len > BLOCK_SIZE - 16 {
+            self.block[self.block_len..].fill(0);
+            let block = self.block;
+            compress(&mut self.state, &block);
+            self.block_len = 0;
+        }
+        self.block[self.block_len..BLOCK_SIZE - 16].fill(0);
+
+        // Message length in bits as a 128-bit big-endian integer
+        self.block[BLOCK_SIZE - 16..].copy_from_slice(&bit_len.to_be_bytes());
+        let block = self.block;
+        compress(&mut self.state, &block);
+
+        let mut digest = [0u8; DIGEST_SIZE];
+        for (i, word) in self.state.iter().enumerate() {
+            digest[i * 8..i * 8 + 8].copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    /// Return the first half of the digest (the XRPL's SHA-512Half).
+    pub(crate) fn finalize_half(self) -> [u8; HALF_DIGEST_SIZE] {
+        let digest = self.finalize();
+        let mut half = [0u8; HALF_DIGEST_SIZE];
+        half.copy_from_slice(&digest[..HALF_DIGEST_SIZE]);
+        half
+    }
+}
+
+/// One-shot SHA-512 of `input`.
+pub(crate) fn sha512(input: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Process one 128-byte block.
+fn compress(state: &mut [u64; 8], block: &[u8; BLOCK_SIZE]) {
+    // Message schedule
+    let mut w = [0u64; 80];
+    for (i, chunk) in block.chunks_exact(8).enumerate() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        w[i] = u64::from_be_bytes(word);
+    }
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for i in 0..80 {
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(add);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — FIPS 180-4 / NIST CAVP published test vectors
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_hex;
+
+    fn digest_from_hex(hex: &[u8]) -> [u8; DIGEST_SIZE] {
+        let mut out = [0u8; DIGEST_SIZE];
+        assert_eq!(decode_hex(hex, &mut out), Some(DIGEST_SIZE));
+        out
+    }
+
+    #[test]
+    fn sha512_empty_message() {
+        assert_eq!(
+            sha512(b""),
+            digest_from_hex(b"cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e")
+        );
+    }
+
+    #[test]
+    fn sha512_abc() {
+        assert_eq!(
+            sha512(b"abc"),
+            digest_from_hex(b"ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f")
+        );
+    }
+
+    #[test]
+    fn sha512_two_block_message() {
+        // FIPS 180-4 example: 896-bit message, padding spills into a second block
+        assert_eq!(
+            sha512(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu"),
+            digest_from_hex(b"8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909")
+        );
+    }
+
+    #[test]
+    fn sha512_million_a() {
+        let mut hasher = Sha512::new();
+        let chunk = [b'a'; 1000];
+        for _ in 0..1000 {
+            hasher.update(&chunk);
+        }
+        assert_eq!(
+            hasher.finalize(),
+            digest_from_hex(b"e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b")
+        );
+    }
+
+    #[test]
+    fn sha512_half_is_prefix() {
+        let mut hasher = Sha512::new();
+        hasher.update(b"abc");
+        assert_eq!(&hasher.finalize_half()[..], &sha512(b"abc")[..HALF_DIGEST_SIZE]);
+    }
+
+    #[test]
+    fn sha512_incremental_matches_one_shot() {
+        // Split points on both sides of the 111/112/128-byte padding boundaries
+        let message = [0xa5u8; 260];
+        for split in [0, 1, 111, 112, 127, 128, 129, 259, 260] {
+            let mut hasher = Sha512::new();
+            hasher.update(&message[..split]);
+            hasher.update(&message[split..]);
+            assert_eq!(hasher.finalize(), sha512(&message), "split at {}", split);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/template.rs — Named policy templates
+// ═══════════════════════════════════════════════════════════════════════
+//
+// `template=<name>` plus a few parameters expands into the low-level keys
+// an operator would otherwise assemble by hand. Expansion runs first in
+// every entry point, before the dead-man switch, and the result is what
+// the contract writes back: `template` and its parameters are replaced by
+// the generated keys and `template_used=<name>`, so it happens once.
+//
+//   notarized_sale   notary=<hex>
+//       one notary; the notary or the destination submits the finish
+//   custody_2of3     notaries=<hex>,<hex>,<hex>
+//       2-of-3 notaries
+//   dead_man_switch  notaries=<hex>,...  backup_notaries=<hex>,...
+//                    inactivity_ledgers=<n>  created_ledger=<n>
+//       majority of the primary set; a majority of the backup set takes
+//       over after `inactivity_ledgers` without approval activity
+//   time_vesting     notary=<hex>  vest_ledger=<n>
+//       the destination (or the notary) finishes once `vest_ledger` is
+//       reached; the notary's approval releases early
+//
+// Every parameter is validated here, and a template refuses data that
+// already sets any key it would generate — mixing the two is exactly the
+// hand-assembly templates exist to avoid.
+// ═══════════════════════════════════════════════════════════════════════
+
+use crate::{
+    copy_entries_except, decode_hex, encode_hex, find_value, format_u32, parse_u32, write_entry,
+    write_separator, ACCOUNT_ID_SIZE, ERR_BAD_CONFIG, MAX_NOTARIES,
+};
+
+/// Template parameters that are consumed by expansion.
+const PARAMETER_KEYS: [&[u8]; 5] = [b"template", b"notary", b"notaries", b"backup_notaries", b"vest_ledger"];
+
+/// Parse a comma-separated list of 1..=MAX_NOTARIES 40-hex AccountIDs.
+fn parse_account_list(value: &[u8]) -> Result<([[u8; ACCOUNT_ID_SIZE]; MAX_NOTARIES], usize), i32> {
+    let mut accounts = [[0u8; ACCOUNT_ID_SIZE]; MAX_NOTARIES];
+    let mut count = 0;
+    for hex in value.split(|&b| b == b',') {
+        if count == MAX_NOTARIES
+            || hex.len() != ACCOUNT_ID_SIZE * 2
+            || decode_hex(hex, &mut accounts[count]) != Some(ACCOUNT_ID_SIZE)
+        {
+            return Err(ERR_BAD_CONFIG);
+        }
+        count += 1;
+    }
+    Ok((accounts, count))
+}
+
+/// A required template parameter.
+fn param<'a>(data: &'a [u8], key: &[u8]) -> Result<&'a [u8], i32> {
+    find_value(data, key).ok_or(ERR_BAD_CONFIG)
+}
+
+/// A required numeric parameter.
+fn param_u32(data: &[u8], key: &[u8]) -> Result<u32, i32> {
+    param(data, key).and_then(|v| parse_u32(v).ok_or(ERR_BAD_CONFIG))
+}
+
+/// Appends generated keys, refusing any the data already sets.
+struct Writer<'a> {
+    existing: &'a [u8],
+    out: [u8; 4096],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn entry(&mut self, key: &[u8], value: &[u8]) -> Result<(), i32> {
+        if find_value(self.existing, key).is_some() {
+            return Err(ERR_BAD_CONFIG);
+        }
+        if self.pos > 0 {
+            self.pos = write_separator(&mut self.out, self.pos);
+        }
+        self.pos = write_entry(&mut self.out, self.pos, key, value);
+        Ok(())
+    }
+
+    fn number(&mut self, key: &[u8], value: u32) -> Result<(), i32> {
+        let mut buf = [0u8; 10];
+        let len = format_u32(value, &mut buf);
+        self.entry(key, &buf[..len])
+    }
+
+    /// `<count_key>=N`, `<threshold_key>=T` and `<prefix>0..<prefix>N-1`.
+    /// Accounts are re-encoded as lowercase hex, the form notary lookups compare.
+    fn notary_set(
+        &mut self,
+        count_key: &[u8],
+        threshold_key: &[u8],
+        threshold: usize,
+        prefix: &[u8],
+        accounts: &[[u8; ACCOUNT_ID_SIZE]],
+    ) -> Result<(), i32> {
+        self.number(count_key, accounts.len() as u32)?;
+        self.number(threshold_key, threshold as u32)?;
+        for (i, account) in accounts.iter().enumerate() {
+            let mut key = [0u8; 24];
+            key[..prefix.len()].copy_from_slice(prefix);
+            key[prefix.len()] = b'0' + i as u8;
+            let mut hex = [0u8; ACCOUNT_ID_SIZE * 2];
+            encode_hex(account, &mut hex);
+            self.entry(&key[..prefix.len() + 1], &hex)?;
+        }
+        Ok(())
+    }
+}
+
+/// Majority of `count` notaries.
+fn majority(count: usize) -> usize {
+    count / 2 + 1
+}
+
+/// Expand a `template` key. Returns Ok(None) when the data has none.
+pub(crate) fn expand_template(
+    existing_data: &[u8],
+    existing_len: usize,
+) -> Result<Option<([u8; 4096], usize)>, i32> {
+    let data = &existing_data[..existing_len];
+    let name = match find_value(data, b"template") {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+
+    let mut writer = Writer { existing: data, out: [0u8; 4096], pos: 0 };
+    writer.pos = copy_entries_except(existing_data, existing_len, &mut writer.out, |k| PARAMETER_KEYS.contains(&k));
+
+    match name {
+        b"notarized_sale" => {
+            let notary = single_account(data)?;
+            writer.notary_set(b"notary_count", b"threshold", 1, b"notary_", &[notary])?;
+            writer.entry(b"finish_by", b"notary,destination")?;
+        }
+        b"custody_2of3" => {
+            let (notaries, count) = parse_account_list(param(data, b"notaries")?)?;
+            if count != 3 {
+                return Err(ERR_BAD_CONFIG);
+            }
+            writer.notary_set(b"notary_count", b"threshold", 2, b"notary_", &notaries[..count])?;
+        }
+        b"dead_man_switch" => {
+            let (primary, primary_count) = parse_account_list(param(data, b"notaries")?)?;
+            let (backup, backup_count) = parse_account_list(param(data, b"backup_notaries")?)?;
+            if param_u32(data, b"inactivity_ledgers")? == 0 {
+                return Err(ERR_BAD_CONFIG);
+            }
+            param_u32(data, b"created_ledger")?;
+            writer.notary_set(b"notary_count", b"threshold", majority(primary_count), b"notary_", &primary[..primary_count])?;
+            writer.notary_set(
+                b"backup_notary_count",
+                b"backup_threshold",
+                majority(backup_count),
+                b"backup_notary_",
+                &backup[..backup_count],
+            )?;
+        }
+        b"time_vesting" => {
+            let notary = single_account(data)?;
+            let vest_ledger = param_u32(data, b"vest_ledger")?;
+            writer.notary_set(b"notary_count", b"threshold", 1, b"notary_", &[notary])?;
+            writer.entry(b"finish_by", b"notary,destination")?;
+            let mut digits = [0u8; 10];
+            let digits_len = format_u32(vest_ledger, &mut digits);
+            let mut policy = [0u8; 40];
+            let mut len = 0;
+            for part in [&b"OR(ledger>="[..], &digits[..digits_len], b", threshold)"] {
+                policy[len..len + part.len()].copy_from_slice(part);
+                len += part.len();
+            }
+            writer.entry(b"policy", &policy[..len])?;
+        }
+        _ => return Err(ERR_BAD_CONFIG),
+    }
+
+    writer.entry(b"template_used", name)?;
+    Ok(Some((writer.out, writer.pos)))
+}
+
+/// The `notary` parameter: exactly one account.
+fn single_account(data: &[u8]) -> Result<[u8; ACCOUNT_ID_SIZE], i32> {
+    let (accounts, count) = parse_account_list(param(data, b"notary")?)?;
+    if count != 1 {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(accounts[0])
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_caller_is_notary, validate_backup_set, validate_config};
+
+    fn account(seed: u8) -> [u8; ACCOUNT_ID_SIZE] {
+        [seed; ACCOUNT_ID_SIZE]
+    }
+
+    fn hex(seed: u8) -> Vec<u8> {
+        let mut out = [0u8; ACCOUNT_ID_SIZE * 2];
+        encode_hex(&account(seed), &mut out);
+        out.to_vec()
+    }
+
+    fn expand(data: &[u8]) -> Result<Vec<u8>, i32> {
+        expand_template(data, data.len()).map(|r| {
+            let (d, l) = r.expect("template expanded");
+            d[..l].to_vec()
+        })
+    }
+
+    #[test]
+    fn no_template_is_untouched() {
+        assert_eq!(expand_template(b"notary_count=1", 14), Ok(None));
+    }
+
+    #[test]
+    fn notarized_sale_expands() {
+        let mut data = b"template=notarized_sale;notary=".to_vec();
+        data.extend_from_slice(&hex(0x01));
+        let out = expand(&data).unwrap();
+        assert_eq!(find_value(&out, b"notary_count"), Some(&b"1"[..]));
+        assert_eq!(find_value(&out, b"threshold"), Some(&b"1"[..]));
+        assert_eq!(find_value(&out, b"finish_by"), Some(&b"notary,destination"[..]));
+        assert_eq!(find_value(&out, b"template_used"), Some(&b"notarized_sale"[..]));
+        assert_eq!(check_caller_is_notary(&out, &account(0x01), 0), Ok(0));
+        // Consumed keys are gone, so expansion happens once
+        assert_eq!(find_value(&out, b"template"), None);
+        assert_eq!(find_value(&out, b"notary"), None);
+        assert_eq!(expand_template(&out, out.len()), Ok(None));
+    }
+
+    #[test]
+    fn custody_normalizes_hex_and_requires_three() {
+        let mut data = b"template=custody_2of3;notaries=".to_vec();
+        data.extend_from_slice(&hex(0xAB).to_ascii_uppercase());
+        data.push(b',');
+        data.extend_from_slice(&hex(0x02));
+        data.push(b',');
+        data.extend_from_slice(&hex(0x03));
+        let out = expand(&data).unwrap();
+        assert_eq!(find_value(&out, b"threshold"), Some(&b"2"[..]));
+        assert_eq!(check_caller_is_notary(&out, &account(0xAB), 0), Ok(0));
+        assert_eq!(check_caller_is_notary(&out, &account(0x03), 0), Ok(2));
+        assert_eq!(validate_config(&out), Ok(()));
+
+        let two = [b"template=custody_2of3;notaries=".to_vec(), hex(0x01), b",".to_vec(), hex(0x02)].concat();
+        assert_eq!(expand(&two), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn dead_man_switch_expands_both_sets() {
+        let data = [
+            b"template=dead_man_switch;inactivity_ledgers=1000;created_ledger=5000;notaries=".to_vec(),
+            hex(0x01),
+            b",".to_vec(),
+            hex(0x02),
+            b",".to_vec(),
+            hex(0x03),
+            b";backup_notaries=".to_vec(),
+            hex(0x11),
+            b",".to_vec(),
+            hex(0x12),
+        ]
+        .concat();
+        let out = expand(&data).unwrap();
+        assert_eq!(find_value(&out, b"threshold"), Some(&b"2"[..]));
+        assert_eq!(find_value(&out, b"backup_notary_count"), Some(&b"2"[..]));
+        assert_eq!(find_value(&out, b"backup_threshold"), Some(&b"2"[..]));
+        assert_eq!(find_value(&out, b"backup_notary_1"), Some(&hex(0x12)[..]));
+        assert_eq!(find_value(&out, b"inactivity_ledgers"), Some(&b"1000"[..]));
+        assert_eq!(validate_backup_set(&out), Ok(()));
+
+        // The switch parameters are required
+        let without_window = [b"template=dead_man_switch;created_ledger=5000;notaries=".to_vec(), hex(0x01), b";backup_notaries=".to_vec(), hex(0x11)].concat();
+        assert_eq!(expand(&without_window), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn time_vesting_generates_policy() {
+        let data = [b"template=time_vesting;vest_ledger=9000000;notary=".to_vec(), hex(0x01)].concat();
+        let out = expand(&data).unwrap();
+        assert_eq!(find_value(&out, b"policy"), Some(&b"OR(ledger>=9000000, threshold)"[..]));
+        assert!(crate::expr::parse_policy(find_value(&out, b"policy").unwrap()).is_ok());
+        assert_eq!(find_value(&out, b"vest_ledger"), None);
+
+        let bad = [b"template=time_vesting;vest_ledger=soon;notary=".to_vec(), hex(0x01)].concat();
+        assert_eq!(expand(&bad), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn rejects_unknown_templates_and_bad_parameters() {
+        assert_eq!(expand(b"template=escrow9000"), Err(ERR_BAD_CONFIG));
+        assert_eq!(expand(b"template=notarized_sale"), Err(ERR_BAD_CONFIG));
+        assert_eq!(expand(b"template=notarized_sale;notary=abcd"), Err(ERR_BAD_CONFIG));
+        let two = [b"template=notarized_sale;notary=".to_vec(), hex(0x01), b",".to_vec(), hex(0x02)].concat();
+        assert_eq!(expand(&two), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn rejects_hand_set_generated_keys() {
+        let data = [b"template=notarized_sale;threshold=2;notary=".to_vec(), hex(0x01)].concat();
+        assert_eq!(expand(&data), Err(ERR_BAD_CONFIG));
+    }
+}
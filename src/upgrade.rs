@@ -0,0 +1,464 @@
+// ═══════════════════════════════════════════════════════════════════════
+// src/upgrade.rs — Time-delayed code upgrade governance (XLS-101)
+// ═══════════════════════════════════════════════════════════════════════
+//
+// XLS-100 escrow code is immutable, but an XLS-101 smart contract can be
+// replaced with ContractModify. This module decides whether such a
+// replacement is authorized; it never touches the host. The contract
+// build exposes it as propose_upgrade, vote_upgrade, veto_upgrade and
+// apply_upgrade (see src/contract.rs); the escrow build has no upgrades.
+// The flow:
+//
+//   1. A notary proposes the hash of the new WASM. The proposer's vote
+//      counts and `upgrade_after_ledger` is set `upgrade_delay_ledgers`
+//      ahead (default 8640, ~12 hours).
+//   2. Other notaries vote for that exact hash.
+//   3. Until it is applied, a stakeholder (`upgrade_veto_by`, default
+//      owner,destination) can veto, which discards the proposal.
+//   4. ContractModify is authorized only for the pending hash, once
+//      `upgrade_threshold` votes are in and the delay has passed.
+//
+// Keys:
+//   upgrade_threshold     — notary votes required (config, required)
+//   upgrade_delay_ledgers — review window after proposal (config)
+//   upgrade_veto_by       — roles that may veto: owner, destination,
+//                           notary, any (config)
+//   pending_code_hash     — 64-hex hash of the proposed code
+//   upgrade_after_ledger  — first ledger the upgrade may be applied at
+//   upgrade_vote_N, upgrade_votes — votes for the pending hash
+//   active_code_hash      — hash of the last applied upgrade
+//   upgrade_last, upgrade_last_ledger — audit: proposed, voted, vetoed
+//                           or applied, and when
+//
+// All `upgrade_*` keys and the code hashes are closed to governance
+// proposals: the upgrade policy can only change by upgrading.
+// ═══════════════════════════════════════════════════════════════════════
+
+use crate::{
+    build_indexed_key, check_listed_notary, copy_entries_except, decode_hex, encode_hex,
+    find_value, format_u32, parse_finish_by, parse_u32, parse_u8_digit, write_entry,
+    write_separator, ACCOUNT_ID_SIZE, ERR_ALREADY_APPROVED, ERR_BAD_CONFIG, ERR_NOT_APPROVED,
+    ERR_TOO_EARLY, ERR_UPGRADE, ERR_WRONG_ACCOUNT, FINISH_BY_ANY, FINISH_BY_DESTINATION,
+    FINISH_BY_NOTARY, FINISH_BY_OWNER, MAX_NOTARIES, SUCCESS,
+};
+
+/// Size of a code hash in bytes
+pub(crate) const CODE_HASH_SIZE: usize = 32;
+
+/// Review window when `upgrade_delay_ledgers` is not set (~12 hours)
+const DEFAULT_UPGRADE_DELAY_LEDGERS: u32 = 8640;
+
+/// Parse a 64-char hex code hash.
+pub(crate) fn parse_code_hash(hex: &[u8]) -> Option<[u8; CODE_HASH_SIZE]> {
+    if hex.len() != CODE_HASH_SIZE * 2 {
+        return None;
+    }
+    let mut hash = [0u8; CODE_HASH_SIZE];
+    decode_hex(hex, &mut hash)?;
+    Some(hash)
+}
+
+/// Votes required to authorize an upgrade. Upgrades are off without
+/// `upgrade_threshold`, and it must be satisfiable by `notary_count`.
+fn upgrade_threshold(data: &[u8]) -> Result<u8, i32> {
+    let threshold = find_value(data, b"upgrade_threshold")
+        .and_then(parse_u8_digit)
+        .ok_or(ERR_BAD_CONFIG)?;
+    let count = find_value(data, b"notary_count")
+        .and_then(parse_u8_digit)
+        .ok_or(ERR_BAD_CONFIG)?;
+    if threshold == 0 || threshold > count || count as usize > MAX_NOTARIES {
+        return Err(ERR_BAD_CONFIG);
+    }
+    Ok(threshold)
+}
+
+/// The pending code hash, or ERR_UPGRADE if nothing is pending.
+fn pending_code_hash(data: &[u8]) -> Result<[u8; CODE_HASH_SIZE], i32> {
+    match find_value(data, b"pending_code_hash") {
+        Some(hex) => parse_code_hash(hex).ok_or(ERR_BAD_CONFIG),
+        None => Err(ERR_UPGRADE),
+    }
+}
+
+/// Is this a key of the pending proposal (dropped on veto or apply)?
+fn is_pending_key(key: &[u8]) -> bool {
+    key == b"pending_code_hash"
+        || key == b"upgrade_after_ledger"
+        || key == b"upgrade_votes"
+        || key.starts_with(b"upgrade_vote_")
+}
+
+/// Append upgrade_last=<action>;upgrade_last_ledger=<ledger>.
+fn write_upgrade_audit(data: &mut [u8], mut pos: usize, action: &[u8], current_ledger: u32) -> usize {
+    if pos > 0 {
+        pos = write_separator(data, pos);
+    }
+    pos = write_entry(data, pos, b"upgrade_last", action);
+    pos = write_separator(data, pos);
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger, &mut ledger_buf);
+    write_entry(data, pos, b"upgrade_last_ledger", &ledger_buf[..ledger_len])
+}
+
+/// Open an upgrade proposal for `code_hash`, counting the proposer's vote.
+/// Refused with ERR_UPGRADE while another upgrade is pending.
+pub(crate) fn record_upgrade_proposal(
+    existing_data: &[u8],
+    existing_len: usize,
+    code_hash: &[u8; CODE_HASH_SIZE],
+    proposer_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    upgrade_threshold(existing_data)?;
+    if find_value(existing_data, b"pending_code_hash").is_some() {
+        return Err(ERR_UPGRADE);
+    }
+    let delay = match find_value(existing_data, b"upgrade_delay_ledgers") {
+        Some(v) => parse_u32(v).ok_or(ERR_BAD_CONFIG)?,
+        None => DEFAULT_UPGRADE_DELAY_LEDGERS,
+    };
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        is_pending_key(k) || k.starts_with(b"upgrade_last")
+    });
+
+    let mut hash_hex = [0u8; CODE_HASH_SIZE * 2];
+    encode_hex(code_hash, &mut hash_hex);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"pending_code_hash", &hash_hex);
+
+    let mut ledger_buf = [0u8; 10];
+    let ledger_len = format_u32(current_ledger.saturating_add(delay), &mut ledger_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"upgrade_after_ledger", &ledger_buf[..ledger_len]);
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"upgrade_vote_", proposer_index, &mut vote_key_buf);
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, &vote_key_buf[..vote_key_len], b"1");
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"upgrade_votes", b"1");
+
+    pos = write_upgrade_audit(&mut new_data, pos, b"proposed", current_ledger);
+    Ok((new_data, pos))
+}
+
+/// Record a notary's vote for the pending upgrade. The vote names the hash
+/// so a notary can never end up backing code they didn't review.
+pub(crate) fn record_upgrade_vote(
+    existing_data: &[u8],
+    existing_len: usize,
+    code_hash: &[u8; CODE_HASH_SIZE],
+    voter_index: u8,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    if pending_code_hash(existing_data)? != *code_hash {
+        return Err(ERR_UPGRADE);
+    }
+
+    let mut vote_key_buf = [0u8; 16];
+    let vote_key_len = build_indexed_key(b"upgrade_vote_", voter_index, &mut vote_key_buf);
+    let vote_key = &vote_key_buf[..vote_key_len];
+    if find_value(existing_data, vote_key) == Some(b"1") {
+        return Err(ERR_ALREADY_APPROVED);
+    }
+    let votes = find_value(existing_data, b"upgrade_votes")
+        .and_then(parse_u8_digit)
+        .unwrap_or(0)
+        + 1;
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        k == vote_key || k == b"upgrade_votes" || k.starts_with(b"upgrade_last")
+    });
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, vote_key, b"1");
+    pos = write_separator(&mut new_data, pos);
+    pos = write_entry(&mut new_data, pos, b"upgrade_votes", &[b'0' + votes]);
+
+    pos = write_upgrade_audit(&mut new_data, pos, b"voted", current_ledger);
+    Ok((new_data, pos))
+}
+
+/// Verify that the caller may veto a pending upgrade.
+/// `owner` and `destination` are the escrow's Account and Destination fields.
+pub(crate) fn check_veto_submitter(
+    data: &[u8],
+    caller: &[u8; ACCOUNT_ID_SIZE],
+    owner: &[u8; ACCOUNT_ID_SIZE],
+    destination: &[u8; ACCOUNT_ID_SIZE],
+) -> Result<(), i32> {
+    let roles = match find_value(data, b"upgrade_veto_by") {
+        Some(value) => parse_finish_by(value).ok_or(ERR_BAD_CONFIG)?,
+        None => FINISH_BY_OWNER | FINISH_BY_DESTINATION,
+    };
+
+    if roles & FINISH_BY_ANY != 0
+        || (roles & FINISH_BY_DESTINATION != 0 && caller == destination)
+        || (roles & FINISH_BY_OWNER != 0 && caller == owner)
+    {
+        return Ok(());
+    }
+    if roles & FINISH_BY_NOTARY != 0 {
+        return check_listed_notary(data, caller).map(|_| ());
+    }
+
+    Err(ERR_WRONG_ACCOUNT)
+}
+
+/// Discard the pending upgrade. A veto is only possible while something
+/// is pending; the caller's right to veto is checked by check_veto_submitter.
+pub(crate) fn record_upgrade_veto(
+    existing_data: &[u8],
+    existing_len: usize,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    pending_code_hash(existing_data)?;
+
+    let mut new_data = [0u8; 4096];
+    let pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        is_pending_key(k) || k.starts_with(b"upgrade_last")
+    });
+    let pos = write_upgrade_audit(&mut new_data, pos, b"vetoed", current_ledger);
+    Ok((new_data, pos))
+}
+
+/// Decide whether ContractModify may install `code_hash` now.
+///
+/// Returns SUCCESS only for the pending hash, with `upgrade_threshold`
+/// votes and `upgrade_after_ledger` reached. ERR_UPGRADE if nothing (or
+/// something else) is pending, ERR_NOT_APPROVED without quorum and
+/// ERR_TOO_EARLY during the review window.
+pub(crate) fn check_upgrade_authorized(
+    data: &[u8],
+    code_hash: &[u8; CODE_HASH_SIZE],
+    current_ledger: u32,
+) -> i32 {
+    let threshold = match upgrade_threshold(data) {
+        Ok(t) => t,
+        Err(code) => return code,
+    };
+    match pending_code_hash(data) {
+        Ok(pending) if pending == *code_hash => {}
+        Ok(_) => return ERR_UPGRADE,
+        Err(code) => return code,
+    }
+
+    let votes = find_value(data, b"upgrade_votes")
+        .and_then(parse_u8_digit)
+        .unwrap_or(0);
+    if votes < threshold {
+        return ERR_NOT_APPROVED;
+    }
+
+    match find_value(data, b"upgrade_after_ledger").and_then(parse_u32) {
+        Some(after) if current_ledger >= after => SUCCESS,
+        Some(_) => ERR_TOO_EARLY,
+        None => ERR_BAD_CONFIG,
+    }
+}
+
+/// Record an authorized upgrade as applied: the pending proposal is
+/// cleared and its hash kept as `active_code_hash`.
+pub(crate) fn record_upgrade_applied(
+    existing_data: &[u8],
+    existing_len: usize,
+    current_ledger: u32,
+) -> Result<([u8; 4096], usize), i32> {
+    let code_hash = pending_code_hash(existing_data)?;
+    let result = check_upgrade_authorized(existing_data, &code_hash, current_ledger);
+    if result != SUCCESS {
+        return Err(result);
+    }
+
+    let mut new_data = [0u8; 4096];
+    let mut pos = copy_entries_except(existing_data, existing_len, &mut new_data, |k| {
+        is_pending_key(k) || k == b"active_code_hash" || k.starts_with(b"upgrade_last")
+    });
+
+    let mut hash_hex = [0u8; CODE_HASH_SIZE * 2];
+    encode_hex(&code_hash, &mut hash_hex);
+    if pos > 0 {
+        pos = write_separator(&mut new_data, pos);
+    }
+    pos = write_entry(&mut new_data, pos, b"active_code_hash", &hash_hex);
+
+    pos = write_upgrade_audit(&mut new_data, pos, b"applied", current_ledger);
+    Ok((new_data, pos))
+}
+
+// ═══════════════════════════════════════════════════════════════════════
+// TESTS — propose / vote / veto / authorize
+// ═══════════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH_A: [u8; CODE_HASH_SIZE] = [0xAA; CODE_HASH_SIZE];
+    const HASH_B: [u8; CODE_HASH_SIZE] = [0xBB; CODE_HASH_SIZE];
+
+    fn account(seed: u8) -> [u8; ACCOUNT_ID_SIZE] {
+        [seed; ACCOUNT_ID_SIZE]
+    }
+
+    /// 3 notaries, 2 upgrade votes, 100-ledger review window.
+    fn upgradeable_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"notary_count=3;threshold=2;upgrade_threshold=2;upgrade_delay_ledgers=100");
+        for (i, seed) in [0x01u8, 0x02, 0x03].iter().enumerate() {
+            let mut hex = [0u8; ACCOUNT_ID_SIZE * 2];
+            encode_hex(&account(*seed), &mut hex);
+            data.extend_from_slice(b";notary_");
+            data.push(b'0' + i as u8);
+            data.push(b'=');
+            data.extend_from_slice(&hex);
+        }
+        data
+    }
+
+    /// Proposed by notary 0 at ledger 1000 and voted by notary 1 at 1010.
+    fn approved_data() -> ([u8; 4096], usize) {
+        let data = upgradeable_data();
+        let (d1, l1) = record_upgrade_proposal(&data, data.len(), &HASH_A, 0, 1000).unwrap();
+        record_upgrade_vote(&d1[..l1], l1, &HASH_A, 1, 1010).unwrap()
+    }
+
+    #[test]
+    fn code_hash_parsing() {
+        let hex = [b'a'; 64];
+        assert_eq!(parse_code_hash(&hex), Some(HASH_A));
+        assert_eq!(parse_code_hash(&hex[..62]), None);
+        assert_eq!(parse_code_hash(&[b'z'; 64]), None);
+    }
+
+    #[test]
+    fn proposal_records_hash_delay_and_vote() {
+        let data = upgradeable_data();
+        let (d1, l1) = record_upgrade_proposal(&data, data.len(), &HASH_A, 2, 1000).unwrap();
+        let pending = &d1[..l1];
+        assert_eq!(find_value(pending, b"pending_code_hash"), Some(&[b'a'; 64][..]));
+        assert_eq!(find_value(pending, b"upgrade_after_ledger"), Some(b"1100" as &[u8]));
+        assert_eq!(find_value(pending, b"upgrade_vote_2"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(pending, b"upgrade_votes"), Some(b"1" as &[u8]));
+        assert_eq!(find_value(pending, b"upgrade_last"), Some(b"proposed" as &[u8]));
+        assert_eq!(find_value(pending, b"upgrade_last_ledger"), Some(b"1000" as &[u8]));
+    }
+
+    #[test]
+    fn default_delay_is_twelve_hours() {
+        let data = b"notary_count=1;upgrade_threshold=1";
+        let (d1, l1) = record_upgrade_proposal(data, data.len(), &HASH_A, 0, 1000).unwrap();
+        assert_eq!(find_value(&d1[..l1], b"upgrade_after_ledger"), Some(b"9640" as &[u8]));
+    }
+
+    #[test]
+    fn only_one_pending_upgrade() {
+        let (d1, l1) = approved_data();
+        assert_eq!(
+            record_upgrade_proposal(&d1[..l1], l1, &HASH_B, 2, 1020).err(),
+            Some(ERR_UPGRADE)
+        );
+    }
+
+    #[test]
+    fn votes_must_name_the_pending_hash() {
+        let data = upgradeable_data();
+        let (d1, l1) = record_upgrade_proposal(&data, data.len(), &HASH_A, 0, 1000).unwrap();
+        assert_eq!(record_upgrade_vote(&d1[..l1], l1, &HASH_B, 1, 1001).err(), Some(ERR_UPGRADE));
+        assert_eq!(record_upgrade_vote(&d1[..l1], l1, &HASH_A, 0, 1001).err(), Some(ERR_ALREADY_APPROVED));
+        assert_eq!(record_upgrade_vote(&data, data.len(), &HASH_A, 1, 1001).err(), Some(ERR_UPGRADE));
+    }
+
+    #[test]
+    fn authorization_needs_quorum_and_delay() {
+        let data = upgradeable_data();
+        let (d1, l1) = record_upgrade_proposal(&data, data.len(), &HASH_A, 0, 1000).unwrap();
+        // One vote of two, even after the window
+        assert_eq!(check_upgrade_authorized(&d1[..l1], &HASH_A, 5000), ERR_NOT_APPROVED);
+
+        let (d2, l2) = approved_data();
+        let approved = &d2[..l2];
+        assert_eq!(check_upgrade_authorized(approved, &HASH_A, 1099), ERR_TOO_EARLY);
+        assert_eq!(check_upgrade_authorized(approved, &HASH_A, 1100), SUCCESS);
+        // Never for a hash nobody voted on
+        assert_eq!(check_upgrade_authorized(approved, &HASH_B, 1100), ERR_UPGRADE);
+        // Nothing pending at all
+        assert_eq!(check_upgrade_authorized(&data, &HASH_A, 1100), ERR_UPGRADE);
+    }
+
+    #[test]
+    fn veto_discards_pending_upgrade() {
+        let (d1, l1) = approved_data();
+        let (d2, l2) = record_upgrade_veto(&d1[..l1], l1, 1050).unwrap();
+        let vetoed = &d2[..l2];
+        assert_eq!(find_value(vetoed, b"pending_code_hash"), None);
+        assert_eq!(find_value(vetoed, b"upgrade_votes"), None);
+        assert_eq!(find_value(vetoed, b"upgrade_vote_0"), None);
+        assert_eq!(find_value(vetoed, b"upgrade_last"), Some(b"vetoed" as &[u8]));
+        assert_eq!(check_upgrade_authorized(vetoed, &HASH_A, 5000), ERR_UPGRADE);
+        // Config survives, and a fresh proposal starts from zero votes
+        assert_eq!(find_value(vetoed, b"upgrade_threshold"), Some(b"2" as &[u8]));
+        let (d3, l3) = record_upgrade_proposal(vetoed, l2, &HASH_B, 1, 1060).unwrap();
+        assert_eq!(find_value(&d3[..l3], b"upgrade_votes"), Some(b"1" as &[u8]));
+
+        assert_eq!(record_upgrade_veto(vetoed, l2, 1051).err(), Some(ERR_UPGRADE));
+    }
+
+    #[test]
+    fn veto_rights_default_to_owner_and_destination() {
+        let data = upgradeable_data();
+        let (owner, destination) = (account(0x10), account(0x20));
+        assert_eq!(check_veto_submitter(&data, &owner, &owner, &destination), Ok(()));
+        assert_eq!(check_veto_submitter(&data, &destination, &owner, &destination), Ok(()));
+        assert_eq!(
+            check_veto_submitter(&data, &account(0x01), &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+
+        let mut notaries = data.clone();
+        notaries.extend_from_slice(b";upgrade_veto_by=notary");
+        assert_eq!(check_veto_submitter(&notaries, &account(0x02), &owner, &destination), Ok(()));
+        assert_eq!(
+            check_veto_submitter(&notaries, &owner, &owner, &destination),
+            Err(ERR_WRONG_ACCOUNT)
+        );
+
+        let mut bad = data;
+        bad.extend_from_slice(b";upgrade_veto_by=auditor");
+        assert_eq!(check_veto_submitter(&bad, &owner, &owner, &destination), Err(ERR_BAD_CONFIG));
+    }
+
+    #[test]
+    fn applied_upgrade_is_recorded() {
+        let (d1, l1) = approved_data();
+        assert_eq!(record_upgrade_applied(&d1[..l1], l1, 1099).err(), Some(ERR_TOO_EARLY));
+
+        let (d2, l2) = record_upgrade_applied(&d1[..l1], l1, 1100).unwrap();
+        let applied = &d2[..l2];
+        assert_eq!(find_value(applied, b"active_code_hash"), Some(&[b'a'; 64][..]));
+        assert_eq!(find_value(applied, b"pending_code_hash"), None);
+        assert_eq!(find_value(applied, b"upgrade_last"), Some(b"applied" as &[u8]));
+        assert_eq!(check_upgrade_authorized(applied, &HASH_A, 2000), ERR_UPGRADE);
+    }
+
+    #[test]
+    fn upgrades_need_valid_threshold() {
+        for data in [
+            b"notary_count=3" as &[u8],
+            b"notary_count=3;upgrade_threshold=0",
+            b"notary_count=3;upgrade_threshold=4",
+            b"upgrade_threshold=1",
+        ] {
+            assert_eq!(
+                record_upgrade_proposal(data, data.len(), &HASH_A, 0, 1).err(),
+                Some(ERR_BAD_CONFIG)
+            );
+        }
+    }
+}